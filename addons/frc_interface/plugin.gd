@@ -1,11 +1,17 @@
 @tool
 extends EditorPlugin
 
+var monitor: Control
+
+
 func _enter_tree():
-	# Initialization of the plugin goes here.
-	pass
+	# Live view of the running game's interface, fed by its status endpoint
+	monitor = ClassDB.instantiate("FRCInterfaceMonitor")
+	add_control_to_dock(DOCK_SLOT_RIGHT_UL, monitor)
 
 
 func _exit_tree():
-	# Clean-up of the plugin goes here.
-	pass
+	if monitor:
+		remove_control_from_docks(monitor)
+		monitor.queue_free()
+		monitor = null
@@ -0,0 +1,220 @@
+use crate::InterfaceCore;
+use godot::classes::CanvasItem;
+use godot::prelude::*;
+use std::time::{Duration, Instant};
+
+// Topic the robot acknowledges a button on when ack_topics has no entry for it
+const DEFAULT_ACK_TOPIC_PREFIX: &str = "/GodotInterface/ack/";
+
+// Half the blink of an action's indicator while its ack is overdue
+const ACK_ERROR_FLASH_PERIOD: Duration = Duration::from_millis(250);
+
+/// Presses of one action the robot hasn't acknowledged yet. Presses made
+/// while one is outstanding join it rather than starting their own timer.
+pub struct PendingAck {
+    // Sequence number of the oldest press waiting, acks for anything before
+    // it are stale
+    first_seq: u64,
+    // Sequence number of the newest press waiting
+    last_seq: u64,
+    deadline: Instant,
+}
+
+/// An indicator flashing because its action's ack is overdue.
+pub struct AckIndicator {
+    item: Gd<CanvasItem>,
+    // Modulate to put back when the error clears
+    base_modulate: Color,
+}
+
+impl InterfaceCore {
+    /// Mark a button as acknowledged by the robot. Acks without a recent
+    /// press are ignored. Without a sequence number this can't tell a
+    /// late ack from a new one, prefer acknowledge_press() where the robot
+    /// echoes press_seq.
+    pub fn acknowledge_button(&mut self, name: GString) {
+        let name = name.to_string();
+        if let Some(seq) = self.pending_acks.get(&name).map(|pending| pending.last_seq) {
+            self.acknowledge_seq(&name, seq);
+        }
+    }
+
+    /// Mark press `seq` of `name`, and everything before it, as
+    /// acknowledged by the robot. Acks older than the presses still
+    /// waiting are dropped.
+    pub fn acknowledge_press(&mut self, name: GString, seq: i64) {
+        self.acknowledge_seq(&name.to_string(), seq.max(0) as u64);
+    }
+
+    fn acknowledge_seq(&mut self, name: &str, seq: u64) {
+        let acked = match self.pending_acks.get(name) {
+            Some(pending) if seq >= pending.first_seq => {
+                self.pending_acks.remove(name);
+                true
+            }
+            _ => false,
+        };
+        // A failure clears on an ack for that press or a later one
+        let cleared = match self.ack_errors.get(name) {
+            Some(failed_seq) if acked || seq >= *failed_seq => {
+                self.clear_ack_error(name);
+                true
+            }
+            _ => false,
+        };
+        if !acked && !cleared {
+            log_debug!("Ignoring stale ack {} for {}", seq, name);
+            return;
+        }
+
+        if acked {
+            if self.haptics_on_ack {
+                self.vibrate(self.haptics_ack_ms);
+            }
+            self.emit("button_acknowledged", &[GString::from(name).to_variant()]);
+        }
+    }
+
+    /// Start or join the ack timer for a press of `name` and tell the robot
+    /// its sequence number.
+    pub fn await_ack(&mut self, name: &str) {
+        let seq = self.next_press_seq;
+        self.next_press_seq += 1;
+
+        let timeout = Duration::from_millis(self.ack_timeout_ms.max(0) as u64);
+        self.pending_acks
+            .entry(name.to_string())
+            .and_modify(|pending| pending.last_seq = seq)
+            .or_insert(PendingAck {
+                first_seq: seq,
+                last_seq: seq,
+                deadline: Instant::now() + timeout,
+            });
+
+        if !self.ack_seq_topic_prefix.is_empty() {
+            let topic = format!("{}{}", self.ack_seq_topic_prefix, name);
+            self.publish_value(topic.into(), seq.to_string().into());
+        }
+    }
+
+    /// Flash `node_path` in ack_error_color while `action` has an
+    /// unanswered press.
+    pub fn bind_ack_indicator(&mut self, action: GString, node_path: NodePath) -> godot::global::Error {
+        let action = action.to_string();
+        if !Self::is_known_action(&action) {
+            log_warn!("Cannot bind an ack indicator to unknown action \"{}\"", action);
+            return godot::global::Error::ERR_INVALID_PARAMETER;
+        }
+        let Some(item) = self.node()
+            .get_node_or_null(&node_path)
+            .and_then(|node| node.try_cast::<CanvasItem>().ok())
+        else {
+            log_warn!("Cannot bind ack indicator for {}: {} is not a CanvasItem", action, node_path);
+            return godot::global::Error::ERR_DOES_NOT_EXIST;
+        };
+
+        self.unbind_ack_indicator(action.as_str().into());
+        let base_modulate = item.get_modulate();
+        self.ack_indicators.insert(action, AckIndicator { item, base_modulate });
+        godot::global::Error::OK
+    }
+
+    pub fn unbind_ack_indicator(&mut self, action: GString) {
+        if let Some(mut indicator) = self.ack_indicators.remove(&action.to_string()) {
+            if indicator.item.is_instance_valid() {
+                indicator.item.set_modulate(indicator.base_modulate);
+            }
+        }
+    }
+
+    /// Stop flashing every overdue ack without waiting for the robot.
+    pub fn clear_ack_errors(&mut self) {
+        let names: Vec<String> = self.ack_errors.keys().cloned().collect();
+        for name in names {
+            self.clear_ack_error(&name);
+        }
+    }
+
+    /// Actions whose last press the robot never acknowledged.
+    pub fn get_ack_errors(&self) -> PackedStringArray {
+        self.ack_errors.keys().map(|name| GString::from(name.as_str())).collect()
+    }
+
+    fn clear_ack_error(&mut self, name: &str) {
+        self.ack_errors.remove(name);
+        if let Some(indicator) = self.ack_indicators.get_mut(name) {
+            if indicator.item.is_instance_valid() {
+                indicator.item.set_modulate(indicator.base_modulate);
+            }
+        }
+    }
+
+    pub fn update_ack_flash(&mut self) {
+        let elapsed = self.ack_flash_started.elapsed();
+        let on = (elapsed.as_millis() / ACK_ERROR_FLASH_PERIOD.as_millis()) % 2 == 0;
+        let error_color = self.ack_error_color;
+        for (name, indicator) in self.ack_indicators.iter_mut() {
+            if !self.ack_errors.contains_key(name) || !indicator.item.is_instance_valid() {
+                continue;
+            }
+            let color = if on { error_color } else { indicator.base_modulate };
+            if indicator.item.get_modulate() != color {
+                indicator.item.set_modulate(color);
+            }
+        }
+    }
+
+    /// Acknowledge whichever button is mapped to `topic`, for bridges that
+    /// forward the robot's ack topics as they update.
+    pub fn acknowledge_topic(&mut self, topic: GString) {
+        if let Some(name) = self.ack_topic_button(&topic.to_string()) {
+            self.acknowledge_button(name.into());
+        }
+    }
+
+    /// Like acknowledge_topic(), with the sequence number the robot echoed.
+    pub fn acknowledge_topic_seq(&mut self, topic: GString, seq: i64) {
+        if let Some(name) = self.ack_topic_button(&topic.to_string()) {
+            self.acknowledge_press(name.into(), seq);
+        }
+    }
+
+    fn ack_topic_button(&self, topic: &str) -> Option<&'static str> {
+        Self::known_actions().find(|name| self.ack_topic(name) == topic)
+    }
+
+    /// Topic the robot publishes the ack for `name` on.
+    pub fn get_ack_topic(&self, name: GString) -> GString {
+        self.ack_topic(&name.to_string()).into()
+    }
+
+    fn ack_topic(&self, name: &str) -> String {
+        match self.ack_topics.get(name) {
+            Some(topic) => topic.to_string(),
+            None => format!("{}{}", DEFAULT_ACK_TOPIC_PREFIX, name),
+        }
+    }
+
+    pub fn expire_pending_acks(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<String> = self
+            .pending_acks
+            .iter()
+            .filter(|(_, pending)| now >= pending.deadline)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in expired {
+            let Some(pending) = self.pending_acks.remove(&name) else {
+                continue;
+            };
+            log_warn!("No acknowledgment for {} from the robot", name);
+            if self.ack_errors.is_empty() {
+                self.ack_flash_started = now;
+            }
+            self.ack_errors.insert(name.clone(), pending.last_seq);
+            self.emit("button_ack_timeout", &[name.to_variant()]);
+            self.emit("button_unacknowledged", &[name.to_variant()]);
+        }
+    }
+}
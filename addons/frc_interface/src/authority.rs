@@ -0,0 +1,291 @@
+use crate::InterfaceCore;
+use godot::classes::web_socket_peer::State;
+use godot::classes::WebSocketPeer;
+use godot::prelude::*;
+use serde_json::json;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::time::{Duration, Instant};
+
+// How long to wait before dialing the other instance again after losing it
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+// How often this instance tells the other one whether it is active
+const AUTHORITY_HEARTBEAT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// What an instance says about itself on every heartbeat.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Heartbeat {
+    pub id: u64,
+    pub priority: i64,
+    pub active: bool,
+}
+
+impl Heartbeat {
+    /// Which of two instances that both claim authority keeps it: the higher
+    /// priority, then the higher id, so both sides reach the same answer.
+    pub fn outranks(&self, other: &Heartbeat) -> bool {
+        (self.priority, self.id) > (other.priority, other.id)
+    }
+}
+
+/// Client side of the authority link: a WebSocket to the other instance's
+/// remote server, carrying this instance's heartbeats and handoff requests.
+/// Its heartbeats come back the same way, through our own remote server.
+///
+/// Polled from the main thread in `process()`, like the remote server.
+pub struct AuthorityLink {
+    peer: Option<Gd<WebSocketPeer>>,
+    url: String,
+    auth_token: String,
+    authenticated: bool,
+    next_attempt: Instant,
+}
+
+impl AuthorityLink {
+    pub fn new() -> Self {
+        Self {
+            peer: None,
+            url: String::new(),
+            auth_token: String::new(),
+            authenticated: false,
+            next_attempt: Instant::now(),
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        !self.url.is_empty()
+    }
+
+    pub fn start(&mut self, url: String, auth_token: String) {
+        self.stop();
+        self.url = url;
+        self.auth_token = auth_token;
+        self.next_attempt = Instant::now();
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(mut peer) = self.peer.take() {
+            peer.close();
+        }
+        self.url.clear();
+        self.authenticated = false;
+    }
+
+    /// Keep the connection going, redialing after a drop, and authenticate
+    /// once it opens. Anything the other side sends back is dropped.
+    pub fn poll(&mut self) {
+        if !self.is_started() {
+            return;
+        }
+
+        if self.peer.is_none() {
+            if Instant::now() < self.next_attempt {
+                return;
+            }
+            let mut peer = WebSocketPeer::new_gd();
+            let result = peer.connect_to_url(self.url.as_str());
+            if result != godot::global::Error::OK {
+                log_warn!("Failed to dial the other instance at {}: {:?}", self.url, result);
+                self.next_attempt = Instant::now() + RECONNECT_DELAY;
+                return;
+            }
+            self.peer = Some(peer);
+            self.authenticated = false;
+        }
+
+        let Some(peer) = self.peer.as_mut() else {
+            return;
+        };
+        peer.poll();
+        match peer.get_ready_state() {
+            State::OPEN => {
+                if !self.authenticated {
+                    let auth = json!({ "cmd": "auth", "token": self.auth_token }).to_string();
+                    peer.send_text(auth.as_str());
+                    self.authenticated = true;
+                    log_info!("Authority link to {} open", self.url);
+                }
+                while peer.get_available_packet_count() > 0 {
+                    peer.get_packet();
+                }
+            }
+            State::CLOSED => {
+                if self.authenticated {
+                    log_warn!("Authority link to {} lost", self.url);
+                }
+                self.peer = None;
+                self.authenticated = false;
+                self.next_attempt = Instant::now() + RECONNECT_DELAY;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn send_heartbeat(&mut self, heartbeat: &Heartbeat) {
+        self.send(json!({
+            "cmd": "heartbeat",
+            "id": heartbeat.id.to_string(),
+            "priority": heartbeat.priority,
+            "active": heartbeat.active,
+        }));
+    }
+
+    /// Ask the other instance to give up authority to this one.
+    pub fn send_handoff_request(&mut self) -> bool {
+        self.send(json!({ "cmd": "request_authority" }))
+    }
+
+    fn send(&mut self, message: serde_json::Value) -> bool {
+        match self.peer.as_mut() {
+            Some(peer) if self.authenticated && peer.get_ready_state() == State::OPEN => {
+                peer.send_text(message.to_string().as_str());
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl InterfaceCore {
+    pub fn start_authority(&mut self) {
+        self.authority_id = RandomState::new().hash_one(std::process::id());
+        self.peer_heartbeat = None;
+        self.peer_last_seen = Instant::now();
+        self.authority_active = !self.authority_start_active;
+        self.set_authority(self.authority_start_active, "started");
+
+        if !self.remote_server_enabled {
+            log_warn!("Authority is on but the remote server is off, the other instance won't be heard");
+        }
+        if self.authority_peer_url.is_empty() {
+            log_warn!("No authority_peer_url, the other instance won't hear this one");
+        } else {
+            self.authority_link
+                .start(self.authority_peer_url.to_string(), self.remote_auth_token.to_string());
+        }
+    }
+
+    fn own_heartbeat(&self) -> Heartbeat {
+        Heartbeat {
+            id: self.authority_id,
+            priority: self.authority_priority,
+            active: self.authority_active,
+        }
+    }
+
+    fn send_heartbeat(&mut self) {
+        let heartbeat = self.own_heartbeat();
+        self.authority_link.send_heartbeat(&heartbeat);
+        self.last_heartbeat_sent = Instant::now();
+    }
+
+    pub fn update_authority(&mut self) {
+        if !self.running {
+            return;
+        }
+        self.authority_link.poll();
+        if self.last_heartbeat_sent.elapsed() >= AUTHORITY_HEARTBEAT_INTERVAL {
+            self.send_heartbeat();
+        }
+
+        // Fail over only from an active (or never heard) instance going
+        // quiet, not from one that released authority on purpose
+        let peer_silent = self.peer_last_seen.elapsed().as_secs_f64() >= self.authority_timeout_secs;
+        let peer_was_active = self.peer_heartbeat.is_none_or(|peer| peer.active);
+        if !self.authority_active && self.authority_failover && peer_silent && peer_was_active {
+            log_warn!(
+                "Nothing from the active instance for {:.1}s, taking authority",
+                self.peer_last_seen.elapsed().as_secs_f64()
+            );
+            self.set_authority(true, "failover");
+            self.send_heartbeat();
+        }
+    }
+
+    pub fn on_peer_heartbeat(&mut self, heartbeat: Heartbeat) {
+        if !self.authority_enabled || heartbeat.id == self.authority_id {
+            return;
+        }
+        self.peer_heartbeat = Some(heartbeat);
+        self.peer_last_seen = Instant::now();
+
+        // Both active: the same comparison runs on both sides, so exactly
+        // one of them stands down
+        if heartbeat.active && self.authority_active {
+            if heartbeat.priority == self.authority_priority {
+                log_warn!("Both instances have authority_priority {}, falling back to instance ids", heartbeat.priority);
+            }
+            if heartbeat.outranks(&self.own_heartbeat()) {
+                log_warn!("Both instances were active, standing down for priority {}", heartbeat.priority);
+                self.set_authority(false, "split_brain");
+                self.send_heartbeat();
+            }
+        }
+    }
+
+    pub fn on_authority_requested(&mut self) {
+        if !self.authority_enabled || !self.authority_active {
+            return;
+        }
+        log_info!("Handing authority to the other instance");
+        self.set_authority(false, "handed_over");
+        self.send_heartbeat();
+    }
+
+    /// Take control authority from the other instance. Works without
+    /// reaching it, e.g. when it has crashed.
+    pub fn request_authority(&mut self) -> bool {
+        if !self.authority_enabled || !self.running {
+            log_warn!("Authority isn't in use, nothing to request");
+            return false;
+        }
+        if !self.authority_link.send_handoff_request() {
+            log_warn!("Other instance unreachable, taking authority without a handoff");
+        }
+        self.set_authority(true, "requested");
+        self.send_heartbeat();
+        true
+    }
+
+    /// Go on standby. The other instance has to request authority (or fail
+    /// over) to take it.
+    pub fn release_authority(&mut self) {
+        if !self.authority_enabled || !self.authority_active {
+            return;
+        }
+        self.set_authority(false, "released");
+        self.send_heartbeat();
+    }
+
+    fn set_authority(&mut self, active: bool, reason: &str) {
+        if self.authority_active == active {
+            return;
+        }
+        self.authority_active = active;
+        log_info!("Authority: {} ({})", if active { "active" } else { "standby" }, reason);
+
+        if !active {
+            self.neutralize_inputs("standby");
+        }
+        if let Some(banner) = self.standby_banner.as_mut().filter(|banner| banner.is_instance_valid()) {
+            banner.set_visible(!active);
+        }
+        self.update_buttons_disabled();
+
+        let args = [active.to_variant(), StringName::from(reason).to_variant()];
+        self.emit("authority_changed", &args);
+    }
+
+    pub fn on_standby(&self) -> bool {
+        self.authority_enabled && !self.authority_active
+    }
+
+    pub fn authority_state(&self) -> &'static str {
+        match (self.authority_enabled, self.authority_active) {
+            (false, _) => "off",
+            (true, true) => "active",
+            (true, false) => "standby",
+        }
+    }
+}
@@ -0,0 +1,662 @@
+use crate::virtual_controller::AXIS_NAMES;
+use crate::{ActionBinding, ActionResult, InputOrigin, InterfaceCore};
+use godot::classes::{BaseButton, ButtonGroup, Range};
+use godot::prelude::*;
+use std::time::{Duration, Instant};
+
+// Shortest gap between two axis updates from bound Range controls
+const AXIS_PUSH_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How an action source reports presses.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ActionSignals {
+    /// Separate press and release signals.
+    PressRelease(&'static str, &'static str),
+    /// BaseButton::toggled, on means held.
+    Toggled,
+}
+
+/// A Range control driving an axis, with the control's value range mapped
+/// onto the axis range.
+pub struct RangeBinding {
+    axis: String,
+    control: Gd<Range>,
+    min: f64,
+    max: f64,
+    // Return to the axis' rest position when a slider drag ends
+    snap_back: bool,
+}
+
+impl RangeBinding {
+    fn is_trigger(&self) -> bool {
+        self.axis.ends_with("_trigger")
+    }
+
+    /// Axis value for a control value, 0 to 1 for triggers and -1 to 1 for sticks.
+    fn axis_value(&self, value: f64) -> f64 {
+        let span = self.max - self.min;
+        let t = if span == 0.0 { 0.0 } else { ((value - self.min) / span).clamp(0.0, 1.0) };
+        if self.is_trigger() {
+            t
+        } else {
+            t * 2.0 - 1.0
+        }
+    }
+
+    /// Control value that puts the axis at rest.
+    fn rest_value(&self) -> f64 {
+        if self.is_trigger() {
+            self.min
+        } else {
+            (self.min + self.max) / 2.0
+        }
+    }
+}
+
+/// Toggle buttons sharing a ButtonGroup, where the pressed member is the one
+/// action held. The buttons themselves are in `action_bindings`.
+pub struct RadioGroup {
+    actions: Vec<String>,
+    selected: Option<String>,
+    // A member toggled off, released in process unless another toggles on first
+    deselect_pending: bool,
+}
+
+impl InterfaceCore {
+    /// How a node drives an action, `None` if it can't.
+    fn action_source_signals(node: &Gd<Node>) -> Option<ActionSignals> {
+        if let Ok(button) = node.clone().try_cast::<BaseButton>() {
+            // Toggle buttons latch, so their state maps onto press and release
+            if button.is_toggle_mode() {
+                Some(ActionSignals::Toggled)
+            } else {
+                Some(ActionSignals::PressRelease("button_down", "button_up"))
+            }
+        } else if node.is_class("TouchScreenButton") {
+            Some(ActionSignals::PressRelease("pressed", "released"))
+        } else {
+            None
+        }
+    }
+
+    /// Every button bound to an action, plus whatever is wrong with the
+    /// configured bindings.
+    pub fn resolve_button_bindings(&self) -> (Vec<(String, Gd<Node>)>, Vec<String>) {
+        let mut bindings = Vec::new();
+        let mut problems = Vec::new();
+
+        let fixed = [
+            ("climb", &self.climb_button),
+            ("zero", &self.zero_button),
+            ("intake", &self.intake_button),
+            ("high", &self.high_button),
+            ("mid", &self.mid_button),
+            ("low", &self.low_button),
+            ("coral", &self.coral_button),
+            ("intake_alga", &self.intake_alga_button),
+            ("drop_alga", &self.drop_alga_button),
+        ];
+        for (action, button) in fixed {
+            let Some(button) = button else {
+                continue;
+            };
+            if Self::action_source_signals(button).is_some() {
+                bindings.push((action.to_string(), button.clone()));
+            } else {
+                problems.push(format!(
+                    "{}_button is a {}, expected a BaseButton or TouchScreenButton",
+                    action,
+                    button.get_class()
+                ));
+            }
+        }
+
+        for (index, entry) in self.button_bindings.iter_shared().enumerate() {
+            let action = entry.get("action").map(|action| action.to_string()).unwrap_or_default();
+            let path = entry
+                .get("button")
+                .and_then(|path| {
+                    path.try_to::<NodePath>()
+                        .ok()
+                        .or_else(|| path.try_to::<GString>().ok().map(|path| NodePath::from(&path)))
+                })
+                .unwrap_or_default();
+
+            if action.is_empty() {
+                problems.push(format!("Button binding {} has no action", index));
+                continue;
+            }
+            if !Self::is_known_action(&action) {
+                problems.push(format!("Button binding {} uses unknown action \"{}\"", index, action));
+                continue;
+            }
+            if path.is_empty() {
+                problems.push(format!("Button binding for {} has no button", action));
+                continue;
+            }
+
+            match self.node().get_node_or_null(&path) {
+                Some(node) if Self::action_source_signals(&node).is_some() => bindings.push((action, node)),
+                Some(_) => problems.push(format!(
+                    "Button binding for {}: {} is not a BaseButton or TouchScreenButton",
+                    action, path
+                )),
+                None => problems.push(format!("Button binding for {}: {} not found", action, path)),
+            }
+        }
+
+        (bindings, problems)
+    }
+
+    pub fn resolve_range_bindings(&self) -> (Vec<RangeBinding>, Vec<String>) {
+        let mut bindings = Vec::new();
+        let mut problems = Vec::new();
+
+        for (index, entry) in self.axis_bindings.iter_shared().enumerate() {
+            let axis = entry.get("axis").map(|axis| axis.to_string()).unwrap_or_default();
+            let path = entry
+                .get("control")
+                .and_then(|path| {
+                    path.try_to::<NodePath>()
+                        .ok()
+                        .or_else(|| path.try_to::<GString>().ok().map(|path| NodePath::from(&path)))
+                })
+                .unwrap_or_default();
+
+            if !AXIS_NAMES.contains(&axis.as_str()) {
+                problems.push(format!("Axis binding {} uses unknown axis \"{}\"", index, axis));
+                continue;
+            }
+            if path.is_empty() {
+                problems.push(format!("Axis binding for {} has no control", axis));
+                continue;
+            }
+
+            let control = match self.node().get_node_or_null(&path).map(|node| node.try_cast::<Range>()) {
+                Some(Ok(control)) => control,
+                Some(Err(_)) => {
+                    problems.push(format!("Axis binding for {}: {} is not a Range control", axis, path));
+                    continue;
+                }
+                None => {
+                    problems.push(format!("Axis binding for {}: {} not found", axis, path));
+                    continue;
+                }
+            };
+
+            let bound = |key: &str, default: f64| {
+                entry.get(key).and_then(|value| value.try_to::<f64>().ok()).unwrap_or(default)
+            };
+            bindings.push(RangeBinding {
+                min: bound("min", control.get_min()),
+                max: bound("max", control.get_max()),
+                snap_back: entry.get("snap_back").and_then(|value| value.try_to::<bool>().ok()).unwrap_or(false),
+                axis,
+                control,
+            });
+        }
+
+        (bindings, problems)
+    }
+
+    pub fn connect_range_bindings(&mut self) {
+        let (bindings, problems) = self.resolve_range_bindings();
+        for problem in problems {
+            log_warn!("{}", problem);
+        }
+
+        let base_obj = self.node();
+        for (index, binding) in bindings.iter().enumerate() {
+            let mut control = binding.control.clone();
+            let index = (index as i64).to_variant();
+
+            let callable = Callable::from_object_method(&base_obj, "on_range_value_changed").bind(&[index.clone()]);
+            control.connect("value_changed", &callable);
+
+            // Only sliders say when they're let go of
+            if binding.snap_back && control.has_signal("drag_ended") {
+                let callable = Callable::from_object_method(&base_obj, "on_range_drag_ended").bind(&[index]);
+                control.connect("drag_ended", &callable);
+            }
+        }
+        self.range_bindings = bindings;
+    }
+
+    pub fn on_range_value_changed(&mut self, value: f64, index: i64) {
+        let Some(binding) = usize::try_from(index).ok().and_then(|index| self.range_bindings.get(index)) else {
+            return;
+        };
+        let axis_value = binding.axis_value(value);
+        self.pending_axes.insert(binding.axis.clone(), axis_value);
+    }
+
+    pub fn on_range_drag_ended(&mut self, _value_changed: bool, index: i64) {
+        let Some(binding) = usize::try_from(index).ok().and_then(|index| self.range_bindings.get(index)) else {
+            return;
+        };
+        // Goes through value_changed like any other move
+        let rest = binding.rest_value();
+        self.call_later(binding.control.clone().upcast(), "set_value", &[rest.to_variant()]);
+    }
+
+    /// Send the latest bound Range values, throttled to AXIS_PUSH_INTERVAL.
+    pub fn push_pending_axes(&mut self) {
+        if self.last_axis_push.elapsed() < AXIS_PUSH_INTERVAL {
+            return;
+        }
+        self.last_axis_push = Instant::now();
+
+        let pending: Vec<(String, f64)> = self.pending_axes.drain().collect();
+        for (axis, value) in pending {
+            match self.set_axis(StringName::from(axis.as_str()), value) {
+                ActionResult::NotConnected | ActionResult::InputsLocked | ActionResult::ControllerNotReady
+                    if self.hold_axes_while_disconnected =>
+                {
+                    // A newer move made in the meantime wins
+                    self.pending_axes.entry(axis).or_insert(value);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn connect_button_signals(&mut self) {
+        let (bindings, problems) = self.resolve_button_bindings();
+        for problem in problems {
+            log_warn!("{}", problem);
+        }
+
+        // Connect all buttons
+        for (action, button) in bindings {
+            let connections = self.connect_action_source(&button, &action);
+            self.action_bindings.push(ActionBinding {
+                action,
+                node: button,
+                connections,
+            });
+        }
+    }
+
+    /// Connect a node's press and release signals to `name`, returning what
+    /// was connected.
+    fn connect_action_source(&self, button: &Gd<Node>, name: &str) -> Vec<(&'static str, Callable)> {
+        let Some(signals) = Self::action_source_signals(button) else {
+            return Vec::new();
+        };
+
+        // Get a reference to this node
+        let base_obj = self.node();
+        let mut btn = button.clone();
+
+        // Create the StringName for the button name once
+        let name_variant = StringName::from(name).to_variant();
+
+        let wanted = match signals {
+            ActionSignals::PressRelease(..) if self.touch_binding_mode && button.is_class("BaseButton") => {
+                let callable = Callable::from_object_method(&base_obj, "on_button_gui_input")
+                    .bind(&[name_variant, button.to_variant()]);
+
+                let result = btn.connect("gui_input", &callable);
+                if result != godot::global::Error::OK {
+                    log_error!("Failed to connect gui_input for {}: {:?}", name, result);
+                    return Vec::new();
+                }
+                return vec![("gui_input", callable)];
+            }
+            ActionSignals::PressRelease(pressed, released) => vec![
+                (pressed, "on_button_pressed"),
+                (released, "on_button_released"),
+            ],
+            ActionSignals::Toggled => vec![("toggled", "on_button_toggled")],
+        };
+
+        let mut connections = Vec::new();
+        for (signal, method) in wanted {
+            let callable = Callable::from_object_method(&base_obj, method).bind(&[name_variant.clone()]);
+
+            let result = btn.connect(signal, &callable);
+            if result != godot::global::Error::OK {
+                log_error!("Failed to connect {} for {}: {:?}", signal, name, result);
+                continue;
+            }
+            connections.push((signal, callable));
+        }
+        connections
+    }
+
+    fn disconnect_action_source(binding: ActionBinding) {
+        let mut node = binding.node;
+        if !node.is_instance_valid() {
+            return;
+        }
+
+        for (signal, callable) in binding.connections {
+            if node.is_connected(signal, &callable) {
+                node.disconnect(signal, &callable);
+            }
+        }
+    }
+
+    /// Drive `action` from the node at `node_path`, replacing any earlier
+    /// binding for it. Holds from the old node are let go first.
+    pub fn bind_button(&mut self, action: GString, node_path: NodePath) -> godot::global::Error {
+        let action = action.to_string();
+        if !Self::is_known_action(&action) {
+            log_warn!("Cannot bind unknown action \"{}\"", action);
+            return godot::global::Error::ERR_INVALID_PARAMETER;
+        }
+
+        let Some(node) = self.node().get_node_or_null(&node_path) else {
+            log_warn!("Cannot bind {}: {} not found", action, node_path);
+            return godot::global::Error::ERR_DOES_NOT_EXIST;
+        };
+        if Self::action_source_signals(&node).is_none() {
+            log_warn!("Cannot bind {}: {} is not a BaseButton or TouchScreenButton", action, node_path);
+            return godot::global::Error::ERR_INVALID_PARAMETER;
+        }
+
+        self.unbind_button(action.as_str().into());
+
+        let connections = self.connect_action_source(&node, &action);
+        if connections.is_empty() {
+            return godot::global::Error::ERR_CANT_CONNECT;
+        }
+        self.action_bindings.push(ActionBinding {
+            action,
+            node,
+            connections,
+        });
+        self.update_buttons_disabled();
+        godot::global::Error::OK
+    }
+
+    /// Disconnect every node bound to `action` and let go of the holds
+    /// they had. It stays down while a key, script or anything else still
+    /// holds it.
+    pub fn unbind_button(&mut self, action: GString) {
+        let action = action.to_string();
+        let (removed, kept): (Vec<_>, Vec<_>) = self
+            .action_bindings
+            .drain(..)
+            .partition(|binding| binding.action == action);
+        self.action_bindings = kept;
+
+        if removed.is_empty() {
+            return;
+        }
+        for binding in removed {
+            // Hand the button back as it was if it's greyed out on our account
+            let node_id = binding.node.instance_id_unchecked();
+            self.buttons_disabled_while_disconnected.retain(|button| {
+                if button.instance_id_unchecked() != node_id {
+                    return true;
+                }
+                if button.is_instance_valid() {
+                    button.clone().set_disabled(false);
+                }
+                false
+            });
+
+            self.action_disabled_buttons.retain(|(_, button)| {
+                if button.instance_id_unchecked() != node_id {
+                    return true;
+                }
+                if button.is_instance_valid() {
+                    button.clone().set_disabled(false);
+                }
+                false
+            });
+
+            Self::disconnect_action_source(binding);
+        }
+
+        self.release_bound_holds(&action);
+    }
+
+    /// Let go of the holds the on-screen nodes had on `action`: its fingers,
+    /// the focused ui_accept hold and its presses. A running tap from a
+    /// confirmation keeps its hold until it ends.
+    fn release_bound_holds(&mut self, action: &str) {
+        for radio in self.radio_groups.values_mut() {
+            if radio.selected.as_deref() == Some(action) {
+                radio.selected = None;
+                radio.deselect_pending = false;
+            }
+        }
+
+        self.touch_pointers.retain(|_, held| *held != action);
+        if self.focus_held.as_deref() == Some(action) {
+            self.focus_held = None;
+        }
+        self.clear_mirrored_press(Some(action));
+
+        let taps = self
+            .pending_taps
+            .iter()
+            .filter(|(tapped, origin, _)| tapped == action && *origin == InputOrigin::Ui)
+            .count() as u32;
+        let held = self.action_holders.count_from(action, InputOrigin::Ui).saturating_sub(taps);
+        for _ in 0..held {
+            self.source_released(action, InputOrigin::Ui);
+        }
+    }
+
+    /// Let go of `action` whatever is holding it.
+    pub fn drop_action(&mut self, action: &str) {
+        for radio in self.radio_groups.values_mut() {
+            if radio.selected.as_deref() == Some(action) {
+                radio.selected = None;
+                radio.deselect_pending = false;
+            }
+        }
+
+        self.pending_taps.retain(|(tapped, ..)| *tapped != action);
+        self.action_holders.remove(action);
+        self.touch_pointers.retain(|_, held| *held != action);
+        if self.focus_held.as_deref() == Some(action) {
+            self.focus_held = None;
+        }
+        self.clear_mirrored_press(Some(action));
+        self.cancel_hold(action);
+        self.release_action(action, InputOrigin::Ui);
+    }
+
+    /// Bind toggle buttons (action → node path) as a radio group: the pressed
+    /// member is the only one of the actions held, and switching members
+    /// changes both buttons in a single controller report. The buttons are
+    /// put in a shared ButtonGroup if they aren't already, and unpressing
+    /// the selected one releases everything.
+    pub fn bind_button_group(&mut self, group: StringName, buttons: Dictionary) -> godot::global::Error {
+        let group = group.to_string();
+
+        let mut members = Vec::new();
+        for (action, path) in buttons.iter_shared() {
+            let action = action.to_string();
+            if !Self::is_known_button(&action) {
+                log_warn!("Cannot bind unknown action \"{}\" in group {}", action, group);
+                return godot::global::Error::ERR_INVALID_PARAMETER;
+            }
+
+            let path = path.try_to::<NodePath>().unwrap_or_default();
+            let Some(button) = self.node()
+                .get_node_or_null(&path)
+                .and_then(|node| node.try_cast::<BaseButton>().ok())
+            else {
+                log_warn!("Cannot bind {} in group {}: {} is not a button", action, group, path);
+                return godot::global::Error::ERR_DOES_NOT_EXIST;
+            };
+            members.push((action, button));
+        }
+
+        self.unbind_button_group(group.as_str().into());
+
+        // Share the group one of them already has, so one set up in the editor is kept
+        let button_group = members
+            .iter()
+            .find_map(|(_, button)| button.get_button_group())
+            .unwrap_or_else(|| {
+                let mut button_group = ButtonGroup::new_gd();
+                button_group.set_allow_unpress(true);
+                button_group
+            });
+
+        let base_obj = self.node();
+        let group_variant = StringName::from(group.as_str()).to_variant();
+        let mut actions = Vec::new();
+        for (action, mut button) in members {
+            self.unbind_button(action.as_str().into());
+
+            button.set_toggle_mode(true);
+            button.set_button_group(&button_group);
+            if button.is_pressed() {
+                button.set_pressed_no_signal(false);
+            }
+
+            let callable = Callable::from_object_method(&base_obj, "on_group_button_toggled")
+                .bind(&[group_variant.clone(), StringName::from(action.as_str()).to_variant()]);
+            let result = button.connect("toggled", &callable);
+            if result != godot::global::Error::OK {
+                log_error!("Failed to connect toggled for {} in group {}: {:?}", action, group, result);
+                continue;
+            }
+
+            self.action_bindings.push(ActionBinding {
+                action: action.clone(),
+                node: button.upcast(),
+                connections: vec![("toggled", callable)],
+            });
+            actions.push(action);
+        }
+
+        self.radio_groups.insert(
+            group,
+            RadioGroup {
+                actions,
+                selected: None,
+                deselect_pending: false,
+            },
+        );
+        self.update_buttons_disabled();
+        godot::global::Error::OK
+    }
+
+    /// Unbind every button in `group`, releasing its selected action.
+    pub fn unbind_button_group(&mut self, group: StringName) {
+        let Some(radio) = self.radio_groups.remove(&group.to_string()) else {
+            return;
+        };
+        for action in radio.actions {
+            self.unbind_button(action.as_str().into());
+        }
+    }
+
+    /// Unpress every button in `group` and release its action.
+    pub fn clear_button_group(&mut self, group: StringName) {
+        let group = group.to_string();
+        let Some(radio) = self.radio_groups.get(&group) else {
+            return;
+        };
+
+        for binding in &self.action_bindings {
+            if !radio.actions.contains(&binding.action) || !binding.node.is_instance_valid() {
+                continue;
+            }
+            if let Ok(mut button) = binding.node.clone().try_cast::<BaseButton>() {
+                button.set_pressed_no_signal(false);
+            }
+        }
+        self.select_in_group(&group, None);
+    }
+
+    /// Selected action of `group`, empty if none.
+    pub fn get_button_group_selection(&self, group: StringName) -> StringName {
+        self.radio_groups
+            .get(&group.to_string())
+            .and_then(|radio| radio.selected.as_deref())
+            .map(StringName::from)
+            .unwrap_or_default()
+    }
+
+    /// A ButtonGroup unpresses the old member before pressing the new one,
+    /// so a toggle off is held back until process to see if a switch follows.
+    pub fn on_group_button_toggled(&mut self, toggled_on: bool, group: StringName, action: StringName) {
+        let group = group.to_string();
+        let action = action.to_string();
+        let Some(radio) = self.radio_groups.get_mut(&group) else {
+            return;
+        };
+
+        if toggled_on {
+            self.select_in_group(&group, Some(action));
+        } else if radio.selected.as_deref() == Some(action.as_str()) {
+            radio.deselect_pending = true;
+        }
+    }
+
+    fn select_in_group(&mut self, group: &str, action: Option<String>) {
+        let Some(radio) = self.radio_groups.get_mut(group) else {
+            return;
+        };
+        radio.deselect_pending = false;
+        let previous = std::mem::replace(&mut radio.selected, action.clone());
+
+        match (previous, action) {
+            (Some(old), Some(new)) if old == new => {}
+            (Some(old), Some(new)) => self.switch_action(&old, &new, InputOrigin::Ui, InputOrigin::Ui),
+            (Some(old), None) => {
+                self.source_released(&old, InputOrigin::Ui);
+            }
+            (None, Some(new)) => {
+                self.source_pressed(&new, InputOrigin::Ui);
+            }
+            (None, None) => {}
+        }
+    }
+
+    /// Release groups whose member was toggled off with nothing taking over.
+    pub fn resolve_group_deselects(&mut self) {
+        let deselected: Vec<String> = self
+            .radio_groups
+            .iter()
+            .filter(|(_, radio)| radio.deselect_pending)
+            .map(|(group, _)| group.clone())
+            .collect();
+        for group in deselected {
+            self.select_in_group(&group, None);
+        }
+    }
+
+    /// Forget group selections after a neutralize, so the screen matches
+    /// the released controller.
+    pub fn unpress_radio_groups(&mut self) {
+        let mut cleared = Vec::new();
+        for radio in self.radio_groups.values_mut() {
+            radio.deselect_pending = false;
+            if radio.selected.take().is_some() {
+                cleared.extend(radio.actions.iter().cloned());
+            }
+        }
+
+        for binding in &self.action_bindings {
+            if !cleared.contains(&binding.action) || !binding.node.is_instance_valid() {
+                continue;
+            }
+            if let Ok(mut button) = binding.node.clone().try_cast::<BaseButton>() {
+                button.set_pressed_no_signal(false);
+            }
+        }
+    }
+
+    /// Current bindings as action → node path, relative to this node.
+    /// Bindings whose node has been freed are left out.
+    pub fn get_bindings(&self) -> Dictionary {
+        let mut bindings = Dictionary::new();
+        for binding in &self.action_bindings {
+            if binding.node.is_instance_valid() {
+                let path = self.node().get_path_to(&binding.node);
+                bindings.set(binding.action.as_str(), path);
+            }
+        }
+        bindings
+    }
+}
@@ -0,0 +1,189 @@
+use crate::ping::connect_tcp;
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Samples kept for the estimate, older ones are dropped
+const MAX_SAMPLES: usize = 16;
+
+// Fewer samples than this and the offset is reported as unknown
+const MIN_SAMPLES: usize = 3;
+
+/// One measurement of how far the robot's clock is ahead of ours.
+#[derive(Clone, Copy, Debug)]
+pub struct ClockSample {
+    pub offset_secs: f64,
+    pub round_trip: Duration,
+}
+
+impl ClockSample {
+    /// Build a sample from the robot's time and when the request went out
+    /// and the answer came back, all in seconds since the Unix epoch.
+    pub fn from_times(robot: f64, sent: f64, received: f64) -> Result<Self, String> {
+        if ![robot, sent, received].iter().all(|time| time.is_finite()) {
+            return Err("times must be finite".into());
+        }
+        let round_trip = (received - sent).max(0.0);
+        Ok(Self {
+            offset_secs: robot - (sent + round_trip / 2.0),
+            round_trip: Duration::try_from_secs_f64(round_trip)
+                .map_err(|_| format!("round trip of {} s is out of range", round_trip))?,
+        })
+    }
+}
+
+/// Combines clock samples into one offset, ignoring slow round trips and
+/// outliers.
+#[derive(Default)]
+pub struct ClockOffsetEstimator {
+    samples: VecDeque<ClockSample>,
+    last_sample: Option<Instant>,
+}
+
+impl ClockOffsetEstimator {
+    pub fn add(&mut self, sample: ClockSample) {
+        if self.samples.len() >= MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        self.last_sample = Some(Instant::now());
+    }
+
+    /// Whether another sample is worth taking.
+    pub fn wants_sample(&self, interval: Duration) -> bool {
+        match self.last_sample {
+            Some(last) => self.samples.len() < MIN_SAMPLES || last.elapsed() >= interval,
+            None => true,
+        }
+    }
+
+    /// Offset in seconds to add to local time to get robot time, `None`
+    /// until there are enough samples.
+    pub fn offset_secs(&self) -> Option<f64> {
+        if self.samples.len() < MIN_SAMPLES {
+            return None;
+        }
+
+        // Fast round trips have the least uncertainty, keep the quicker half
+        let mut samples: Vec<ClockSample> = self.samples.iter().copied().collect();
+        samples.sort_by_key(|sample| sample.round_trip);
+        samples.truncate((samples.len() / 2).max(MIN_SAMPLES));
+
+        // The median ignores the odd sample that hit a scheduling hiccup
+        let mut offsets: Vec<f64> = samples.iter().map(|sample| sample.offset_secs).collect();
+        offsets.sort_by(f64::total_cmp);
+        Some(offsets[offsets.len() / 2])
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Sample the robot's clock from the Date header of its web server.
+pub fn sample_http_date(
+    host: &str,
+    addr: IpAddr,
+    local: Option<IpAddr>,
+    timeout: Duration,
+) -> Result<ClockSample, String> {
+    let http_addr = SocketAddr::new(addr, 80);
+    let mut stream = connect_tcp(http_addr, local, timeout)
+        .map_err(|e| format!("could not reach web server at {}: {}", http_addr, e))?;
+
+    let _ = stream.set_read_timeout(Some(timeout));
+    let _ = stream.set_write_timeout(Some(timeout));
+
+    let head = format!("HEAD / HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n", host);
+    let sent = unix_now();
+    stream
+        .write_all(head.as_bytes())
+        .map_err(|e| format!("could not send HTTP request: {}", e))?;
+
+    let mut response = Vec::new();
+    let mut buffer = [0u8; 1024];
+    let mut received = None;
+    loop {
+        match stream.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => {
+                received.get_or_insert_with(unix_now);
+                response.extend_from_slice(&buffer[..n]);
+                if response.windows(4).any(|w| w == b"\r\n\r\n") || response.len() >= 8192 {
+                    break;
+                }
+            }
+            Err(e) if response.is_empty() => return Err(format!("no HTTP response: {}", e)),
+            Err(_) => break,
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+
+    let received = received.ok_or("no HTTP response")?;
+    let text = String::from_utf8_lossy(&response);
+    let date = text
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim().eq_ignore_ascii_case("date").then(|| value.trim().to_string())
+        })
+        .ok_or("HTTP response has no Date header")?;
+
+    let robot = parse_http_date(&date).ok_or_else(|| format!("could not parse Date header: {}", date))?;
+
+    // The header is truncated to whole seconds, the middle of that second is the best guess
+    ClockSample::from_times(robot + 0.5, sent, received)
+}
+
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// Parse an RFC 7231 date such as "Sun, 06 Nov 1994 08:49:37 GMT" into
+/// seconds since the Unix epoch.
+fn parse_http_date(date: &str) -> Option<f64> {
+    let (_, rest) = date.split_once(", ")?;
+    let mut parts = rest.split_whitespace();
+
+    let day: i64 = parts.next()?.parse().ok()?;
+    let month = match parts.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year: i64 = parts.next()?.parse().ok()?;
+
+    let mut time = parts.next()?.split(':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+
+    if parts.next()? != "GMT" {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    Some((days * 86_400 + hour * 3_600 + minute * 60 + second) as f64)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
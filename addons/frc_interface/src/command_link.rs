@@ -0,0 +1,224 @@
+use crate::ping::connect_tcp;
+use crate::shutdown::{join_by, SHUTDOWN_TIMEOUT};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Commands kept while the coprocessor is unreachable, the oldest are dropped first
+const MAX_QUEUED_COMMANDS: usize = 32;
+
+// How long the worker waits on new commands before checking the socket again
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(250);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// Longest response line accepted before the connection is considered broken
+const MAX_LINE_LENGTH: usize = 64 * 1024;
+
+pub enum CommandEvent {
+    Connected(bool),
+    Sent(String),
+    Failed { command: String, reason: String },
+    Response(String),
+}
+
+/// Persistent newline-delimited JSON link to a coprocessor. Commands are
+/// queued while disconnected and the link reconnects on its own.
+pub struct CommandLink {
+    commands: Option<mpsc::Sender<String>>,
+    events: mpsc::Receiver<CommandEvent>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl CommandLink {
+    pub fn start(host: String, port: u16, timeout: Duration) -> Self {
+        let (command_tx, command_rx) = mpsc::channel::<String>();
+        let (event_tx, event_rx) = mpsc::channel::<CommandEvent>();
+
+        let thread = thread::spawn(move || run(host, port, timeout, command_rx, event_tx));
+
+        Self {
+            commands: Some(command_tx),
+            events: event_rx,
+            thread: Some(thread),
+        }
+    }
+
+    /// Queue one JSON line. Returns false if the link has been shut down.
+    pub fn send(&self, line: String) -> bool {
+        match &self.commands {
+            Some(sender) => sender.send(line).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn poll(&self) -> Vec<CommandEvent> {
+        self.events.try_iter().collect()
+    }
+
+    /// Close the link. A connect in progress is abandoned after
+    /// SHUTDOWN_TIMEOUT rather than waited out.
+    pub fn shutdown(&mut self) {
+        self.shutdown_by(Instant::now() + SHUTDOWN_TIMEOUT);
+    }
+
+    /// Tell the link to close without waiting for it.
+    pub fn begin_shutdown(&mut self) {
+        self.commands = None;
+    }
+
+    /// Close the link, waiting for it until `deadline` at most.
+    pub fn shutdown_by(&mut self, deadline: Instant) {
+        self.begin_shutdown();
+
+        if let Some(handle) = self.thread.take() {
+            if !join_by(handle, deadline) {
+                log_debug!("Command link still connecting, leaving it to time out in the background");
+            }
+        }
+    }
+}
+
+impl Drop for CommandLink {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+struct Connection {
+    stream: TcpStream,
+    pending: Vec<u8>,
+}
+
+fn run(host: String, port: u16, timeout: Duration, commands: mpsc::Receiver<String>, events: mpsc::Sender<CommandEvent>) {
+    let mut queue: VecDeque<String> = VecDeque::new();
+    let mut connection: Option<Connection> = None;
+    let mut reconnect_delay = MIN_RECONNECT_DELAY;
+    let mut next_attempt = Instant::now();
+
+    loop {
+        match commands.recv_timeout(POLL_INTERVAL) {
+            Ok(line) => enqueue(&mut queue, line, &events),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        for line in commands.try_iter() {
+            enqueue(&mut queue, line, &events);
+        }
+
+        if connection.is_none() && Instant::now() >= next_attempt {
+            match connect(&host, port, timeout) {
+                Ok(stream) => {
+                    connection = Some(Connection {
+                        stream,
+                        pending: Vec::new(),
+                    });
+                    reconnect_delay = MIN_RECONNECT_DELAY;
+                    let _ = events.send(CommandEvent::Connected(true));
+                }
+                Err(_) => {
+                    next_attempt = Instant::now() + reconnect_delay;
+                    reconnect_delay = (reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+                }
+            }
+        }
+
+        let Some(conn) = connection.as_mut() else {
+            continue;
+        };
+
+        let healthy = flush_queue(conn, &mut queue, &events) && read_responses(conn, &events);
+        if !healthy {
+            connection = None;
+            next_attempt = Instant::now() + reconnect_delay;
+            let _ = events.send(CommandEvent::Connected(false));
+        }
+    }
+
+    // Anything still queued will never go out
+    for command in queue {
+        let _ = events.send(CommandEvent::Failed {
+            command,
+            reason: "command link stopped".into(),
+        });
+    }
+}
+
+fn enqueue(queue: &mut VecDeque<String>, line: String, events: &mpsc::Sender<CommandEvent>) {
+    if queue.len() >= MAX_QUEUED_COMMANDS {
+        if let Some(command) = queue.pop_front() {
+            let _ = events.send(CommandEvent::Failed {
+                command,
+                reason: "outgoing queue full".into(),
+            });
+        }
+    }
+    queue.push_back(line);
+}
+
+fn connect(host: &str, port: u16, timeout: Duration) -> io::Result<TcpStream> {
+    let addrs: Vec<SocketAddr> = (host, port).to_socket_addrs()?.collect();
+
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, format!("{} did not resolve", host));
+    for addr in addrs {
+        match connect_tcp(addr, None, timeout) {
+            Ok(stream) => {
+                stream.set_nodelay(true)?;
+                stream.set_read_timeout(Some(Duration::from_millis(1)))?;
+                stream.set_write_timeout(Some(timeout))?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = e,
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Write queued commands in order. A command that fails to write stays
+/// queued for the next connection.
+fn flush_queue(conn: &mut Connection, queue: &mut VecDeque<String>, events: &mpsc::Sender<CommandEvent>) -> bool {
+    while let Some(command) = queue.front() {
+        let result = conn
+            .stream
+            .write_all(command.as_bytes())
+            .and_then(|_| conn.stream.write_all(b"\n"));
+
+        if result.is_err() {
+            return false;
+        }
+
+        if let Some(command) = queue.pop_front() {
+            let _ = events.send(CommandEvent::Sent(command));
+        }
+    }
+
+    true
+}
+
+fn read_responses(conn: &mut Connection, events: &mpsc::Sender<CommandEvent>) -> bool {
+    let mut buf = [0u8; 4096];
+    loop {
+        match conn.stream.read(&mut buf) {
+            Ok(0) => return false,
+            Ok(n) => conn.pending.extend_from_slice(&buf[..n]),
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => break,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return false,
+        }
+    }
+
+    while let Some(end) = conn.pending.iter().position(|b| *b == b'\n') {
+        let line: Vec<u8> = conn.pending.drain(..=end).collect();
+        let line = String::from_utf8_lossy(&line).trim().to_string();
+        if !line.is_empty() {
+            let _ = events.send(CommandEvent::Response(line));
+        }
+    }
+
+    conn.pending.len() <= MAX_LINE_LENGTH
+}
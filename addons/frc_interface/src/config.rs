@@ -0,0 +1,126 @@
+use godot::prelude::*;
+
+// Defaults shared with FRCInterfaceBase
+pub const DEFAULT_PING_ADDRESS: &str = "10.45.33.2";
+pub const DEFAULT_PING_PORT: i64 = 22;
+pub const DEFAULT_PING_INTERVAL_SECS: f64 = 15.0;
+pub const DEFAULT_PING_TIMEOUT_MS: i64 = 2000;
+pub const DEFAULT_TEAM_NUMBER: i64 = 4533;
+pub const DEFAULT_ACK_TIMEOUT_MS: i64 = 1000;
+
+/// A node setting that can also come from FRCInterfaceConfig.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConfigField {
+    PingAddress,
+    PingPort,
+    PingInterval,
+    PingTimeout,
+    PersistentConnection,
+    FailuresBeforeDisconnect,
+    SuccessesBeforeConnect,
+    LocalBindAddress,
+    TeamNumber,
+    Services,
+    Devices,
+    ButtonBindings,
+    AckTimeout,
+    ModeMappings,
+    Cooldown,
+}
+
+/// The ConfigFields set on a node, in the scene, by a script or from saved
+/// settings. Those keep the node's value, the rest are taken from the config
+/// resource, whatever value they have.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct ExplicitFields(u32);
+
+impl ExplicitFields {
+    pub fn mark(&mut self, field: ConfigField) {
+        self.0 |= 1 << field as u32;
+    }
+
+    pub fn contains(self, field: ConfigField) -> bool {
+        self.0 & (1 << field as u32) != 0
+    }
+}
+
+/// Interface settings that can be saved as a .tres and shared between scenes.
+#[derive(GodotClass)]
+#[class(base=Resource, init)]
+pub struct FRCInterfaceConfig {
+    #[export]
+    #[init(val = DEFAULT_PING_ADDRESS.into())]
+    pub ping_address: GString,
+
+    #[export]
+    #[init(val = DEFAULT_PING_PORT)]
+    pub ping_port: i64,
+
+    #[export(range = (1.0, 300.0))]
+    #[init(val = DEFAULT_PING_INTERVAL_SECS)]
+    pub ping_interval_secs: f64,
+
+    #[export(range = (100.0, 10000.0))]
+    #[init(val = DEFAULT_PING_TIMEOUT_MS)]
+    pub ping_timeout_ms: i64,
+
+    #[export]
+    pub persistent_connection: bool,
+
+    #[export(range = (1.0, 20.0))]
+    #[init(val = 1)]
+    pub failures_before_disconnect: i64,
+
+    #[export(range = (1.0, 20.0))]
+    #[init(val = 1)]
+    pub successes_before_connect: i64,
+
+    #[export]
+    pub local_bind_address: GString,
+
+    #[export]
+    #[init(val = DEFAULT_TEAM_NUMBER)]
+    pub team_number: i64,
+
+    #[export]
+    pub services: Dictionary,
+
+    #[export]
+    pub devices: Array<Dictionary>,
+
+    #[export]
+    pub button_bindings: Array<Dictionary>,
+
+    #[export(range = (50.0, 10000.0))]
+    #[init(val = DEFAULT_ACK_TIMEOUT_MS)]
+    pub ack_timeout_ms: i64,
+
+    // Mode -> { action -> Xbox button }, merged over BUTTON_MAPPING
+    #[export]
+    pub mode_mappings: Dictionary,
+
+    // Press debounce, action name -> cooldown_secs
+    #[export]
+    pub cooldown: Dictionary,
+
+    base: Base<Resource>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_marked_fields_count_as_set() {
+        let mut explicit = ExplicitFields::default();
+        assert!(!explicit.contains(ConfigField::PingAddress));
+
+        // A value equal to the default still counts once it's set
+        explicit.mark(ConfigField::PingPort);
+        explicit.mark(ConfigField::Cooldown);
+        assert!(explicit.contains(ConfigField::PingPort));
+        assert!(explicit.contains(ConfigField::Cooldown));
+        assert!(!explicit.contains(ConfigField::PingAddress));
+        assert!(!explicit.contains(ConfigField::ModeMappings));
+    }
+}
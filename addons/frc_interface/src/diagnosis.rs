@@ -0,0 +1,56 @@
+use crate::ping::connect_tcp;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+// The radio's web configuration page, answered by every FRC radio firmware
+const RADIO_PORT: u16 = 80;
+
+/// Radio address for a team, 10.TE.AM.1.
+pub fn radio_address_for_team(team: u32) -> Option<Ipv4Addr> {
+    if team == 0 || team > 25_599 {
+        return None;
+    }
+
+    Some(Ipv4Addr::new(10, (team / 100) as u8, (team % 100) as u8, 1))
+}
+
+/// Whether `local` is on the same /24 as the radio.
+pub fn same_subnet(local: Ipv4Addr, radio: Ipv4Addr) -> bool {
+    local.octets()[..3] == radio.octets()[..3]
+}
+
+/// One-off reachability check of the radio on a background thread.
+pub struct RadioProbe {
+    result: mpsc::Receiver<bool>,
+}
+
+impl RadioProbe {
+    pub fn start(radio: Ipv4Addr, local: Option<IpAddr>, timeout: Duration) -> Self {
+        let (result_tx, result_rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let addr = SocketAddr::new(IpAddr::V4(radio), RADIO_PORT);
+
+            // A refusal still means something answered at that address
+            let reachable = match connect_tcp(addr, local, timeout) {
+                Ok(_) => true,
+                Err(e) => e.kind() == io::ErrorKind::ConnectionRefused,
+            };
+            let _ = result_tx.send(reachable);
+        });
+
+        Self { result: result_rx }
+    }
+
+    /// The result once the probe has finished, a dead probe counts as unreachable.
+    pub fn poll(&self) -> Option<bool> {
+        match self.result.try_recv() {
+            Ok(reachable) => Some(reachable),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(false),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::ping::{connect_tcp, verify_address, Verification};
+use crate::shutdown::{join_by, SHUTDOWN_TIMEOUT};
+use std::collections::VecDeque;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub struct DiscoveryConfig {
+    /// Local IPv4 addresses whose /24 gets scanned (the address itself is skipped).
+    pub local_addresses: Vec<Ipv4Addr>,
+    pub ports: Vec<u16>,
+    pub timeout: Duration,
+    /// Upper bound on connection attempts in flight at once.
+    pub concurrency: usize,
+    /// Upper bound on new connection attempts per second.
+    pub attempts_per_sec: u32,
+    pub bind_address: Option<IpAddr>,
+    pub verification: Option<Verification>,
+}
+
+pub enum DiscoveryEvent {
+    Found { addr: SocketAddr, verified: bool },
+    Finished { cancelled: bool },
+}
+
+/// Scans the local subnets for the robot on a background thread.
+pub struct Discovery {
+    cancel: Arc<AtomicBool>,
+    events: mpsc::Receiver<DiscoveryEvent>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Discovery {
+    pub fn start(config: DiscoveryConfig) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (event_tx, event_rx) = mpsc::channel();
+
+        let thread_cancel = cancel.clone();
+        let thread = thread::spawn(move || run(config, thread_cancel, event_tx));
+
+        Self {
+            cancel,
+            events: event_rx,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn poll(&self) -> Vec<DiscoveryEvent> {
+        self.events.try_iter().collect()
+    }
+
+    /// Ask the scan to stop. Doesn't wait, a `Finished` event follows once
+    /// the attempts in flight have given up.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    /// Stop the scan, waiting for it until `deadline` at most.
+    pub fn shutdown_by(&mut self, deadline: Instant) {
+        self.cancel();
+
+        // Attempts in flight can take a whole timeout to give up
+        if let Some(handle) = self.thread.take() {
+            if !join_by(handle, deadline) {
+                log_debug!("Discovery attempts still in flight, leaving them to time out in the background");
+            }
+        }
+    }
+}
+
+impl Drop for Discovery {
+    fn drop(&mut self) {
+        self.shutdown_by(Instant::now() + SHUTDOWN_TIMEOUT);
+    }
+}
+
+fn run(config: DiscoveryConfig, cancel: Arc<AtomicBool>, events: mpsc::Sender<DiscoveryEvent>) {
+    let mut targets = VecDeque::new();
+    for local in &config.local_addresses {
+        let [a, b, c, own] = local.octets();
+        for host in 1..=254u8 {
+            if host == own {
+                continue;
+            }
+            for port in &config.ports {
+                targets.push_back(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(a, b, c, host)), *port));
+            }
+        }
+    }
+
+    let targets = Mutex::new(targets);
+    let interval = Duration::from_secs(1) / config.attempts_per_sec.max(1);
+    let next_slot = Mutex::new(Instant::now());
+
+    thread::scope(|scope| {
+        for _ in 0..config.concurrency.max(1) {
+            scope.spawn(|| {
+                loop {
+                    if cancel.load(Ordering::SeqCst) {
+                        return;
+                    }
+
+                    let Some(addr) = targets.lock().ok().and_then(|mut t| t.pop_front()) else {
+                        return;
+                    };
+
+                    // Rate limit across all workers so the scan can't flood the radio
+                    let wait = {
+                        let Ok(mut slot) = next_slot.lock() else {
+                            return;
+                        };
+                        let now = Instant::now();
+                        let start = (*slot).max(now);
+                        *slot = start + interval;
+                        start - now
+                    };
+                    if !wait.is_zero() {
+                        thread::sleep(wait);
+                    }
+
+                    if connect_tcp(addr, config.bind_address, config.timeout).is_err() {
+                        continue;
+                    }
+
+                    let verified = match &config.verification {
+                        Some(verification) => {
+                            verify_address(addr, config.bind_address, config.timeout, verification).is_ok()
+                        }
+                        None => false,
+                    };
+
+                    let _ = events.send(DiscoveryEvent::Found { addr, verified });
+                }
+            });
+        }
+    });
+
+    let _ = events.send(DiscoveryEvent::Finished {
+        cancelled: cancel.load(Ordering::SeqCst),
+    });
+}
@@ -0,0 +1,120 @@
+use std::net::Ipv6Addr;
+
+/// Split `host`, `host:port`, `[v6]`, `[v6]:port` or a bare IPv6 address
+/// into host and optional port.
+pub fn parse_endpoint(text: &str) -> Result<(String, Option<u16>), String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("endpoint is empty".into());
+    }
+
+    let (host, port) = if let Some(rest) = text.strip_prefix('[') {
+        let Some((host, after)) = rest.split_once(']') else {
+            return Err(format!("missing ']' in {}", text));
+        };
+
+        let port = match after {
+            "" => None,
+            _ => match after.strip_prefix(':') {
+                Some(port) => Some(port),
+                None => return Err(format!("unexpected text after ']' in {}", text)),
+            },
+        };
+
+        if host.parse::<Ipv6Addr>().is_err() {
+            return Err(format!("{} is not an IPv6 address", host));
+        }
+        (host, port)
+    } else if text.matches(':').count() > 1 {
+        // More than one colon only makes sense as a bare IPv6 address
+        if text.parse::<Ipv6Addr>().is_err() {
+            return Err(format!("{} is not a valid address, use [addr]:port for IPv6", text));
+        }
+        (text, None)
+    } else {
+        match text.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (text, None),
+        }
+    };
+
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return Err(format!("invalid host in {}", text));
+    }
+
+    let port = match port {
+        Some(port) => match port.parse::<u16>() {
+            Ok(port) if port != 0 => Some(port),
+            _ => return Err(format!("invalid port in {}", text)),
+        },
+        None => None,
+    };
+
+    Ok((host.to_string(), port))
+}
+
+/// Join host and port, adding brackets around IPv6 addresses.
+pub fn format_endpoint(host: &str, port: i64) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(host: &str, port: Option<u16>) -> Result<(String, Option<u16>), String> {
+        Ok((host.to_string(), port))
+    }
+
+    #[test]
+    fn host_with_and_without_port() {
+        assert_eq!(parse_endpoint("10.45.33.2"), parsed("10.45.33.2", None));
+        assert_eq!(parse_endpoint(" roborio-4533-frc.local:1735 "), parsed("roborio-4533-frc.local", Some(1735)));
+        assert_eq!(parse_endpoint("10.45.33.2:65535"), parsed("10.45.33.2", Some(65535)));
+    }
+
+    #[test]
+    fn ipv6_bracketed_or_bare() {
+        assert_eq!(parse_endpoint("[fe80::1]"), parsed("fe80::1", None));
+        assert_eq!(parse_endpoint("[fe80::1]:5810"), parsed("fe80::1", Some(5810)));
+        assert_eq!(parse_endpoint("fe80::1"), parsed("fe80::1", None));
+        assert_eq!(parse_endpoint("::1"), parsed("::1", None));
+    }
+
+    #[test]
+    fn bad_endpoints_are_refused() {
+        for text in [
+            "",
+            "   ",
+            "10.45.33.2:",
+            ":1735",
+            "10.45.33.2:0",
+            "10.45.33.2:65536",
+            "10.45.33.2:-1",
+            "10.45.33.2:port",
+            "robo rio:1735",
+            "[fe80::1",
+            "[fe80::1]1735",
+            "[fe80::1]:",
+            "[fe80::1]:70000",
+            "[10.45.33.2]:1735",
+            "fe80::1:1735:x",
+        ] {
+            assert!(parse_endpoint(text).is_err(), "{:?} was accepted", text);
+        }
+    }
+
+    #[test]
+    fn formatted_endpoints_parse_back() {
+        assert_eq!(format_endpoint("10.45.33.2", 1735), "10.45.33.2:1735");
+        assert_eq!(format_endpoint("fe80::1", 1735), "[fe80::1]:1735");
+
+        for (host, port) in [("roborio-4533-frc.local", 1735), ("fe80::1", 5810)] {
+            assert_eq!(parse_endpoint(&format_endpoint(host, port)), parsed(host, Some(port as u16)));
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::shutdown::{join_by, SHUTDOWN_TIMEOUT};
+use godot::prelude::*;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+
+/// Appends JSON lines to a log file from a background thread, so disk
+/// writes never happen on the main thread.
+pub struct EventLog {
+    sender: Option<mpsc::Sender<String>>,
+    thread: Option<thread::JoinHandle<()>>,
+    path: PathBuf,
+}
+
+impl EventLog {
+    /// Create `dir` if needed and start a new log file inside it.
+    pub fn open(dir: PathBuf, file_name: &str) -> Self {
+        let path = dir.join(file_name);
+        let (sender, receiver) = mpsc::channel::<String>();
+
+        let file_path = path.clone();
+        let thread = thread::spawn(move || {
+            let mut writer = match open_file(&dir, &file_path) {
+                Ok(file) => BufWriter::new(file),
+                Err(e) => {
+                    log_error!("Failed to open log file {}: {}", file_path.display(), e);
+                    return;
+                }
+            };
+
+            while let Ok(line) = receiver.recv() {
+                // Flush every line so a crash doesn't take the last events with it
+                let result = writeln!(writer, "{}", line).and_then(|_| writer.flush());
+                if let Err(e) = result {
+                    log_error!("Failed to write log file {}: {}", file_path.display(), e);
+                    return;
+                }
+            }
+        });
+
+        Self {
+            sender: Some(sender),
+            thread: Some(thread),
+            path,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&self, entry: serde_json::Value) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(entry.to_string());
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.close_by(Instant::now() + SHUTDOWN_TIMEOUT);
+    }
+
+    /// Tell the writer to finish what's queued without waiting for it.
+    pub fn begin_close(&mut self) {
+        // Dropping the sender lets the writer drain what's queued and exit
+        self.sender = None;
+    }
+
+    /// Close the log, waiting for the writer until `deadline` at most.
+    pub fn close_by(&mut self, deadline: Instant) {
+        self.begin_close();
+
+        if let Some(handle) = self.thread.take() {
+            if !join_by(handle, deadline) {
+                log_warn!("Log file {} still writing, the last entries may be missing", self.path.display());
+            }
+        }
+    }
+}
+
+impl Drop for EventLog {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn open_file(dir: &Path, path: &Path) -> std::io::Result<File> {
+    fs::create_dir_all(dir)?;
+    OpenOptions::new().create(true).append(true).open(path)
+}
@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// How many transitions are remembered
+const MAX_TRANSITIONS: usize = 100;
+
+pub struct Transition {
+    /// Seconds since the Unix epoch when the change happened.
+    pub wall_time: f64,
+    /// Seconds since tracking started (or was last reset).
+    pub monotonic_secs: f64,
+    pub connected: bool,
+    /// How long the state that just ended lasted.
+    pub previous_duration: Duration,
+}
+
+/// Tracks how long the link has been up and down during a session.
+pub struct ConnectionHistory {
+    started: Instant,
+    connected: bool,
+    state_since: Instant,
+    total_connected: Duration,
+    total_disconnected: Duration,
+    transitions: VecDeque<Transition>,
+}
+
+impl ConnectionHistory {
+    pub fn new(connected: bool) -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            connected,
+            state_since: now,
+            total_connected: Duration::ZERO,
+            total_disconnected: Duration::ZERO,
+            transitions: VecDeque::new(),
+        }
+    }
+
+    /// Record a state change and return how long the previous state lasted.
+    pub fn record(&mut self, connected: bool) -> Duration {
+        let now = Instant::now();
+        let ended = now - self.state_since;
+
+        if self.connected {
+            self.total_connected += ended;
+        } else {
+            self.total_disconnected += ended;
+        }
+
+        self.connected = connected;
+        self.state_since = now;
+
+        if self.transitions.len() >= MAX_TRANSITIONS {
+            self.transitions.pop_front();
+        }
+        self.transitions.push_back(Transition {
+            wall_time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |d| d.as_secs_f64()),
+            monotonic_secs: (now - self.started).as_secs_f64(),
+            connected,
+            previous_duration: ended,
+        });
+
+        ended
+    }
+
+    pub fn time_in_current_state(&self) -> Duration {
+        self.state_since.elapsed()
+    }
+
+    /// Total time connected, including the current stretch.
+    pub fn total_uptime(&self) -> Duration {
+        if self.connected {
+            self.total_connected + self.time_in_current_state()
+        } else {
+            self.total_connected
+        }
+    }
+
+    /// Total time disconnected, including the current stretch.
+    pub fn total_downtime(&self) -> Duration {
+        if self.connected {
+            self.total_disconnected
+        } else {
+            self.total_disconnected + self.time_in_current_state()
+        }
+    }
+
+    pub fn transitions(&self) -> impl Iterator<Item = &Transition> {
+        self.transitions.iter()
+    }
+
+    /// Start over from the current state, e.g. between matches.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.connected);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use crate::InputOrigin;
+
+/// How many sources of each origin hold each action: on-screen buttons,
+/// keys, remote clients, taps, macros and sequences. An action is let go
+/// once the last of them does, and a source can only let go of its own
+/// holds, so a key released while a finger is still on the button changes
+/// nothing.
+#[derive(Default)]
+pub struct HoldCounts {
+    holders: HashMap<String, HashMap<InputOrigin, u32>>,
+}
+
+impl HoldCounts {
+    /// Count a hold of `action` by `origin`. Returns whether it's the first
+    /// hold of the action, i.e. whether it should be pressed.
+    pub fn hold(&mut self, action: &str, origin: InputOrigin) -> bool {
+        let first = !self.is_held(action);
+        *self.holders.entry(action.to_string()).or_default().entry(origin).or_insert(0) += 1;
+        first
+    }
+
+    /// Let go of one hold of `action` by `origin`. Returns whether nothing
+    /// holds the action any more, i.e. whether it should be released. A
+    /// release from an origin that holds nothing takes no one else's hold.
+    pub fn release(&mut self, action: &str, origin: InputOrigin) -> bool {
+        let Some(origins) = self.holders.get_mut(action) else {
+            return true;
+        };
+        if let Some(count) = origins.get_mut(&origin) {
+            *count -= 1;
+            if *count == 0 {
+                origins.remove(&origin);
+            }
+        }
+        if !origins.is_empty() {
+            return false;
+        }
+        self.holders.remove(action);
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.holders.is_empty()
+    }
+
+    pub fn is_held(&self, action: &str) -> bool {
+        self.holders.contains_key(action)
+    }
+
+    /// How many holds `action` has, from every origin.
+    pub fn count(&self, action: &str) -> u32 {
+        self.holders.get(action).map_or(0, |origins| origins.values().sum())
+    }
+
+    /// How many holds `action` has from `origin`.
+    pub fn count_from(&self, action: &str, origin: InputOrigin) -> u32 {
+        self.holders
+            .get(action)
+            .and_then(|origins| origins.get(&origin))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Forget every hold of `action`, e.g. when it's dropped whatever holds it.
+    pub fn remove(&mut self, action: &str) {
+        self.holders.remove(action);
+    }
+
+    pub fn clear(&mut self) {
+        self.holders.clear();
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &String> {
+        self.holders.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn released_once_the_last_source_lets_go() {
+        let mut holds = HoldCounts::default();
+        assert!(holds.hold("intake", InputOrigin::Keyboard));
+        assert!(!holds.hold("intake", InputOrigin::Ui));
+        assert!(!holds.hold("intake", InputOrigin::Ui));
+        assert_eq!(holds.count("intake"), 3);
+
+        assert!(!holds.release("intake", InputOrigin::Ui));
+        assert!(!holds.release("intake", InputOrigin::Keyboard));
+        assert!(holds.release("intake", InputOrigin::Ui));
+        assert!(!holds.is_held("intake"));
+    }
+
+    #[test]
+    fn a_release_only_takes_its_own_hold() {
+        let mut holds = HoldCounts::default();
+        holds.hold("climb", InputOrigin::Keyboard);
+
+        // A remote client that never pressed it can't let go for the key
+        assert!(!holds.release("climb", InputOrigin::Remote));
+        assert!(!holds.release("climb", InputOrigin::Remote));
+        assert_eq!(holds.count("climb"), 1);
+        assert!(holds.release("climb", InputOrigin::Keyboard));
+    }
+
+    #[test]
+    fn letting_go_of_one_origin_keeps_the_others() {
+        let mut holds = HoldCounts::default();
+        holds.hold("climb", InputOrigin::Ui);
+        holds.hold("climb", InputOrigin::Ui);
+        holds.hold("climb", InputOrigin::Keyboard);
+        assert_eq!(holds.count_from("climb", InputOrigin::Ui), 2);
+
+        // The on-screen button is unbound: only its two holds go
+        for _ in 0..holds.count_from("climb", InputOrigin::Ui) {
+            assert!(!holds.release("climb", InputOrigin::Ui));
+        }
+        assert_eq!(holds.count_from("climb", InputOrigin::Ui), 0);
+        assert!(holds.is_held("climb"), "the key still holds it");
+    }
+
+    #[test]
+    fn releasing_something_nothing_holds_releases_it() {
+        let mut holds = HoldCounts::default();
+        assert!(holds.release("coral", InputOrigin::Script));
+        assert!(!holds.is_held("coral"));
+    }
+
+    #[test]
+    fn release_after_clear_lets_go() {
+        let mut holds = HoldCounts::default();
+        assert!(holds.hold("intake", InputOrigin::Ui));
+
+        // Neutralizing clears the holds mid-press, then the operator lets go
+        holds.clear();
+        assert!(holds.release("intake", InputOrigin::Ui));
+        assert!(holds.hold("intake", InputOrigin::Ui), "the next press isn't a fresh one");
+    }
+
+    #[test]
+    fn remove_drops_every_hold() {
+        let mut holds = HoldCounts::default();
+        holds.hold("algae", InputOrigin::Ui);
+        holds.hold("algae", InputOrigin::Sequence);
+        holds.hold("coral", InputOrigin::Ui);
+
+        holds.remove("algae");
+        assert!(!holds.is_held("algae"));
+        assert!(holds.hold("algae", InputOrigin::Ui));
+        assert_eq!(holds.actions().count(), 2);
+    }
+}
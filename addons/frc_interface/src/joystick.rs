@@ -0,0 +1,194 @@
+use crate::{FRCInterface2D, FRCInterfaceBase, EMULATED_MOUSE_DEVICE, MOUSE_POINTER};
+use godot::classes::{
+    Control, Engine, IControl, InputEvent, InputEventMouseButton, InputEventMouseMotion, InputEventScreenDrag,
+    InputEventScreenTouch, Texture2D,
+};
+use godot::global::MouseButton;
+use godot::prelude::*;
+
+/// Draggable on-screen thumbstick that drives an axis pair on an
+/// FRCInterfaceBase or FRCInterface2D. Each stick follows its own finger, so several can be
+/// used at once.
+#[derive(GodotClass)]
+#[class(tool, base=Control)]
+pub struct FRCVirtualJoystick {
+    #[export]
+    interface: NodePath,
+
+    #[export]
+    #[init(val = "left_x".into())]
+    x_axis: StringName,
+
+    #[export]
+    #[init(val = "left_y".into())]
+    y_axis: StringName,
+
+    #[export]
+    base_texture: Option<Gd<Texture2D>>,
+
+    #[export]
+    knob_texture: Option<Gd<Texture2D>>,
+
+    /// How far the knob travels from the center, in pixels.
+    #[export(range = (8.0, 512.0))]
+    #[init(val = 64.0)]
+    radius: f32,
+
+    #[export(range = (4.0, 256.0))]
+    #[init(val = 24.0)]
+    knob_radius: f32,
+
+    #[export]
+    #[init(val = Color::from_rgba(0.5, 0.5, 0.5, 0.4))]
+    base_color: Color,
+
+    #[export]
+    #[init(val = Color::from_rgba(0.9, 0.9, 0.9, 0.8))]
+    knob_color: Color,
+
+    // Touch index being followed, MOUSE_POINTER for the mouse
+    touch: Option<i32>,
+
+    /// Current position, -1 to 1 on each axis with down positive.
+    #[var(get)]
+    value: Vector2,
+
+    base: Base<Control>,
+}
+
+#[godot_api]
+impl IControl for FRCVirtualJoystick {
+    fn input(&mut self, event: Gd<InputEvent>) {
+        if Engine::singleton().is_editor_hint() {
+            return;
+        }
+
+        // Presses have to start on the stick, drags and releases are followed
+        // anywhere until the finger lifts
+        let handled = if let Ok(touch) = event.clone().try_cast::<InputEventScreenTouch>() {
+            self.touch_changed(touch.get_index(), touch.is_pressed(), touch.get_position())
+        } else if let Ok(drag) = event.clone().try_cast::<InputEventScreenDrag>() {
+            self.touch_moved(drag.get_index(), drag.get_position())
+        } else if let Ok(click) = event.clone().try_cast::<InputEventMouseButton>() {
+            click.get_device() != EMULATED_MOUSE_DEVICE
+                && click.get_button_index() == MouseButton::LEFT
+                && self.touch_changed(MOUSE_POINTER, click.is_pressed(), click.get_position())
+        } else if let Ok(motion) = event.try_cast::<InputEventMouseMotion>() {
+            motion.get_device() != EMULATED_MOUSE_DEVICE && self.touch_moved(MOUSE_POINTER, motion.get_position())
+        } else {
+            false
+        };
+
+        if handled {
+            if let Some(mut viewport) = self.base().get_viewport() {
+                viewport.set_input_as_handled();
+            }
+        }
+    }
+
+    fn draw(&mut self) {
+        let center = self.base().get_size() / 2.0;
+        let knob = center + self.value * self.radius;
+
+        match self.base_texture.clone() {
+            Some(texture) => self.draw_texture_centered(&texture, center, self.radius),
+            None => {
+                let (radius, color) = (self.radius, self.base_color);
+                self.base_mut().draw_circle(center, radius, color);
+            }
+        }
+        match self.knob_texture.clone() {
+            Some(texture) => self.draw_texture_centered(&texture, knob, self.knob_radius),
+            None => {
+                let (radius, color) = (self.knob_radius, self.knob_color);
+                self.base_mut().draw_circle(knob, radius, color);
+            }
+        }
+    }
+
+    fn exit_tree(&mut self) {
+        if self.touch.take().is_some() {
+            self.set_value(Vector2::ZERO);
+        }
+    }
+}
+
+#[godot_api]
+impl FRCVirtualJoystick {
+    #[signal]
+    fn stick_moved(x: f64, y: f64);
+
+    fn touch_changed(&mut self, index: i32, pressed: bool, position: Vector2) -> bool {
+        if pressed {
+            if self.touch.is_some() || !self.base().get_global_rect().contains_point(position) {
+                return false;
+            }
+            self.touch = Some(index);
+            self.follow(position);
+            true
+        } else if self.touch == Some(index) {
+            // Snap back to center
+            self.touch = None;
+            self.set_value(Vector2::ZERO);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn touch_moved(&mut self, index: i32, position: Vector2) -> bool {
+        if self.touch != Some(index) {
+            return false;
+        }
+        self.follow(position);
+        true
+    }
+
+    fn follow(&mut self, position: Vector2) {
+        let local = self.base().get_global_transform_with_canvas().affine_inverse() * position;
+        let offset = (local - self.base().get_size() / 2.0).limit_length(Some(self.radius));
+        self.set_value(offset / self.radius);
+    }
+
+    fn set_value(&mut self, value: Vector2) {
+        if value == self.value {
+            return;
+        }
+
+        self.value = value;
+        self.base_mut().queue_redraw();
+        self.push_to_interface();
+
+        let args = [(value.x as f64).to_variant(), (value.y as f64).to_variant()];
+        self.base_mut().emit_signal("stick_moved", &args);
+    }
+
+    fn push_to_interface(&self) {
+        if self.interface.is_empty() {
+            return;
+        }
+
+        let Some(node) = self.base().get_node_or_null(&self.interface) else {
+            log_warn!("Virtual joystick interface {} not found", self.interface);
+            return;
+        };
+        let (x, y) = (self.value.x as f64, self.value.y as f64);
+        if let Ok(mut interface) = node.clone().try_cast::<FRCInterfaceBase>() {
+            let mut interface = interface.bind_mut();
+            interface.set_axis(self.x_axis.clone(), x);
+            interface.set_axis(self.y_axis.clone(), y);
+        } else if let Ok(mut interface) = node.try_cast::<FRCInterface2D>() {
+            let mut interface = interface.bind_mut();
+            interface.set_axis(self.x_axis.clone(), x);
+            interface.set_axis(self.y_axis.clone(), y);
+        } else {
+            log_warn!("Virtual joystick interface {} is not an FRC interface node", self.interface);
+        }
+    }
+
+    fn draw_texture_centered(&mut self, texture: &Gd<Texture2D>, center: Vector2, radius: f32) {
+        let size = Vector2::splat(radius * 2.0);
+        let rect = Rect2::new(center - size / 2.0, size);
+        self.base_mut().draw_texture_rect(texture, rect, false);
+    }
+}
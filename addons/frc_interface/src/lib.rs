@@ -1,246 +1,7401 @@
+#[macro_use]
+mod logging;
+
+mod acks;
+mod authority;
+mod bindings;
+mod clock;
+mod command_link;
+mod config;
+mod diagnosis;
+mod discovery;
+mod endpoint;
+mod event_log;
+mod history;
+mod holds;
+mod joystick;
+mod match_report;
+mod match_session;
+mod match_timer;
+mod monitor;
+mod ping;
+mod remote_server;
+mod scouting;
+mod sequence;
+mod services;
+mod shutdown;
+mod sim_output;
+mod status_server;
 mod virtual_controller;
 
-use std::ops::DerefMut;
-use std::net::TcpStream;
-use std::time::{Duration, Instant};
-use std::io::ErrorKind;
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::io::ErrorKind;
+use std::marker::PhantomData;
+
+use acks::{AckIndicator, PendingAck};
+use authority::{AuthorityLink, Heartbeat};
+use bindings::{RadioGroup, RangeBinding};
+use clock::{ClockOffsetEstimator, ClockSample};
+use command_link::{CommandEvent, CommandLink};
+use config::{
+    ConfigField, ExplicitFields, FRCInterfaceConfig, DEFAULT_ACK_TIMEOUT_MS, DEFAULT_PING_ADDRESS,
+    DEFAULT_PING_INTERVAL_SECS, DEFAULT_PING_PORT, DEFAULT_PING_TIMEOUT_MS, DEFAULT_TEAM_NUMBER,
+};
+use diagnosis::{radio_address_for_team, same_subnet, RadioProbe};
+use discovery::{Discovery, DiscoveryConfig, DiscoveryEvent};
+use endpoint::{format_endpoint, parse_endpoint};
+use event_log::EventLog;
+use godot::classes::display_server::WindowMode;
+use godot::global::MouseButton;
+use godot::meta::PropertyHintInfo;
+use history::ConnectionHistory;
+use holds::HoldCounts;
+use logging::LogLevel;
+use match_report::{ReportWriter, WriteKind};
+use match_timer::{MatchPhase, MatchTimer};
+use godot::{classes::{
+    AudioStream, AudioStreamPlayer, BaseButton, ButtonGroup, CanvasItem, ConfigFile, ConfirmationDialog, DisplayServer,
+    Engine, Input, InputEvent, InputEventJoypadButton, InputEventMouseButton, InputEventScreenTouch, InputMap, Ip, Json,
+    Label, ProjectSettings, Time,
+}, prelude::*};
+use ping::{PingFailure, PingRequest, PingResult, PingWorker, Verification};
+use remote_server::{RemoteEvent, RemoteServer};
+use sequence::{Sequence, SequenceEvent, SequenceRunner, Step};
+use serde_json::json;
+use services::{ServiceCheck, ServiceMonitor, ServiceResult};
+use shutdown::SHUTDOWN_TIMEOUT;
+use sim_output::SimJoystickOutput;
+use status_server::{StatusServer, DEFAULT_PORT as DEFAULT_STATUS_SERVER_PORT};
+use virtual_controller::{
+    button_bitmask, xinput_bit, InputError, SharedController, AXIS_NAMES, BUTTON_MAPPING, SPARE_BUTTONS,
+};
+
+// How many recent errors are kept for the status endpoint
+const MAX_RECENT_ERRORS: usize = 10;
+
+// Button events kept for the status snapshot
+const MAX_RECENT_ACTIONS: usize = 10;
+
+// Allowed range for the ping timeout
+const MIN_PING_TIMEOUT_MS: i64 = 100;
+const MAX_PING_TIMEOUT_MS: i64 = 10_000;
+
+// Persistent mode checks the open connection often, it's cheap
+const PERSISTENT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// First reconnect delay in persistent mode, doubled after every failure
+const MIN_RECONNECT_BACKOFF: Duration = Duration::from_millis(500);
+
+// Delay between the quick probes sent while the link state is in doubt
+const BURST_PROBE_INTERVAL: Duration = Duration::from_millis(250);
+
+// How often the robot's clock is sampled once the offset is known
+const TIME_SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+// How often the local interface list is checked for changes
+const INTERFACE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Connect timeout for each address tried during discovery
+const DISCOVERY_TIMEOUT: Duration = Duration::from_millis(300);
+
+// Where connection logs are written
+const CONNECTION_LOG_DIR: &str = "user://frc_interface_logs";
+
+// Settings changed at runtime are saved here
+const SETTINGS_PATH: &str = "user://frc_interface.cfg";
+
+// How often the status endpoint snapshot is refreshed
+const STATUS_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+// Latency samples kept for percentiles, older ones are dropped
+const MAX_LATENCY_SAMPLES: usize = 1000;
+
+// Pointer id used for the mouse, touch indices are never negative
+const MOUSE_POINTER: i32 = -1;
+
+// Device id Godot gives mouse events emulated from touches
+const EMULATED_MOUSE_DEVICE: i32 = -1;
+
+// Shortest gap between two handheld vibrations
+const HAPTIC_MIN_INTERVAL: Duration = Duration::from_millis(80);
+
+// How often hold_progress is emitted while a hold-to-activate button is held
+const HOLD_PROGRESS_INTERVAL: Duration = Duration::from_millis(33);
+
+// Values of selected_level other than "none", each also a button
+const LEVELS: [&str; 3] = ["low", "mid", "high"];
+
+// Distinct recent presses undo_last_action can step back through
+const MAX_UNDO_HISTORY: usize = 8;
+
+// How often the "x.x s ago" of the last action labels is redrawn
+const LAST_ACTION_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+// p95 ping latency above this marks the connection as degraded
+const DEGRADED_LATENCY: Duration = Duration::from_millis(100);
+
+const ALLIANCES: [&str; 3] = ["red", "blue", "unknown"];
+
+const REEF_FACES: [&str; 6] = ["A", "B", "C", "D", "E", "F"];
+
+// Tells the human player to act. Not a controller action: it goes out on a
+// spare button and/or a topic, see hp_signal_button and hp_signal_topic
+const HP_SIGNAL: &str = "hp_signal";
+
+// How long past ping_timeout_ms the pre-match check waits for its probe
+const PREMATCH_PROBE_GRACE: Duration = Duration::from_secs(1);
+
+// Wait between attempts to bring back a dead controller thread
+const CONTROLLER_RESTART_DELAY: Duration = Duration::from_secs(1);
+
+struct FRCInterface;
+
+#[derive(Default)]
+struct PingStats {
+    attempts: u32,
+    successes: u32,
+    // Indexed by ConnectionErrorKind
+    failures: [u32; FAILURE_KINDS.len() + 1],
+    last: Option<Duration>,
+    min: Option<Duration>,
+    max: Option<Duration>,
+    total: Duration,
+    samples: u32,
+    // Most recent latencies, for the percentile
+    recent: VecDeque<Duration>,
+}
+
+impl PingStats {
+    fn record(&mut self, latency: Duration) {
+        self.last = Some(latency);
+        self.min = Some(self.min.map_or(latency, |min| min.min(latency)));
+        self.max = Some(self.max.map_or(latency, |max| max.max(latency)));
+        self.total += latency;
+        self.samples += 1;
+
+        if self.recent.len() >= MAX_LATENCY_SAMPLES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(latency);
+    }
+
+    fn record_failure(&mut self, kind: ConnectionErrorKind) {
+        self.attempts += 1;
+        self.failures[kind as usize] += 1;
+    }
+
+    fn average(&self) -> Option<Duration> {
+        if self.samples == 0 {
+            None
+        } else {
+            Some(self.total / self.samples)
+        }
+    }
+
+    fn percentile(&self, pct: f64) -> Option<Duration> {
+        if self.recent.is_empty() {
+            return None;
+        }
+
+        let mut sorted: Vec<Duration> = self.recent.iter().copied().collect();
+        sorted.sort();
+        let index = ((sorted.len() as f64 * pct / 100.0).ceil() as usize).clamp(1, sorted.len()) - 1;
+        Some(sorted[index])
+    }
+}
+
+/// Durations of one kind of cycle, e.g. intake to coral.
+#[derive(Default)]
+struct CycleStats {
+    count: u32,
+    // Started again, or never finished, before the end action came
+    aborted: u32,
+    min: Option<Duration>,
+    max: Option<Duration>,
+    total: Duration,
+}
+
+impl CycleStats {
+    fn record(&mut self, duration: Duration) {
+        self.count += 1;
+        self.min = Some(self.min.map_or(duration, |min| min.min(duration)));
+        self.max = Some(self.max.map_or(duration, |max| max.max(duration)));
+        self.total += duration;
+    }
+
+    fn average(&self) -> Option<Duration> {
+        (self.count > 0).then(|| self.total / self.count)
+    }
+}
+
+/// Collapses identical consecutive errors into one line with a repeat count.
+#[derive(Default)]
+struct ErrorAggregator {
+    message: Option<String>,
+    // Repeats since the message was last printed
+    repeats: u32,
+    // Repeats since the streak started
+    total_repeats: u32,
+    last_report: Option<Instant>,
+}
+
+impl ErrorAggregator {
+    /// Returns the line to print, if any.
+    fn note(&mut self, message: &str, interval: Duration) -> Option<String> {
+        if self.message.as_deref() != Some(message) {
+            let summary = self.flush();
+            self.message = Some(message.to_string());
+            self.total_repeats = 0;
+            self.last_report = Some(Instant::now());
+            return Some(match summary {
+                Some(summary) => format!("{}\n{}", summary, message),
+                None => message.to_string(),
+            });
+        }
+
+        self.repeats += 1;
+        self.total_repeats += 1;
+        if self.last_report.is_some_and(|last| last.elapsed() < interval) {
+            return None;
+        }
+
+        self.last_report = Some(Instant::now());
+        let line = format!("{} (repeated {} times)", message, self.repeats);
+        self.repeats = 0;
+        Some(line)
+    }
+
+    /// End the current streak, returning a summary of unprinted repeats.
+    fn flush(&mut self) -> Option<String> {
+        let message = self.message.take()?;
+        let repeats = std::mem::take(&mut self.repeats);
+        self.total_repeats = 0;
+        (repeats > 0).then(|| format!("{} (repeated {} more times)", message, repeats))
+    }
+}
+
+/// How to confirm that whatever answered the ping is actually the robot.
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+enum VerificationMode {
+    None = 0,
+    SshBanner = 1,
+    Http = 2,
+}
+
+/// Why the last probe failed.
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+enum ConnectionErrorKind {
+    None = 0,
+    Timeout = 1,
+    Refused = 2,
+    Unreachable = 3,
+    Resolve = 4,
+    InvalidAddress = 5,
+    LocalAddress = 6,
+    Other = 7,
+}
+
+// Every kind a failed probe can report, in a fixed order for stats
+const FAILURE_KINDS: [ConnectionErrorKind; 7] = [
+    ConnectionErrorKind::Timeout,
+    ConnectionErrorKind::Refused,
+    ConnectionErrorKind::Unreachable,
+    ConnectionErrorKind::Resolve,
+    ConnectionErrorKind::InvalidAddress,
+    ConnectionErrorKind::LocalAddress,
+    ConnectionErrorKind::Other,
+];
+
+impl ConnectionErrorKind {
+    fn from_io(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::TimedOut => ConnectionErrorKind::Timeout,
+            ErrorKind::ConnectionRefused => ConnectionErrorKind::Refused,
+            ErrorKind::HostUnreachable | ErrorKind::NetworkUnreachable => ConnectionErrorKind::Unreachable,
+            ErrorKind::AddrNotAvailable => ConnectionErrorKind::LocalAddress,
+            _ => ConnectionErrorKind::Other,
+        }
+    }
+}
+
+/// Where the virtual controller's state is sent.
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+enum OutputMode {
+    /// A ViGEm Xbox controller picked up by the Driver Station.
+    Vigem = 0,
+    /// A joystick on a WPILib simulation (halsim_ws) server.
+    HalSim = 1,
+}
+
+/// Manual override of the connection state, for bench testing.
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+enum OverrideMode {
+    /// Connected follows the pings.
+    Normal = 0,
+    /// Report connected without pinging. Expires after force_connected_expiry_secs.
+    ForceConnected = 1,
+    /// Report disconnected without pinging.
+    ForceDisconnected = 2,
+}
+
+/// What a feedback sound does when triggered while still playing.
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+enum SoundOverlap {
+    /// Start it again from the beginning.
+    Restart = 0,
+    /// Let the one playing finish and skip the new one.
+    Skip = 1,
+}
+
+/// Best guess at why the robot can't be reached.
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+enum NetworkDiagnosis {
+    Unknown = 0,
+    Ok = 1,
+    RioDown = 2,
+    NotOnRobotNetwork = 3,
+}
+
+/// Last known state of one monitored robot service.
+struct ServiceStatus {
+    up: bool,
+    required: bool,
+    latency: Option<Duration>,
+    last_checked: Option<SystemTime>,
+}
+
+/// Where a button press or release came from, used for logging and the
+/// origin argument of action_pressed/action_released.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum InputOrigin {
+    Ui,
+    Keyboard,
+    ButtonBox,
+    Script,
+    Remote,
+    Macro,
+    Sequence,
+}
+
+impl InputOrigin {
+    fn as_str(self) -> &'static str {
+        match self {
+            InputOrigin::Ui => "ui",
+            InputOrigin::Keyboard => "keyboard",
+            InputOrigin::ButtonBox => "button_box",
+            InputOrigin::Script => "script",
+            InputOrigin::Remote => "remote",
+            InputOrigin::Macro => "macro",
+            InputOrigin::Sequence => "sequence",
+        }
+    }
+
+    /// Someone at the controls, as opposed to a script, macro or sequence.
+    fn is_operator(self) -> bool {
+        matches!(self, InputOrigin::Ui | InputOrigin::Keyboard | InputOrigin::ButtonBox | InputOrigin::Remote)
+    }
+}
+
+/// What operator input does while a sequence is running.
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+enum SequenceInputPolicy {
+    /// Presses are rejected with press_blocked until the sequence ends.
+    Block = 0,
+    /// The first press stops the sequence and goes through.
+    Abort = 1,
+}
+
+/// A hold-to-activate button that is held but hasn't fired yet.
+struct PendingHold {
+    started: Instant,
+    hold: Duration,
+    last_progress: Instant,
+}
+
+/// A press waiting on the coach's tablet.
+struct CoachConfirmation {
+    id: u64,
+    action: String,
+    deadline: Instant,
+    // While the local button is still down, for the long-press override
+    held_since: Option<Instant>,
+}
+
+/// A press held back for the press filter.
+struct FilterCheck {
+    action: String,
+    origin: InputOrigin,
+    // Made by undo_last_action, so it isn't recorded for undo again
+    undoing: bool,
+}
+
+impl FilterCheck {
+    /// Ask `filter` about the press. No filter, e.g. removed while the press
+    /// waited, lets it through.
+    fn ask(&self, filter: Option<&Callable>) -> Variant {
+        match filter {
+            Some(filter) => {
+                let action = StringName::from(self.action.as_str());
+                filter.call(&[action.to_variant(), StringName::from(self.origin.as_str()).to_variant()])
+            }
+            None => true.to_variant(),
+        }
+    }
+}
+
+/// A button showing as pressed for a press that came from somewhere else.
+struct MirroredPress {
+    action: String,
+    button: Gd<BaseButton>,
+    was_toggle: bool,
+}
+
+/// A node currently driving an action, with the callables connected to it
+/// so they can be disconnected again.
+struct ActionBinding {
+    action: String,
+    node: Gd<Node>,
+    connections: Vec<(&'static str, Callable)>,
+}
+
+/// A score_coral() run: wait for the level to settle, then tap coral. The
+/// runner times it like any other sequence.
+struct ScoreSequence {
+    level: String,
+    runner: SequenceRunner,
+}
+
+/// Outcome of a press or release.
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+enum ActionResult {
+    Ok = 0,
+    NotConnected = 1,
+    UnknownButton = 2,
+    ControllerNotReady = 3,
+    InputsLocked = 4,
+    UnknownAxis = 5,
+    CoolingDown = 6,
+    Vetoed = 7,
+    ActionDisabled = 8,
+    NoInverse = 9,
+    NothingToUndo = 10,
+    EndgameLockout = 11,
+    Busy = 12,
+    /// Another driver station instance has control authority.
+    Standby = 13,
+    /// The robot is in autonomous and lockout_during_auto is on.
+    AutoLockout = 14,
+    /// A sequence is running and sequence_input_policy is Block.
+    SequenceRunning = 15,
+    /// Held back until it's held long enough or confirmed.
+    Pending = 16,
+    /// Waiting on the press filter, asked once the current call returns.
+    Filtering = 17,
+}
+
+impl ActionResult {
+    /// Sent, or only waiting on the press filter: the caller's hold stays.
+    fn going_out(self) -> bool {
+        matches!(self, ActionResult::Ok | ActionResult::Filtering)
+    }
+}
+
+fn duration_ms(duration: Option<Duration>) -> Option<f64> {
+    duration.map(|d| d.as_secs_f64() * 1000.0)
+}
+
+/// A number from the editor or a script, which may be an int or a float.
+fn variant_number(value: &Variant) -> Option<f64> {
+    match value.get_type() {
+        VariantType::INT => Some(value.to::<i64>() as f64),
+        VariantType::FLOAT => Some(value.to::<f64>()),
+        _ => None,
+    }
+}
+
+/// A list of names from the editor or a script, which may be a
+/// PackedStringArray or an Array of strings.
+fn variant_strings(value: &Variant) -> Option<Vec<String>> {
+    if let Ok(strings) = value.try_to::<PackedStringArray>() {
+        return Some(strings.as_slice().iter().map(|s| s.to_string()).collect());
+    }
+    let array = value.try_to::<VariantArray>().ok()?;
+    array
+        .iter_shared()
+        .map(|item| match item.get_type() {
+            VariantType::STRING | VariantType::STRING_NAME => Some(item.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A duration entry in `unit`s, `seconds_per_unit` long each, as a duration.
+/// `None` for zero or less, which turns the setting off.
+fn variant_duration(value: &Variant, seconds_per_unit: f64, unit: &str) -> Result<Option<Duration>, String> {
+    let amount = variant_number(value).ok_or_else(|| format!("is {}, expected a number of {}", value, unit))?;
+    if amount <= 0.0 {
+        return Ok(None);
+    }
+    Duration::try_from_secs_f64(amount * seconds_per_unit)
+        .map(Some)
+        .map_err(|_| format!("of {} {} is out of range", amount, unit))
+}
+
+#[gdextension]
+unsafe impl ExtensionLibrary for FRCInterface {}
+
+/// Everything behind FRCInterfaceBase and FRCInterface2D: the settings, the
+/// state and the logic. The node classes only declare the exports, signals and
+/// functions and hand every call to this, so the two behave the same.
+struct InterfaceCore {
+    // Shared settings, applied on ready. Node exports set on the node win,
+    // the rest come from here
+    config: Option<Gd<FRCInterfaceConfig>>,
+
+    // Which of the settings the config resource covers were set on the node
+    explicit_config: ExplicitFields,
+
+    // Load SETTINGS_PATH on ready, on top of the config resource
+    auto_load_on_ready: bool,
+
+    // Start monitoring and plug in the controller on ready. Scenes that only
+    // need it some of the time turn this off and call start()/stop()
+    auto_start: bool,
+
+    running: bool,
+
+    // Presses forwarded since start()
+    presses_this_session: i64,
+
+    // How much the extension prints. There is one level for the whole
+    // process, shared by every node and worker thread: setting it on any
+    // node changes it for all of them, and reading it gives the level in
+    // effect. The field keeps what this node asked for, for save_settings
+    log_level: LogLevel,
+
+    connected: bool,
+
+    // Takes effect as soon as it is set. A simulated connection loss still wins
+    override_mode: OverrideMode,
+
+    // ForceConnected drops back to Normal after this long so it can't be
+    // left on by accident, 0 keeps it until changed
+    force_connected_expiry_secs: f64,
+
+    override_expires: Option<Instant>,
+
+    // Simulated connection loss for rehearsing the "comms dropped" flow
+    simulating_connection_loss: bool,
+
+    simulation_end: Option<Instant>,
+
+    // Uptime/downtime tracking, the properties are computed on read
+    connection_history: ConnectionHistory,
+
+    time_in_current_state: f64,
+
+    total_downtime: f64,
+
+    // Action sources, each any BaseButton or a TouchScreenButton
+    climb_button: Option<Gd<Node>>,
+
+    zero_button: Option<Gd<Node>>,
+
+    intake_button: Option<Gd<Node>>,
+
+    high_button: Option<Gd<Node>>,
+
+    mid_button: Option<Gd<Node>>,
+
+    low_button: Option<Gd<Node>>,
+
+    coral_button: Option<Gd<Node>>,
+
+    intake_alga_button: Option<Gd<Node>>,
+
+    drop_alga_button: Option<Gd<Node>>,
+
+    // Extra buttons, each entry { "action": String, "button": NodePath }
+    // pointing at a BaseButton or TouchScreenButton.
+    // The fixed exports above feed the same registry
+    button_bindings: Array<Dictionary>,
+
+    // Range controls (sliders, spin boxes) driving axes, each entry
+    // { "axis": String, "control": NodePath } with optional "min"/"max"
+    // (defaults to the control's own range) and "snap_back": bool
+    axis_bindings: Array<Dictionary>,
+
+    // Keep slider moves made while disconnected and send them on
+    // reconnect, instead of dropping them
+    hold_axes_while_disconnected: bool,
+
+    range_bindings: Vec<RangeBinding>,
+
+    // Latest value per axis waiting to go out, sent at most every
+    // AXIS_PUSH_INTERVAL
+    pending_axes: HashMap<String, f64>,
+    last_axis_push: Instant,
+
+    // Shared with every other interface node in ViGEm mode
+    virtual_controller: Option<SharedController>,
+
+    // Failed attempts to restart a dead controller thread before giving
+    // up and reporting the controller as "failed"
+    controller_max_restarts: i64,
+
+    // "ok", "restarting" or "failed"
+    controller_state: &'static str,
+    controller_restarts: u32,
+    controller_restart_failures: u32,
+    next_controller_restart: Instant,
+
+    // Local match clock, driven by start_match() or the robot's mode
+    match_auto_secs: f64,
+
+    match_teleop_secs: f64,
+
+    // Last part of teleop, counted back from the end of the match
+    match_endgame_secs: f64,
+
+    // Start the clock when notify_robot_mode() reports autonomous
+    match_sync_to_robot_mode: bool,
+
+    /// Last mode from notify_robot_mode(): "autonomous", "teleop", "test",
+    /// "disabled" or "unknown". Unknown again when the link drops.
+    robot_mode: GString,
+
+    // Block every press and hold the controller neutral while the robot
+    // is in autonomous
+    lockout_during_auto: bool,
+
+    // Whether the lockout applies while the robot mode is unknown
+    auto_lockout_when_mode_unknown: bool,
+
+    auto_locked_out: bool,
+
+    match_timer: MatchTimer,
+
+    // Actions blocked, and their buttons greyed out, until endgame
+    endgame_only_actions: PackedStringArray,
+
+    // Whether endgame-only actions are blocked when no match clock has
+    // been started
+    lock_endgame_actions_without_match: bool,
+
+    // Set by override_endgame_lockout(), until the clock is reset
+    endgame_lockout_overridden: bool,
+
+    match_phase: MatchPhase,
+
+    /// Seconds left in the current period, auto or teleop.
+    match_time_remaining: f64,
+
+    // Seconds before the end of the match to raise endgame_warning at,
+    // each once per match
+    endgame_warning_secs: PackedFloat64Array,
+
+    // Optional cues for the warning: a node to blink and a sound
+    endgame_warning_flash: Option<Gd<CanvasItem>>,
+
+    endgame_warning_flash_secs: f64,
+
+    endgame_warning_sound: Option<Gd<AudioStream>>,
+
+    // Thresholds already raised this match
+    endgame_warnings_fired: Vec<f64>,
+
+    // Flash in progress: when it started and whether the node was visible
+    warning_flash: Option<(Instant, bool)>,
+
+    // Write a report to MATCH_REPORT_DIR when the match ends
+    write_match_reports: bool,
+
+    // What the report for the current match has collected so far. Only
+    // gathered between start_match() and the end of the match
+    match_recording: bool,
+    match_started_wall: f64,
+    match_events: Vec<serde_json::Value>,
+    // Seconds into the match each outage started and ended
+    match_outages: Vec<(f64, Option<f64>)>,
+    // Ping attempts and successes when the match started
+    match_ping_baseline: (u32, u32),
+    // Scouting CSV columns and row for the last match, taken as it ended
+    finished_match: Option<(Vec<String>, Vec<String>)>,
+    report_writer: ReportWriter,
+
+    // Alerts outside the UI while the robot is unreachable. Never raised
+    // while an override is active
+    alert_window_title: bool,
+
+    alert_title_suffix: GString,
+
+    alert_request_attention: bool,
+
+    alert_sound: Option<Gd<AudioStream>>,
+
+    // Title from before the suffix was added, restored on reconnect
+    saved_window_title: Option<GString>,
+    alert_player: Option<Gd<AudioStreamPlayer>>,
+
+    // Feedback sounds for presses from any origin, each optional.
+    // action_press_sounds (name -> AudioStream) overrides press_sound
+    press_sound: Option<Gd<AudioStream>>,
+
+    release_sound: Option<Gd<AudioStream>>,
+
+    // Played when a press is turned away: not connected, locked, cooling
+    // down, disabled or vetoed
+    denied_sound: Option<Gd<AudioStream>>,
+
+    action_press_sounds: Dictionary,
+
+    sound_overlap: SoundOverlap,
+
+    // One player per kind of sound, so fast input can't pile them up
+    feedback_players: HashMap<&'static str, Gd<AudioStreamPlayer>>,
+
+    // Built-in status display, either node can be left unassigned. Text
+    // templates can use {latency}, {error} and {address}
+    status_indicator: Option<Gd<CanvasItem>>,
+
+    status_label: Option<Gd<Label>>,
+
+    // Readout of the last press sent, either can be left unassigned.
+    // Formats can use {action}, {origin} and {age} (seconds)
+    last_action_label: Option<Gd<Label>>,
+
+    last_action_time_label: Option<Gd<Label>>,
+
+    last_action_format: GString,
+
+    last_action_time_format: GString,
+
+    // Shown before anything has been pressed
+    text_no_action: GString,
+
+    last_action: Option<(String, InputOrigin, Instant)>,
+    last_action_refresh: Instant,
+
+    // When each action currently down on the controller was pressed
+    held_since: HashMap<String, Instant>,
+
+    // How often button_held is emitted for each held action, 0 for never
+    button_held_rate_hz: f64,
+
+    last_button_held: Instant,
+
+    color_connected: Color,
+
+    color_degraded: Color,
+
+    color_disconnected: Color,
+
+    color_forced: Color,
+
+    text_connected: GString,
+
+    text_degraded: GString,
+
+    text_disconnected: GString,
+
+    text_forced: GString,
+
+    text_forced_disconnected: GString,
+
+    // Put in front of the status text while dry_run is on
+    text_dry_run: GString,
+
+    last_indicator_state: Option<(Color, String)>,
+
+    // Output backend, chosen once on ready
+    output_mode: OutputMode,
+
+    /// Run the whole pipeline without any output: no ViGEm pad or sim
+    /// joystick is created, presses only reach an internal button state.
+    dry_run: bool,
+
+    halsim_address: GString,
+
+    halsim_port: i64,
+
+    halsim_joystick: i64,
+
+    sim_output: Option<SimJoystickOutput>,
+
+    // TCP ping fields
+    last_ping_time: Instant,
+    ping_interval: Duration,
+    next_ping_delay: Duration,
+    reconnect_backoff: Duration,
+
+    // Hysteresis, the link only changes state after this many probes in a row agree
+    failures_before_disconnect: i64,
+
+    successes_before_connect: i64,
+
+    consecutive_failures: u32,
+    consecutive_successes: u32,
+
+    // Local addresses seen on the last interface check, sorted
+    local_addresses: Vec<String>,
+    last_interface_check: Instant,
+
+    // Set when the network changed, the next probe starts from scratch
+    fresh_probe_pending: bool,
+
+    // Stop probing while the app is backgrounded (mobile builds)
+    pause_when_hidden: bool,
+
+    probing_paused: bool,
+
+    // Keep one TCP connection open and watch its health instead of
+    // reconnecting on every ping
+    persistent_connection: bool,
+
+    ping_address: GString,
+
+    ping_port: i64,
+
+    // Combined "host:port" view of ping_address/ping_port, kept in sync both ways
+    endpoint: GString,
+
+    // Last ping target typed that didn't parse, with why. Nothing is
+    // probed while it's set
+    rejected_endpoint: Option<(String, String)>,
+
+    /// Connect timeout for each ping, also bounds hostname resolution.
+    ping_timeout_ms: i64,
+
+    // Verification of the ping target, matched against expected_banner
+    verification_mode: VerificationMode,
+
+    expected_banner: GString,
+
+    verified: bool,
+
+    last_verification_failure: Option<String>,
+
+    // Local IP the probes bind to, so they definitely use the robot-facing NIC
+    local_bind_address: GString,
+
+    // Local address the last successful probe actually used
+    local_address: GString,
+
+    last_error: GString,
+
+    last_error_kind: ConnectionErrorKind,
+
+    // Robot clock offset, sampled from the RIO's web server alongside the ping
+    time_sync_enabled: bool,
+
+    // Add robot_time to log entries once the offset is known
+    apply_clock_offset_to_logs: bool,
+
+    robot_clock_offset_ms: f64,
+
+    robot_clock_offset_known: bool,
+
+    clock_offset: ClockOffsetEstimator,
+    last_time_sync_error: Option<String>,
+
+    // Failure diagnosis, the radio is 10.TE.AM.1 unless radio_address is set.
+    // Also stamped into logs, match reports and the status endpoint
+    team_number: i64,
+
+    // Event and match this session is for, e.g. "2025txhou" and "Q42",
+    // stamped next to team_number
+    event_code: GString,
+
+    match_label: GString,
+
+    radio_address: GString,
+
+    network_diagnosis: NetworkDiagnosis,
+
+    radio_probe: Option<RadioProbe>,
+
+    // Keep inputs locked after a disconnect until rearm_inputs() is called
+    lock_inputs_on_disconnect: bool,
+
+    inputs_locked: bool,
+
+    // Also lock inputs when the window loses focus or the app is paused.
+    // Everything is released either way
+    lock_inputs_on_focus_loss: bool,
+
+    window_focused: bool,
+
+    // Grey out bound buttons while disconnected. Buttons that were already
+    // disabled for some other reason are left alone
+    disable_buttons_when_disconnected: bool,
+
+    // Buttons disabled by the flag above, re-enabled on reconnect
+    buttons_disabled_while_disconnected: Vec<Gd<BaseButton>>,
+
+    // Actions turned off with set_action_enabled, rejected from every origin
+    disabled_actions: HashSet<String>,
+
+    // Named sets of the actions left live, e.g. { "defense": ["intake",
+    // "climb"] }. Everything else is disabled while a set is active.
+    // "default" means every action
+    action_sets: Dictionary,
+
+    // Match phase ("pre_match", "auto", "teleop", "endgame" or
+    // "post_match") -> action set activated when the phase starts. A
+    // phase with no entry leaves the set as it is
+    phase_action_sets: Dictionary,
+
+    active_action_set: String,
+    // Actions the active set allows, None for "default"
+    action_set_allowed: Option<HashSet<String>>,
+
+    // Grey out the bound buttons of disabled actions
+    gray_out_disabled_actions: bool,
+
+    // Buttons greyed out for their action, with that action
+    action_disabled_buttons: Vec<(String, Gd<BaseButton>)>,
+
+    // Extra robot services checked alongside the ping, name -> port or
+    // name -> { "port": int, "required": bool }
+    services: Dictionary,
+
+    service_monitor: Option<ServiceMonitor>,
+    service_status: HashMap<String, ServiceStatus>,
+    primary_up: bool,
+
+    // Other devices on the robot network with their own address, each entry
+    // { "name": String, "address": String, "port": int, "required": bool }.
+    // They don't affect connected, which only means the RIO is reachable
+    devices: Array<Dictionary>,
+
+    device_status: HashMap<String, ServiceStatus>,
+
+    devices_ok: bool,
+
+    ping_worker: Option<PingWorker>,
+    ping_stats: PingStats,
+
+    // Repeated connection errors are printed at most this often
+    error_report_interval_secs: f64,
+
+    error_aggregator: ErrorAggregator,
+    recent_errors: VecDeque<(Instant, String)>,
+
+    // Latest presses and releases for the status snapshot: when, action,
+    // pressed, origin
+    recent_actions: VecDeque<(Instant, String, bool, InputOrigin)>,
+
+    // Robot discovery fields, an empty port list means ping_port
+    discovery_ports: PackedInt32Array,
+
+    discovery_auto_adopt: bool,
+
+    discovery_attempts_per_sec: i64,
+
+    discovery_concurrency: i64,
+
+    discovery: Option<Discovery>,
+    discovery_adopted: bool,
+
+    // Connection log fields, verbose also logs every ping result
+    connection_log_enabled: bool,
+
+    connection_log_verbose: bool,
+
+    connection_log: Option<EventLog>,
+
+    // Status endpoint fields
+    status_server_enabled: bool,
+
+    status_server_port: i64,
+
+    status_server: StatusServer,
+    last_status_update: Instant,
+    start_time: Instant,
+
+    // Remote control fields
+    remote_server_enabled: bool,
+
+    remote_server_port: i64,
+
+    remote_auth_token: GString,
+
+    remote_server: RemoteServer,
+    last_remote_state: String,
+
+    // Control authority between a primary and a backup instance, each
+    // running the remote server. Only the active one drives the controller;
+    // with this off every instance is active
+    authority_enabled: bool,
+
+    // The other instance's remote server, e.g. "ws://10.45.33.20:5801".
+    // remote_auth_token has to match on both
+    authority_peer_url: GString,
+
+    // Who keeps authority when both instances claim it, higher wins.
+    // Give the two instances different values
+    authority_priority: i64,
+
+    authority_start_active: bool,
+
+    // Take authority when the active instance stops heartbeating for
+    // authority_timeout_secs
+    authority_failover: bool,
+
+    authority_timeout_secs: f64,
+
+    // Shown while this instance is on standby
+    standby_banner: Option<Gd<CanvasItem>>,
+
+    authority_active: bool,
+
+    // Tie-breaker for equal priorities, fresh on every start
+    authority_id: u64,
+    authority_link: AuthorityLink,
+    last_heartbeat_sent: Instant,
+    // Latest heartbeat from the other instance, or when we started
+    // listening if there hasn't been one
+    peer_heartbeat: Option<Heartbeat>,
+    peer_last_seen: Instant,
+
+    // Per-button acknowledgment from the robot, button -> ack topic. Buttons
+    // missing from ack_topics use DEFAULT_ACK_TOPIC_PREFIX + name
+    ack_enabled: bool,
+
+    ack_topics: Dictionary,
+
+    ack_timeout_ms: i64,
+
+    // Each acknowledged press publishes its sequence number here plus the
+    // button name. The robot echoes it back as the ack, so a late ack for
+    // an old press can't clear a newer failure
+    ack_seq_topic_prefix: GString,
+
+    // What an action's indicator flashes while its ack is overdue
+    ack_error_color: Color,
+
+    next_press_seq: u64,
+    // Actions whose last ack never came, with the sequence number of the
+    // press that went unanswered
+    ack_errors: HashMap<String, u64>,
+    // CanvasItems bound with bind_ack_indicator(), by action
+    ack_indicators: HashMap<String, AckIndicator>,
+    ack_flash_started: Instant,
+
+    /// "red", "blue" or "unknown". Setting it overrides what the FMS
+    /// reports until clear_alliance_override().
+    alliance: GString,
+
+    // Last alliance reported by report_fms_alliance()
+    detected_alliance: String,
+    alliance_manual: bool,
+
+    // Where the chosen alliance is published over the command link
+    alliance_topic: GString,
+
+    /// Reef face the operator means to score on, "A" to "F" or "none".
+    selected_face: GString,
+
+    // Where the face is published over the command link, empty for never
+    face_topic: GString,
+
+    // Face -> spare Xbox buttons (A, X, Y, LS, RS) held while it is
+    // selected, e.g. { "C": ["A", "X"] }. Empty to leave the pad alone
+    face_button_bits: Dictionary,
+
+    // Buttons bound by bind_face_buttons(), as a radio group
+    face_buttons: Vec<(String, Gd<BaseButton>, Callable)>,
+    // The selected face's button was toggled off, cleared in process
+    // unless another face toggles on first
+    face_deselect_pending: bool,
+
+    // Shortest time between two hp_signal presses, per the rules
+    hp_signal_cooldown_secs: f64,
+
+    // Spare Xbox button (A, X, Y, LS or RS) tapped for hp_signal, empty
+    // to not use the controller
+    hp_signal_button: GString,
+
+    hp_signal_tap_ms: i64,
+
+    // Where the hp_signal count is published, empty for never
+    hp_signal_topic: GString,
+
+    // When the hp_signal tap lets go, while it's down
+    hp_signal_release_at: Option<Instant>,
+
+    /// Game-piece mode, e.g. "coral" or "algae". Picks the overrides in
+    /// mode_mappings.
+    mode: GString,
+
+    // Mode -> { action -> Xbox button } merged over BUTTON_MAPPING while
+    // that mode is active. Only buttons in BUTTON_MAPPING can be used
+    mode_mappings: Dictionary,
+
+    // Where the mode is published over the command link, empty for never
+    mode_topic: GString,
+
+    pending_acks: HashMap<String, PendingAck>,
+
+    // Running taps, each holding its action until it's time to let go
+    pending_taps: Vec<(String, InputOrigin, Instant)>,
+
+    // What undo_last_action taps for each action, name -> inverse name
+    inverse_actions: Dictionary,
+
+    undo_tap_ms: i64,
+
+    // Presses that reached the controller and ones turned away, per
+    // action since reset_action_counts()
+    action_counts: HashMap<String, i64>,
+    blocked_counts: HashMap<String, i64>,
+
+    // Zero the counts whenever the match clock starts
+    reset_counts_on_match_start: bool,
+
+    // Cycles to time, each { "start": action, "end": action, "label": name }.
+    // Reset with the counts
+    cycle_pairs: VariantArray,
+
+    // Label -> when its cycle started, while one is open
+    open_cycles: HashMap<String, Instant>,
+    cycle_stats: HashMap<String, CycleStats>,
+
+    // Distinct accepted presses, newest last. Undo taps aren't recorded
+    undo_history: VecDeque<String>,
+    undoing: bool,
+
+    // Actions the UI only presses after being held this long, name -> hold_ms.
+    // Releasing early cancels the press
+    hold_to_activate: Dictionary,
+
+    // Actions that can't be pressed again for a while after a press goes
+    // out, name -> cooldown_secs
+    cooldown: Dictionary,
+
+    // When each cooling action can be pressed again. Kept by name, so
+    // rebinding a button doesn't reset it
+    cooldown_until: HashMap<String, Instant>,
+
+    pending_holds: HashMap<String, PendingHold>,
+
+    // Actions that only go out after a confirmation dialog, then as a tap of
+    // confirm_tap_ms. A dialog is made if none is assigned
+    confirm_actions: PackedStringArray,
+
+    confirm_dialog: Option<Gd<ConfirmationDialog>>,
+
+    confirm_tap_ms: i64,
+
+    // The dialog closes by itself after this long, discarding the press
+    confirm_timeout_secs: f64,
+
+    // Action waiting on the dialog and when it gives up
+    pending_confirmation: Option<(String, Instant)>,
+
+    // Actions that only go out once a remote client (the coach's tablet)
+    // confirms them, then as a tap of confirm_tap_ms. Holding the button
+    // for coach_override_hold_ms sends it without the coach
+    coach_confirm_actions: PackedStringArray,
+
+    coach_confirm_timeout_secs: f64,
+
+    coach_override_hold_ms: i64,
+
+    pending_coach: Option<CoachConfirmation>,
+    next_coach_request: u64,
+
+    // Vibrate handhelds when an on-screen press is accepted, when the robot
+    // acknowledges a button and when a press is rejected
+    haptics_on_press: bool,
+
+    haptics_press_ms: i64,
+
+    haptics_on_ack: bool,
+
+    haptics_ack_ms: i64,
+
+    haptics_on_reject: bool,
+
+    haptics_reject_ms: i64,
+
+    last_vibration: Option<Instant>,
+
+    // Keyboard fallback, InputMap action -> button name
+    input_action_bindings: Dictionary,
+
+    // InputMap actions currently held, so a release without a press is ignored
+    held_input_actions: HashSet<String>,
+
+    // USB button box seen as a joypad. Only this device is listened to, so
+    // other joypads (including our own virtual pad) never drive actions
+    button_box_device: i64,
+
+    // Joypad button index -> button name
+    button_box_bindings: Dictionary,
+
+    held_box_buttons: HashSet<i64>,
+
+    // Track each finger on a bound button separately instead of relying on
+    // the button's own press tracking, so several can be held at once.
+    // Applies to buttons bound after it is set
+    touch_binding_mode: bool,
+
+    // Hold a focused bound button for as long as ui_accept is held, for
+    // gamepad navigation of the screen. Toggle buttons keep their own handling
+    focus_activation: bool,
+
+    // Action held through ui_accept on a focused button
+    focus_held: Option<String>,
+
+    // The high/mid/low buttons select a level instead of being held,
+    // and the selected level's button stays down until it changes
+    level_select_mode: bool,
+
+    /// "none", "low", "mid" or "high".
+    selected_level: GString,
+
+    // Set while set_selected_level drives the level buttons itself
+    applying_level: bool,
+    // Who selected the level, its hold is theirs to let go of
+    level_holder: InputOrigin,
+
+    // score_coral(): how long to wait after selecting the level, how
+    // long to hold coral, and whether to drop the level afterwards
+    score_settle_ms: i64,
+
+    score_tap_ms: i64,
+
+    score_clears_level: bool,
+
+    score: Option<ScoreSequence>,
+
+    sequence_input_policy: SequenceInputPolicy,
+
+    // Set by load_sequence(), copied for each run
+    loaded_sequence: Option<Sequence>,
+    running_sequence: Option<(Sequence, SequenceRunner)>,
+    // What the running sequence has down, let go of when it ends
+    sequence_held: HashSet<String>,
+    sequence_axes: HashSet<String>,
+
+    // Tap zero auto_zero_delay_ms after climb is let go of
+    auto_zero_after_climb: bool,
+
+    auto_zero_delay_ms: i64,
+
+    auto_zero_tap_ms: i64,
+
+    // When the scheduled zero tap is due
+    auto_zero_at: Option<Instant>,
+
+    // run_prematch_check(): slowest acceptable fresh probe, and the XInput
+    // slot the Driver Station expects the pad in (-1 for any)
+    prematch_max_latency_ms: f64,
+
+    expected_user_index: i64,
+
+    // Report waiting on the latency probe, and when to give up on it
+    prematch: Option<(Dictionary, Instant)>,
+
+    // Script veto on presses, see set_press_filter
+    press_filter: Option<Callable>,
+
+    // Presses waiting on the press filter, see next_filter_check
+    filter_checks: VecDeque<FilterCheck>,
+
+    // Finger (or MOUSE_POINTER) -> action it is holding in touch mode
+    touch_pointers: HashMap<i32, String>,
+
+    // What holds each action: buttons, keys, remote clients, taps, macros
+    // and sequences. It's only released once the last of them lets go
+    action_holders: HoldCounts,
+
+    // Nodes whose signals are connected to an action, from the exports or
+    // bind_button()
+    action_bindings: Vec<ActionBinding>,
+
+    // Radio-style groups by name, see bind_button_group
+    radio_groups: HashMap<String, RadioGroup>,
+
+    // Buttons drawn pressed for keyboard, remote and script presses
+    mirrored_presses: Vec<MirroredPress>,
+
+    // Coprocessor command link, separate from the ping target. Started on
+    // ready when command_address is set
+    command_address: GString,
+
+    command_port: i64,
+
+    command_link_connected: bool,
+
+    command_link: Option<CommandLink>,
+
+    // The node this belongs to, for the calls that need the scene tree
+    owner: OnceCell<Gd<Node>>,
+
+    // Signals and tree changes left for the node, see HostOp
+    host_ops: Vec<HostOp>,
+}
+
+impl Default for InterfaceCore {
+    fn default() -> Self {
+        Self {
+            config: None,
+            explicit_config: ExplicitFields::default(),
+            auto_load_on_ready: false,
+            auto_start: true,
+            running: false,
+            presses_this_session: 0,
+            log_level: LogLevel::Info,
+            connected: false,
+            override_mode: OverrideMode::Normal,
+            force_connected_expiry_secs: 300.0,
+            override_expires: None,
+            simulating_connection_loss: false,
+            simulation_end: None,
+            connection_history: ConnectionHistory::new(false),
+            time_in_current_state: 0.0,
+            total_downtime: 0.0,
+            climb_button: None,
+            zero_button: None,
+            intake_button: None,
+            high_button: None,
+            mid_button: None,
+            low_button: None,
+            coral_button: None,
+            intake_alga_button: None,
+            drop_alga_button: None,
+            button_bindings: Array::new(),
+            axis_bindings: Array::new(),
+            hold_axes_while_disconnected: true,
+            range_bindings: Vec::new(),
+            pending_axes: HashMap::new(),
+            last_axis_push: Instant::now(),
+            virtual_controller: None,
+            controller_max_restarts: 3,
+            controller_state: "ok",
+            controller_restarts: 0,
+            controller_restart_failures: 0,
+            next_controller_restart: Instant::now(),
+            match_auto_secs: 15.0,
+            match_teleop_secs: 135.0,
+            match_endgame_secs: 20.0,
+            match_sync_to_robot_mode: false,
+            robot_mode: "unknown".into(),
+            lockout_during_auto: false,
+            auto_lockout_when_mode_unknown: false,
+            auto_locked_out: false,
+            match_timer: MatchTimer::default(),
+            endgame_only_actions: [GString::from("climb")].into_iter().collect(),
+            lock_endgame_actions_without_match: false,
+            endgame_lockout_overridden: false,
+            endgame_warning_secs: [30.0, 10.0].into_iter().collect(),
+            endgame_warning_flash: None,
+            endgame_warning_flash_secs: 1.5,
+            endgame_warning_sound: None,
+            endgame_warnings_fired: Vec::new(),
+            warning_flash: None,
+            write_match_reports: true,
+            match_recording: false,
+            match_started_wall: 0.0,
+            match_events: Vec::new(),
+            match_outages: Vec::new(),
+            match_ping_baseline: (0, 0),
+            finished_match: None,
+            report_writer: ReportWriter::new(),
+            match_phase: MatchPhase::PreMatch,
+            match_time_remaining: 0.0,
+            alert_window_title: false,
+            alert_title_suffix: " [NO ROBOT COMMS]".into(),
+            alert_request_attention: false,
+            alert_sound: None,
+            saved_window_title: None,
+            alert_player: None,
+            press_sound: None,
+            release_sound: None,
+            denied_sound: None,
+            action_press_sounds: Dictionary::new(),
+            sound_overlap: SoundOverlap::Restart,
+            feedback_players: HashMap::new(),
+            status_indicator: None,
+            status_label: None,
+            color_connected: Color::from_rgb(0.2, 0.8, 0.2),
+            color_degraded: Color::from_rgb(0.9, 0.8, 0.1),
+            color_disconnected: Color::from_rgb(0.9, 0.2, 0.2),
+            color_forced: Color::from_rgb(0.7, 0.3, 0.9),
+            text_connected: "Connected ({latency} ms)".into(),
+            text_degraded: "Checking {address}...".into(),
+            last_action_label: None,
+            last_action_time_label: None,
+            last_action_format: "{action} ({origin})".into(),
+            last_action_time_format: "{age}s ago".into(),
+            text_no_action: "-".into(),
+            last_action: None,
+            last_action_refresh: Instant::now(),
+            held_since: HashMap::new(),
+            button_held_rate_hz: 10.0,
+            last_button_held: Instant::now(),
+            text_disconnected: "Disconnected: {error}".into(),
+            text_forced: "FORCED CONNECTED".into(),
+            text_forced_disconnected: "FORCED DISCONNECTED".into(),
+            last_indicator_state: None,
+            text_dry_run: "DRY RUN - ".into(),
+            output_mode: OutputMode::Vigem,
+            dry_run: false,
+            halsim_address: "localhost".into(),
+            halsim_port: 3300,
+            halsim_joystick: 0,
+            sim_output: None,
+            last_ping_time: Instant::now(),
+            ping_interval: Duration::from_secs_f64(DEFAULT_PING_INTERVAL_SECS),
+            next_ping_delay: Duration::from_secs_f64(DEFAULT_PING_INTERVAL_SECS),
+            reconnect_backoff: MIN_RECONNECT_BACKOFF,
+            failures_before_disconnect: 1,
+            successes_before_connect: 1,
+            consecutive_failures: 0,
+            consecutive_successes: 0,
+            local_addresses: Vec::new(),
+            last_interface_check: Instant::now(),
+            fresh_probe_pending: false,
+            pause_when_hidden: false,
+            probing_paused: false,
+            persistent_connection: false,
+            ping_address: DEFAULT_PING_ADDRESS.into(),
+            ping_port: DEFAULT_PING_PORT,
+            endpoint: format_endpoint(DEFAULT_PING_ADDRESS, DEFAULT_PING_PORT).as_str().into(),
+            rejected_endpoint: None,
+            ping_timeout_ms: DEFAULT_PING_TIMEOUT_MS,
+            verification_mode: VerificationMode::None,
+            expected_banner: "NI".into(),
+            verified: false,
+            last_verification_failure: None,
+            local_bind_address: GString::new(),
+            local_address: GString::new(),
+            last_error: GString::new(),
+            last_error_kind: ConnectionErrorKind::None,
+            time_sync_enabled: false,
+            apply_clock_offset_to_logs: false,
+            robot_clock_offset_ms: 0.0,
+            robot_clock_offset_known: false,
+            clock_offset: ClockOffsetEstimator::default(),
+            last_time_sync_error: None,
+            team_number: DEFAULT_TEAM_NUMBER,
+            event_code: GString::new(),
+            match_label: GString::new(),
+            radio_address: GString::new(),
+            network_diagnosis: NetworkDiagnosis::Unknown,
+            radio_probe: None,
+            lock_inputs_on_disconnect: false,
+            inputs_locked: false,
+            lock_inputs_on_focus_loss: false,
+            window_focused: true,
+            disable_buttons_when_disconnected: false,
+            buttons_disabled_while_disconnected: Vec::new(),
+            disabled_actions: HashSet::new(),
+            action_sets: Dictionary::new(),
+            phase_action_sets: Dictionary::new(),
+            active_action_set: "default".into(),
+            action_set_allowed: None,
+            gray_out_disabled_actions: true,
+            action_disabled_buttons: Vec::new(),
+            services: Dictionary::new(),
+            service_monitor: None,
+            service_status: HashMap::new(),
+            primary_up: false,
+            devices: Array::new(),
+            device_status: HashMap::new(),
+            devices_ok: true,
+            ping_worker: None,
+            ping_stats: PingStats::default(),
+            error_report_interval_secs: 10.0,
+            error_aggregator: ErrorAggregator::default(),
+            recent_errors: VecDeque::new(),
+            recent_actions: VecDeque::new(),
+            discovery_ports: PackedInt32Array::new(),
+            discovery_auto_adopt: false,
+            discovery_attempts_per_sec: 50,
+            discovery_concurrency: 8,
+            discovery: None,
+            discovery_adopted: false,
+            connection_log_enabled: false,
+            connection_log_verbose: false,
+            connection_log: None,
+            status_server_enabled: false,
+            status_server_port: DEFAULT_STATUS_SERVER_PORT,
+            status_server: StatusServer::new(),
+            last_status_update: Instant::now(),
+            start_time: Instant::now(),
+            remote_server_enabled: false,
+            remote_server_port: 5801,
+            remote_auth_token: GString::new(),
+            remote_server: RemoteServer::new(),
+            last_remote_state: String::new(),
+            authority_enabled: false,
+            authority_peer_url: GString::new(),
+            authority_priority: 0,
+            authority_start_active: true,
+            authority_failover: true,
+            authority_timeout_secs: 2.0,
+            standby_banner: None,
+            authority_active: true,
+            authority_id: 0,
+            authority_link: AuthorityLink::new(),
+            last_heartbeat_sent: Instant::now(),
+            peer_heartbeat: None,
+            peer_last_seen: Instant::now(),
+            ack_enabled: false,
+            ack_topics: Dictionary::new(),
+            ack_timeout_ms: DEFAULT_ACK_TIMEOUT_MS,
+            ack_seq_topic_prefix: "/GodotInterface/press_seq/".into(),
+            ack_error_color: Color::from_rgb(1.0, 0.2, 0.2),
+            next_press_seq: 1,
+            ack_errors: HashMap::new(),
+            ack_indicators: HashMap::new(),
+            ack_flash_started: Instant::now(),
+            alliance: "unknown".into(),
+            detected_alliance: "unknown".into(),
+            alliance_manual: false,
+            alliance_topic: "/GodotInterface/alliance".into(),
+            selected_face: "none".into(),
+            face_topic: "/GodotInterface/reef_face".into(),
+            face_button_bits: Dictionary::new(),
+            face_buttons: Vec::new(),
+            face_deselect_pending: false,
+            hp_signal_cooldown_secs: 3.0,
+            hp_signal_button: GString::new(),
+            hp_signal_tap_ms: 200,
+            hp_signal_topic: "/GodotInterface/hp_signal".into(),
+            hp_signal_release_at: None,
+            mode: "coral".into(),
+            mode_mappings: Dictionary::new(),
+            mode_topic: "/GodotInterface/mode".into(),
+            pending_acks: HashMap::new(),
+            pending_taps: Vec::new(),
+            inverse_actions: Dictionary::new(),
+            undo_tap_ms: 200,
+            action_counts: HashMap::new(),
+            blocked_counts: HashMap::new(),
+            reset_counts_on_match_start: true,
+            cycle_pairs: VariantArray::new(),
+            open_cycles: HashMap::new(),
+            cycle_stats: HashMap::new(),
+            undo_history: VecDeque::new(),
+            undoing: false,
+            hold_to_activate: Dictionary::new(),
+            cooldown: Dictionary::new(),
+            cooldown_until: HashMap::new(),
+            pending_holds: HashMap::new(),
+            confirm_actions: PackedStringArray::new(),
+            confirm_dialog: None,
+            confirm_tap_ms: 250,
+            confirm_timeout_secs: 10.0,
+            pending_confirmation: None,
+            coach_confirm_actions: PackedStringArray::new(),
+            coach_confirm_timeout_secs: 15.0,
+            coach_override_hold_ms: 1500,
+            pending_coach: None,
+            next_coach_request: 1,
+            haptics_on_press: false,
+            haptics_press_ms: 20,
+            haptics_on_ack: false,
+            haptics_ack_ms: 40,
+            haptics_on_reject: false,
+            haptics_reject_ms: 150,
+            last_vibration: None,
+            input_action_bindings: Dictionary::new(),
+            held_input_actions: HashSet::new(),
+            action_holders: HoldCounts::default(),
+            touch_binding_mode: false,
+            focus_activation: true,
+            focus_held: None,
+            level_select_mode: false,
+            selected_level: "none".into(),
+            level_holder: InputOrigin::Ui,
+            applying_level: false,
+            score_settle_ms: 300,
+            score_tap_ms: 200,
+            score_clears_level: true,
+            score: None,
+            sequence_input_policy: SequenceInputPolicy::Abort,
+            loaded_sequence: None,
+            running_sequence: None,
+            sequence_held: HashSet::new(),
+            sequence_axes: HashSet::new(),
+            auto_zero_after_climb: false,
+            auto_zero_delay_ms: 500,
+            auto_zero_tap_ms: 200,
+            auto_zero_at: None,
+            prematch_max_latency_ms: 50.0,
+            expected_user_index: -1,
+            prematch: None,
+            press_filter: None,
+            filter_checks: VecDeque::new(),
+            touch_pointers: HashMap::new(),
+            button_box_device: 0,
+            button_box_bindings: Dictionary::new(),
+            held_box_buttons: HashSet::new(),
+            action_bindings: Vec::new(),
+            radio_groups: HashMap::new(),
+            mirrored_presses: Vec::new(),
+            command_address: GString::new(),
+            command_port: 5802,
+            command_link_connected: false,
+            command_link: None,
+            owner: OnceCell::new(),
+            host_ops: Vec::new(),
+        }
+    }
+}
+
+impl InterfaceCore {
+    /// The node this core belongs to.
+    fn node(&self) -> Gd<Node> {
+        self.owner.get().expect("the node sets itself before calling in").clone()
+    }
+
+    /// Emit `signal` on the node once the current call returns.
+    fn emit(&mut self, signal: &str, args: &[Variant]) {
+        self.host_ops.push(HostOp::Emit(StringName::from(signal), args.to_vec()));
+    }
+
+    /// Add `child` to the node once the current call returns.
+    fn add_child(&mut self, child: Gd<Node>) {
+        self.host_ops.push(HostOp::AddChild(child));
+    }
+
+    /// Call `method` on `object` once the current call returns, after the
+    /// children added before it are in the tree.
+    fn call_later(&mut self, object: Gd<Object>, method: &str, args: &[Variant]) {
+        self.host_ops.push(HostOp::Call(object, StringName::from(method), args.to_vec()));
+    }
+
+    fn ready(&mut self) {
+        if Self::in_editor() {
+            return;
+        }
+
+        // Settings from the config resource come first, everything below uses them
+        if self.config.is_some() {
+            self.apply_config();
+        }
+        if self.auto_load_on_ready {
+            self.load_settings();
+        }
+
+        // Connect button signals
+        self.connect_button_signals();
+        self.update_buttons_disabled();
+        self.connect_range_bindings();
+
+        // Follow the button box being plugged in and out
+        let joy_callable = Callable::from_object_method(&self.node(), "on_joy_connection_changed");
+        let mut input = Input::singleton();
+        if !input.is_connected("joy_connection_changed", &joy_callable) {
+            input.connect("joy_connection_changed", &joy_callable);
+        }
+
+        if self.auto_start {
+            self.start();
+        }
+    }
+
+    fn process(&mut self) {
+        if Self::in_editor() || !self.running {
+            return;
+        }
+
+        if self.last_interface_check.elapsed() >= INTERFACE_POLL_INTERVAL {
+            self.check_network_interfaces();
+        }
+
+        // End a timed connection loss simulation
+        if self.simulation_end.is_some_and(|end| Instant::now() >= end) {
+            self.simulate_connection_restore();
+        }
+
+        if self.last_action.is_some() && self.last_action_refresh.elapsed() >= LAST_ACTION_REFRESH_INTERVAL {
+            self.update_last_action_labels();
+        }
+
+        if !self.held_since.is_empty() && self.button_held_rate_hz > 0.0 {
+            self.emit_button_held();
+        }
+
+        if !self.pending_axes.is_empty() {
+            self.push_pending_axes();
+        }
+
+        if self.match_timer.is_running() {
+            self.update_match_phase();
+        }
+
+        if self.warning_flash.is_some() {
+            self.update_warning_flash();
+        }
+
+        for written in self.report_writer.poll() {
+            let path = GString::from(written.path.as_str());
+            let (what, written_signal, failed_signal) = match written.kind {
+                WriteKind::MatchReport => ("match report", "match_report_written", "match_report_failed"),
+                WriteKind::ScoutingCsv => ("scouting CSV", "scouting_csv_written", "scouting_csv_failed"),
+            };
+            match written.error {
+                None => {
+                    log_info!("Wrote {} to {}", what, path);
+                    self.emit(written_signal, &[path.to_variant()]);
+                }
+                Some(error) => {
+                    log_error!("Failed to write {} {}: {}", what, path, error);
+                    let args = [path.to_variant(), GString::from(error.as_str()).to_variant()];
+                    self.emit(failed_signal, &args);
+                }
+            }
+        }
+
+        if let Some(score) = &self.score {
+            for event in score.runner.poll() {
+                self.apply_score_event(event);
+            }
+        }
+
+        if self.auto_zero_at.is_some_and(|at| Instant::now() >= at) {
+            self.fire_auto_zero();
+        }
+
+        if let Some((_, runner)) = &self.running_sequence {
+            for event in runner.poll() {
+                self.apply_sequence_event(event);
+                if self.running_sequence.is_none() {
+                    break;
+                }
+            }
+        }
+
+        if self.override_expires.is_some_and(|end| Instant::now() >= end) {
+            log_warn!("Force connected expired, back to normal probing");
+            self.set_override_mode(OverrideMode::Normal);
+            self.emit("override_expired", &[]);
+        }
+
+        // Check if it's time to ping again, real probing is suspended while
+        // a connection loss is being simulated
+        let paused = (self.pause_when_hidden && self.probing_paused) || self.simulating_connection_loss;
+        if !paused && self.last_ping_time.elapsed() >= self.next_ping_delay {
+            self.ping_tcp_server();
+            self.last_ping_time = Instant::now();
+        }
+
+        // Pick up finished probes
+        if let Some(result) = self.ping_worker.as_mut().and_then(|worker| worker.poll()) {
+            self.apply_ping_result(result);
+        }
+
+        if let Some(results) = self.service_monitor.as_mut().and_then(|monitor| monitor.poll()) {
+            self.apply_service_results(results);
+        }
+
+        if self.discovery.is_some() {
+            self.poll_discovery();
+        }
+
+        if let Some(reachable) = self.radio_probe.as_ref().and_then(|probe| probe.poll()) {
+            self.radio_probe = None;
+            self.set_network_diagnosis(if reachable {
+                NetworkDiagnosis::RioDown
+            } else {
+                NetworkDiagnosis::Unknown
+            });
+        }
+
+        // Refresh the status endpoint snapshot
+        if self.status_server.is_running() && self.last_status_update.elapsed() >= STATUS_UPDATE_INTERVAL {
+            self.status_server.update(self.status_json());
+            self.last_status_update = Instant::now();
+        }
+
+        // Forward the controller state to the simulation
+        if let (Some(sim), Some(controller)) = (self.sim_output.as_mut(), self.virtual_controller.as_ref()) {
+            sim.poll(&controller.pressed_buttons(), &controller.spare_buttons(), controller.axes());
+        }
+
+        if self.status_indicator.is_some() || self.status_label.is_some() {
+            self.update_status_indicator();
+        }
+
+        // Handle remote clients
+        if self.remote_server.is_running() {
+            self.poll_remote_server();
+        }
+
+        if self.authority_enabled {
+            self.update_authority();
+        }
+
+        // Also picks up lockout_during_auto being changed from the inspector
+        self.update_auto_lockout();
+
+        if self.virtual_controller.is_some() {
+            self.check_controller_thread();
+        }
+
+        if !self.pending_taps.is_empty() {
+            self.release_finished_taps();
+        }
+
+        if !self.pending_holds.is_empty() {
+            self.update_pending_holds();
+        }
+
+        self.resolve_group_deselects();
+        if std::mem::take(&mut self.face_deselect_pending) {
+            self.set_face("none".into());
+        }
+        if self.hp_signal_release_at.is_some_and(|release_at| Instant::now() >= release_at) {
+            self.hp_signal_release_at = None;
+            self.apply_spare_bits();
+        }
+
+        if matches!(&self.pending_confirmation, Some((_, deadline)) if Instant::now() >= *deadline) {
+            self.cancel_confirmation("timeout");
+        }
+
+        if self.pending_coach.is_some() {
+            self.update_coach_confirmation();
+        }
+
+        if !self.pending_acks.is_empty() {
+            self.expire_pending_acks();
+        }
+        if !self.ack_errors.is_empty() {
+            self.update_ack_flash();
+        }
+
+        if matches!(&self.prematch, Some((_, deadline)) if Instant::now() >= *deadline) {
+            self.finish_prematch_latency(false, "No probe result in time".into());
+        }
+
+        if self.command_link.is_some() {
+            self.poll_command_link();
+        }
+    }
+
+    fn on_notification(&mut self, what: NodeNotification) {
+        if Self::in_editor() {
+            return;
+        }
+
+        match what {
+            NodeNotification::APPLICATION_PAUSED => {
+                self.on_focus_lost("paused");
+                self.set_probing_paused(true);
+            }
+            NodeNotification::APPLICATION_FOCUS_OUT | NodeNotification::WM_WINDOW_FOCUS_OUT => {
+                self.on_focus_lost("focus_lost");
+                if Self::window_minimized() {
+                    self.set_probing_paused(true);
+                }
+            }
+            NodeNotification::APPLICATION_RESUMED | NodeNotification::APPLICATION_FOCUS_IN => {
+                self.window_focused = true;
+                self.set_probing_paused(false)
+            }
+            NodeNotification::WM_WINDOW_FOCUS_IN => self.window_focused = true,
+            _ => {}
+        }
+    }
+
+    fn input(&mut self, event: Gd<InputEvent>) {
+        if Self::in_editor() {
+            return;
+        }
+
+        if self.focus_activation && self.handle_focus_accept(&event) {
+            if let Some(mut viewport) = self.node().get_viewport() {
+                viewport.set_input_as_handled();
+            }
+            return;
+        }
+
+        if self.touch_pointers.is_empty() {
+            return;
+        }
+
+        // A finger holds its action until it lifts, wherever it has moved to.
+        // The event is left unhandled so the button sees it too
+        let pointer = if let Ok(touch) = event.clone().try_cast::<InputEventScreenTouch>() {
+            if touch.is_pressed() {
+                return;
+            }
+            touch.get_index()
+        } else if let Ok(click) = event.try_cast::<InputEventMouseButton>() {
+            if click.is_pressed() || click.get_device() == EMULATED_MOUSE_DEVICE || click.get_button_index() != MouseButton::LEFT {
+                return;
+            }
+            MOUSE_POINTER
+        } else {
+            return;
+        };
+
+        if let Some(name) = self.touch_pointers.remove(&pointer) {
+            self.source_released(&name, InputOrigin::Ui);
+        }
+    }
+
+    fn unhandled_input(&mut self, event: Gd<InputEvent>) {
+        if Self::in_editor() {
+            return;
+        }
+
+        if !self.button_box_bindings.is_empty() {
+            if let Ok(joypad) = event.clone().try_cast::<InputEventJoypadButton>() {
+                if joypad.get_device() == self.button_box_device as i32 {
+                    self.handle_button_box(joypad);
+                    return;
+                }
+            }
+        }
+
+        if self.input_action_bindings.is_empty() {
+            return;
+        }
+
+        let input_map = InputMap::singleton();
+        let mut handled = false;
+        for (input_action, button) in self.input_action_bindings.iter_shared() {
+            let input_action = StringName::from(input_action.to_string().as_str());
+            let button = button.to_string();
+            if !input_map.has_action(&input_action) {
+                continue;
+            }
+
+            // Key repeat arrives as echo presses, which is_action_pressed skips
+            let key = input_action.to_string();
+            if event.is_action_pressed(&input_action) && self.held_input_actions.insert(key.clone()) {
+                self.source_pressed(&button, InputOrigin::Keyboard);
+                handled = true;
+            } else if event.is_action_released(&input_action) && self.held_input_actions.remove(&key) {
+                self.source_released(&button, InputOrigin::Keyboard);
+                handled = true;
+            }
+        }
+
+        if handled {
+            if let Some(mut viewport) = self.node().get_viewport() {
+                viewport.set_input_as_handled();
+            }
+        }
+    }
+
+    fn configuration_warnings(&self) -> PackedStringArray {
+        self.configuration_problems()
+            .iter()
+            .map(|problem| GString::from(problem.as_str()))
+            .collect()
+    }
+
+    fn exit_tree(&mut self) {
+        if Self::in_editor() {
+            return;
+        }
+
+        self.stop();
+    }
+
+    /// Plug in the controller and start monitoring the robot. Does nothing
+    /// if already running.
+    fn start(&mut self) {
+        if self.running || Self::in_editor() {
+            return;
+        }
+        self.running = true;
+        self.inputs_locked = false;
+        self.arm_override_expiry();
+        self.presses_this_session = 0;
+        self.clear_last_action();
+        self.undo_history.clear();
+
+        // Initialize the virtual controller
+        self.init_controller();
+
+        // Start a new connection log for this session
+        self.start_time = Instant::now();
+        self.connection_history = ConnectionHistory::new(self.connected);
+        if self.connection_log_enabled {
+            self.open_connection_log();
+        }
+
+        // Baseline for interface change detection
+        self.local_addresses = Self::read_local_addresses();
+        self.last_interface_check = Instant::now();
+
+        // Perform initial ping
+        self.ping_worker = Some(PingWorker::new());
+        self.service_monitor = Some(ServiceMonitor::new());
+        self.ping_tcp_server();
+
+        // Start the pit status endpoint if requested
+        if self.status_server_enabled {
+            self.start_status_server();
+        }
+
+        // Start the remote control server if requested
+        if self.remote_server_enabled {
+            self.start_remote_server();
+        }
+
+        if self.authority_enabled {
+            self.start_authority();
+        }
+
+        // Connect to the coprocessor if one is configured
+        if !self.command_address.is_empty() {
+            self.start_command_link();
+        }
+
+        self.emit("started", &[]);
+    }
+
+    /// Stop monitoring, release everything and unplug the controller.
+    /// Presses are rejected until start() is called again.
+    fn stop(&mut self) {
+        if !self.running {
+            return;
+        }
+
+        // Nothing stays held, and the UI sees the link go down
+        self.running = false;
+        self.neutralize_inputs("stopped");
+        self.primary_up = false;
+        self.set_connected(false);
+        self.restore_window_title();
+
+        // Drop remote clients and release anything they held
+        self.stop_remote_server();
+        self.authority_link.stop();
+
+        // Tell every background worker to stop before waiting on any, so
+        // together they take one SHUTDOWN_TIMEOUT at most
+        self.status_server.begin_stop();
+        if let Some(worker) = &mut self.ping_worker {
+            worker.begin_shutdown();
+        }
+        if let Some(monitor) = &mut self.service_monitor {
+            monitor.begin_shutdown();
+        }
+        if let Some(discovery) = &self.discovery {
+            discovery.cancel();
+        }
+        if let Some(link) = &mut self.command_link {
+            link.begin_shutdown();
+        }
+        if let Some(log) = &mut self.connection_log {
+            log.begin_close();
+        }
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+
+        self.status_server.stop_by(deadline);
+        if let Some(mut worker) = self.ping_worker.take() {
+            worker.shutdown_by(deadline);
+        }
+        if let Some(mut monitor) = self.service_monitor.take() {
+            monitor.shutdown_by(deadline);
+        }
+        if let Some(mut discovery) = self.discovery.take() {
+            discovery.shutdown_by(deadline);
+        }
+        self.stop_command_link_by(deadline);
+
+        // Flush and close the connection log
+        if let Some(mut log) = self.connection_log.take() {
+            log.close_by(deadline);
+        }
+
+        // Leave the simulated joystick neutral
+        if let Some(mut sim) = self.sim_output.take() {
+            sim.close();
+        }
+
+        // Let go of the virtual controller. Dropping the handle releases what
+        // this node held, and only that, other scenes may still hold the pad
+        self.virtual_controller = None;
+
+        self.emit("stopped", &[]);
+    }
+
+    /// Take settings from the config resource. A setting set on the node, in
+    /// the scene, by a script or from saved settings, keeps the node's
+    /// value, even one that happens to equal the default.
+    fn apply_config(&mut self) {
+        let Some(config) = self.config.clone() else {
+            log_warn!("No config resource to apply");
+            return;
+        };
+        let config = config.bind();
+        let explicit = self.explicit_config;
+        let from_config = |field| !explicit.contains(field);
+
+        if from_config(ConfigField::PingAddress) || from_config(ConfigField::PingPort) {
+            let address = if from_config(ConfigField::PingAddress) {
+                config.ping_address.to_string()
+            } else {
+                self.ping_address.to_string()
+            };
+            let port = if from_config(ConfigField::PingPort) { config.ping_port } else { self.ping_port };
+            self.change_endpoint_to(&address, port);
+        }
+        if from_config(ConfigField::PingInterval) {
+            self.ping_interval = Duration::from_secs_f64(config.ping_interval_secs.max(1.0));
+            self.next_ping_delay = self.ping_interval;
+        }
+        if from_config(ConfigField::PingTimeout) {
+            self.ping_timeout_ms = config.ping_timeout_ms.clamp(MIN_PING_TIMEOUT_MS, MAX_PING_TIMEOUT_MS);
+        }
+        if from_config(ConfigField::PersistentConnection) {
+            self.persistent_connection = config.persistent_connection;
+        }
+        if from_config(ConfigField::FailuresBeforeDisconnect) {
+            self.failures_before_disconnect = config.failures_before_disconnect;
+        }
+        if from_config(ConfigField::SuccessesBeforeConnect) {
+            self.successes_before_connect = config.successes_before_connect;
+        }
+        if from_config(ConfigField::LocalBindAddress) {
+            self.local_bind_address = config.local_bind_address.clone();
+        }
+        if from_config(ConfigField::TeamNumber) {
+            self.team_number = config.team_number;
+        }
+        if from_config(ConfigField::Services) {
+            self.services = config.services.duplicate_shallow();
+        }
+        if from_config(ConfigField::Devices) {
+            self.devices = config.devices.duplicate_shallow();
+        }
+        if from_config(ConfigField::ButtonBindings) {
+            self.button_bindings = config.button_bindings.duplicate_shallow();
+        }
+        if from_config(ConfigField::AckTimeout) {
+            self.ack_timeout_ms = config.ack_timeout_ms;
+        }
+        if from_config(ConfigField::ModeMappings) {
+            self.mode_mappings = config.mode_mappings.duplicate_shallow();
+        }
+        if from_config(ConfigField::Cooldown) {
+            self.cooldown = config.cooldown.duplicate_shallow();
+        }
+    }
+
+    /// Snapshot the node's current settings into a new config resource,
+    /// e.g. to save it with ResourceSaver.
+    fn extract_config(&self) -> Gd<FRCInterfaceConfig> {
+        let mut config = FRCInterfaceConfig::new_gd();
+        {
+            let mut c = config.bind_mut();
+            c.ping_address = self.ping_address.clone();
+            c.ping_port = self.ping_port;
+            c.ping_interval_secs = self.ping_interval.as_secs_f64();
+            c.ping_timeout_ms = self.ping_timeout_ms;
+            c.persistent_connection = self.persistent_connection;
+            c.failures_before_disconnect = self.failures_before_disconnect;
+            c.successes_before_connect = self.successes_before_connect;
+            c.local_bind_address = self.local_bind_address.clone();
+            c.team_number = self.team_number;
+            c.services = self.services.duplicate_shallow();
+            c.devices = self.devices.duplicate_shallow();
+            c.button_bindings = self.button_bindings.duplicate_shallow();
+            c.ack_timeout_ms = self.ack_timeout_ms;
+            c.mode_mappings = self.mode_mappings.duplicate_shallow();
+            c.cooldown = self.cooldown.duplicate_shallow();
+        }
+        config
+    }
+
+    // Setters of the exports the config resource also covers, which mark
+    // them as set on the node
+
+    fn set_persistent_connection(&mut self, value: bool) {
+        self.persistent_connection = value;
+        self.explicit_config.mark(ConfigField::PersistentConnection);
+    }
+
+    fn set_failures_before_disconnect(&mut self, value: i64) {
+        self.failures_before_disconnect = value;
+        self.explicit_config.mark(ConfigField::FailuresBeforeDisconnect);
+    }
+
+    fn set_successes_before_connect(&mut self, value: i64) {
+        self.successes_before_connect = value;
+        self.explicit_config.mark(ConfigField::SuccessesBeforeConnect);
+    }
+
+    fn set_local_bind_address(&mut self, value: GString) {
+        self.local_bind_address = value;
+        self.explicit_config.mark(ConfigField::LocalBindAddress);
+    }
+
+    fn set_team_number(&mut self, value: i64) {
+        self.team_number = value;
+        self.explicit_config.mark(ConfigField::TeamNumber);
+    }
+
+    fn set_services(&mut self, value: Dictionary) {
+        self.services = value;
+        self.explicit_config.mark(ConfigField::Services);
+    }
+
+    fn set_devices(&mut self, value: Array<Dictionary>) {
+        self.devices = value;
+        self.explicit_config.mark(ConfigField::Devices);
+    }
+
+    fn set_button_bindings(&mut self, value: Array<Dictionary>) {
+        self.button_bindings = value;
+        self.explicit_config.mark(ConfigField::ButtonBindings);
+    }
+
+    fn set_ack_timeout_ms(&mut self, value: i64) {
+        self.ack_timeout_ms = value;
+        self.explicit_config.mark(ConfigField::AckTimeout);
+    }
+
+    fn set_mode_mappings(&mut self, value: Dictionary) {
+        self.mode_mappings = value;
+        self.explicit_config.mark(ConfigField::ModeMappings);
+    }
+
+    fn set_cooldown(&mut self, value: Dictionary) {
+        self.cooldown = value;
+        self.explicit_config.mark(ConfigField::Cooldown);
+    }
+
+    /// Save the runtime settings to user://frc_interface.cfg.
+    fn save_settings(&mut self) -> bool {
+        let mut file = ConfigFile::new_gd();
+        let mut set = |section: &str, key: &str, value: Variant| file.set_value(section, key, &value);
+
+        set("network", "ping_address", self.ping_address.to_variant());
+        set("network", "ping_port", self.ping_port.to_variant());
+        set("network", "ping_interval_secs", self.ping_interval.as_secs_f64().to_variant());
+        set("network", "ping_timeout_ms", self.ping_timeout_ms.to_variant());
+        set("network", "persistent_connection", self.persistent_connection.to_variant());
+        set("network", "failures_before_disconnect", self.failures_before_disconnect.to_variant());
+        set("network", "successes_before_connect", self.successes_before_connect.to_variant());
+        set("network", "local_bind_address", self.local_bind_address.to_variant());
+        set("network", "team_number", self.team_number.to_variant());
+        set("network", "services", self.services.to_variant());
+        set("network", "devices", self.devices.to_variant());
+        set("buttons", "button_bindings", self.button_bindings.to_variant());
+        set("buttons", "lock_inputs_on_disconnect", self.lock_inputs_on_disconnect.to_variant());
+        set(
+            "buttons",
+            "disable_buttons_when_disconnected",
+            self.disable_buttons_when_disconnected.to_variant(),
+        );
+        set("buttons", "ack_enabled", self.ack_enabled.to_variant());
+        set("buttons", "ack_timeout_ms", self.ack_timeout_ms.to_variant());
+        set("general", "pause_when_hidden", self.pause_when_hidden.to_variant());
+        set("general", "connection_log_enabled", self.connection_log_enabled.to_variant());
+        set("general", "log_level", self.log_level.to_variant());
+
+        let result = file.save(SETTINGS_PATH);
+        if result != godot::global::Error::OK {
+            log_error!("Failed to save settings to {}: {:?}", SETTINGS_PATH, result);
+            return false;
+        }
+        true
+    }
+
+    /// Load settings saved by save_settings(). A missing or corrupt file
+    /// leaves the current values alone, as does any entry of the wrong type.
+    fn load_settings(&mut self) -> bool {
+        let mut file = ConfigFile::new_gd();
+        let result = file.load(SETTINGS_PATH);
+        if result == godot::global::Error::ERR_FILE_NOT_FOUND {
+            log_info!("No saved settings at {}, using the scene's values", SETTINGS_PATH);
+            return false;
+        }
+        if result != godot::global::Error::OK {
+            log_warn!("Could not read settings from {} ({:?}), using the scene's values", SETTINGS_PATH, result);
+            return false;
+        }
+
+        fn get<T: FromGodot>(file: &Gd<ConfigFile>, section: &str, key: &str) -> Option<T> {
+            if !file.has_section_key(section, key) {
+                return None;
+            }
+            match file.get_value(section, key).try_to::<T>() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    log_warn!("Ignoring saved setting {}/{} of the wrong type", section, key);
+                    None
+                }
+            }
+        }
+
+        let port = get::<i64>(&file, "network", "ping_port");
+        let address = get::<GString>(&file, "network", "ping_address");
+        if port.is_some() {
+            self.explicit_config.mark(ConfigField::PingPort);
+        }
+        if address.is_some() {
+            self.explicit_config.mark(ConfigField::PingAddress);
+        }
+        let port = port.unwrap_or(self.ping_port);
+        match address {
+            Some(address) => self.change_endpoint_to(&address.to_string(), port),
+            None => self.change_endpoint(self.ping_address.to_string(), port),
+        }
+        if let Some(secs) = get::<f64>(&file, "network", "ping_interval_secs") {
+            self.ping_interval = Duration::from_secs_f64(secs.max(1.0));
+            self.next_ping_delay = self.ping_interval;
+            self.explicit_config.mark(ConfigField::PingInterval);
+        }
+        if let Some(timeout) = get::<i64>(&file, "network", "ping_timeout_ms") {
+            self.set_ping_timeout_ms(timeout);
+        }
+        if let Some(value) = get(&file, "network", "persistent_connection") {
+            self.set_persistent_connection(value);
+        }
+        if let Some(value) = get(&file, "network", "failures_before_disconnect") {
+            self.set_failures_before_disconnect(value);
+        }
+        if let Some(value) = get(&file, "network", "successes_before_connect") {
+            self.set_successes_before_connect(value);
+        }
+        if let Some(value) = get(&file, "network", "local_bind_address") {
+            self.set_local_bind_address(value);
+        }
+        if let Some(value) = get(&file, "network", "team_number") {
+            self.set_team_number(value);
+        }
+        if let Some(value) = get(&file, "network", "services") {
+            self.set_services(value);
+        }
+        if let Some(value) = get(&file, "network", "devices") {
+            self.set_devices(value);
+        }
+        if let Some(value) = get(&file, "buttons", "button_bindings") {
+            self.set_button_bindings(value);
+        }
+        if let Some(value) = get(&file, "buttons", "lock_inputs_on_disconnect") {
+            self.lock_inputs_on_disconnect = value;
+        }
+        if let Some(value) = get(&file, "buttons", "disable_buttons_when_disconnected") {
+            self.set_disable_buttons_when_disconnected(value);
+        }
+        if let Some(value) = get(&file, "buttons", "ack_enabled") {
+            self.ack_enabled = value;
+        }
+        if let Some(value) = get(&file, "buttons", "ack_timeout_ms") {
+            self.set_ack_timeout_ms(value);
+        }
+        if let Some(value) = get(&file, "general", "pause_when_hidden") {
+            self.pause_when_hidden = value;
+        }
+        if let Some(value) = get(&file, "general", "connection_log_enabled") {
+            self.connection_log_enabled = value;
+        }
+        if let Some(value) = get(&file, "general", "log_level") {
+            self.set_log_level(value);
+        }
+
+        log_info!("Loaded settings from {}", SETTINGS_PATH);
+        self.emit("settings_loaded", &[]);
+        true
+    }
+
+    fn update_status_indicator(&mut self) {
+        let ping_in_flight = self.ping_worker.as_ref().is_some_and(|worker| worker.is_in_flight());
+        let degraded = self.consecutive_failures > 0
+            || (self.verification_mode != VerificationMode::None && !self.verified);
+
+        // A simulated connection loss wins over the override
+        let override_active = !self.simulating_connection_loss;
+        let (color, template) = if override_active && self.override_mode == OverrideMode::ForceConnected {
+            (self.color_forced, &self.text_forced)
+        } else if override_active && self.override_mode == OverrideMode::ForceDisconnected {
+            (self.color_forced, &self.text_forced_disconnected)
+        } else if ping_in_flight || (self.connected && degraded) {
+            // A probe is out, up or down, or the link is up but shaky
+            (self.color_degraded, &self.text_degraded)
+        } else if !self.connected {
+            (self.color_disconnected, &self.text_disconnected)
+        } else {
+            (self.color_connected, &self.text_connected)
+        };
+
+        let latency = self
+            .ping_stats
+            .last
+            .map_or(String::from("-"), |latency| format!("{:.0}", latency.as_secs_f64() * 1000.0));
+        let text = template
+            .to_string()
+            .replace("{latency}", &latency)
+            .replace("{error}", &self.last_error.to_string())
+            .replace("{address}", &self.endpoint.to_string());
+        let text = if self.dry_run {
+            format!("{}{}", self.text_dry_run, text)
+        } else {
+            text
+        };
+
+        // Only touch the nodes when something changed
+        let state = Some((color, text));
+        if self.last_indicator_state == state {
+            return;
+        }
+        self.last_indicator_state = state;
+
+        let Some((color, text)) = &self.last_indicator_state else {
+            return;
+        };
+        if let Some(indicator) = self.status_indicator.as_mut() {
+            indicator.set_modulate(*color);
+        }
+        if let Some(label) = self.status_label.as_mut() {
+            label.set_text(text.as_str());
+        }
+    }
+
+    /// Select a reef level, holding its button until the level changes.
+    /// "none" lets go of it.
+    fn set_selected_level(&mut self, level: GString) {
+        self.select_level(level, InputOrigin::Ui);
+    }
+
+    fn select_level(&mut self, level: GString, origin: InputOrigin) {
+        let new = level.to_string().to_lowercase();
+        if new != "none" && !LEVELS.contains(&new.as_str()) {
+            log_warn!("Unknown level \"{}\", expected none, low, mid or high", level);
+            return;
+        }
+
+        let old = self.selected_level.to_string();
+        if old == new {
+            return;
+        }
+        self.selected_level = GString::from(new.as_str());
+
+        self.applying_level = true;
+        let holder = std::mem::replace(&mut self.level_holder, origin);
+        match (old.as_str(), new.as_str()) {
+            ("none", new) => {
+                self.source_pressed(new, origin);
+            }
+            (old, "none") => {
+                self.source_released(old, holder);
+            }
+            (old, new) => self.switch_action(old, new, holder, origin),
+        }
+        self.applying_level = false;
+
+        log_info!("Level {} -> {}", old, new);
+        let args = [StringName::from(new.as_str()).to_variant(), StringName::from(old.as_str()).to_variant()];
+        self.emit("level_changed", &args);
+    }
+
+    /// Select `level`, wait score_settle_ms, then tap coral for
+    /// score_tap_ms. Timed on a worker like run_sequence(), and aborted by
+    /// anything that neutralizes the inputs.
+    fn score_coral(&mut self, level: GString) -> ActionResult {
+        let level = level.to_string().to_lowercase();
+        if !LEVELS.contains(&level.as_str()) {
+            log_warn!("Cannot score at unknown level \"{}\"", level);
+            return ActionResult::UnknownButton;
+        }
+        if self.score.is_some() {
+            log_warn!("Already scoring, ignoring score_coral({})", level);
+            return ActionResult::Busy;
+        }
+        for action in [level.as_str(), "coral"] {
+            if let Err(result) = self.check_press(action, InputOrigin::Macro) {
+                return result;
+            }
+        }
+
+        self.select_level(GString::from(level.as_str()), InputOrigin::Macro);
+        let settle = Duration::from_millis(self.score_settle_ms.max(0) as u64);
+        let tap = Duration::from_millis(self.score_tap_ms.max(0) as u64);
+        self.score = Some(ScoreSequence {
+            level: level.clone(),
+            runner: SequenceRunner::start(vec![Step::Wait(settle), Step::Tap("coral".into(), tap)]),
+        });
+        self.emit("score_started", &[StringName::from(level.as_str()).to_variant()]);
+        ActionResult::Ok
+    }
+
+    fn apply_score_event(&mut self, event: SequenceEvent) {
+        // Events left over from a run that was aborted
+        if self.score.is_none() {
+            return;
+        }
+
+        match event {
+            // The level has settled, start the coral tap
+            SequenceEvent::Step(1) => {
+                if !self.hold_action("coral", InputOrigin::Macro).going_out() {
+                    self.finish_score(false);
+                }
+            }
+            SequenceEvent::TapEnd(_) => {
+                self.unhold_action("coral", InputOrigin::Macro);
+                if self.score_clears_level {
+                    self.select_level("none".into(), InputOrigin::Macro);
+                }
+                self.finish_score(true);
+            }
+            SequenceEvent::Step(_) | SequenceEvent::Finished => {}
+        }
+    }
+
+    /// Load a JSON timeline from `path` for run_sequence(). It's checked
+    /// against the known actions and axes, and not loaded if anything in
+    /// it is wrong.
+    fn load_sequence(&mut self, path: GString) -> godot::global::Error {
+        let file = ProjectSettings::singleton().globalize_path(&path).to_string();
+        let text = match std::fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(e) => {
+                log_warn!("Failed to read sequence {}: {}", path, e);
+                return godot::global::Error::ERR_FILE_CANT_OPEN;
+            }
+        };
+        let sequence = match Sequence::parse(&text) {
+            Ok(sequence) => sequence,
+            Err(e) => {
+                log_warn!("Failed to parse sequence {}: {}", path, e);
+                return godot::global::Error::ERR_PARSE_ERROR;
+            }
+        };
+
+        for (index, step) in sequence.steps.iter().enumerate() {
+            let problem = match step {
+                Step::Press(action) | Step::Release(action) | Step::Tap(action, _)
+                    if !Self::is_known_action(action) =>
+                {
+                    Some(format!("unknown action \"{}\"", action))
+                }
+                Step::Axis(axis, _) if !AXIS_NAMES.contains(&axis.as_str()) => {
+                    Some(format!("unknown axis \"{}\"", axis))
+                }
+                _ => None,
+            };
+            if let Some(problem) = problem {
+                log_warn!("Sequence {} step {}: {}", path, index, problem);
+                return godot::global::Error::ERR_INVALID_DATA;
+            }
+        }
+
+        log_info!("Loaded sequence {} ({} steps)", path, sequence.steps.len());
+        self.loaded_sequence = Some(sequence);
+        godot::global::Error::OK
+    }
+
+    /// Run the loaded sequence from the start. Each step goes through the
+    /// same checks as a press, and one that is turned down stops it.
+    fn run_sequence(&mut self) -> godot::global::Error {
+        let Some(sequence) = self.loaded_sequence.clone() else {
+            log_warn!("No sequence loaded");
+            return godot::global::Error::ERR_UNCONFIGURED;
+        };
+        if self.running_sequence.is_some() {
+            log_warn!("A sequence is already running");
+            return godot::global::Error::ERR_BUSY;
+        }
+
+        log_info!("Running sequence ({} steps)", sequence.steps.len());
+        let runner = SequenceRunner::start(sequence.steps.clone());
+        self.running_sequence = Some((sequence, runner));
+        godot::global::Error::OK
+    }
+
+    fn stop_sequence(&mut self) {
+        if self.running_sequence.is_some() {
+            self.finish_sequence(false, "stopped");
+        }
+    }
+
+    fn is_sequence_running(&self) -> bool {
+        self.running_sequence.is_some()
+    }
+
+    fn apply_sequence_event(&mut self, event: SequenceEvent) {
+        let (index, step) = match event {
+            SequenceEvent::Finished => {
+                self.finish_sequence(true, "finished");
+                return;
+            }
+            SequenceEvent::Step(index) | SequenceEvent::TapEnd(index) => {
+                let Some((sequence, _)) = &self.running_sequence else {
+                    return;
+                };
+                let Some(step) = sequence.steps.get(index).cloned() else {
+                    return;
+                };
+                if let SequenceEvent::Step(index) = event {
+                    let description = GString::from(sequence.describe(index));
+                    let args = [(index as i64).to_variant(), description.to_variant()];
+                    self.emit("sequence_step", &args);
+                }
+                (index, step)
+            }
+        };
+        let tap_end = matches!(event, SequenceEvent::TapEnd(_));
+
+        let result = match &step {
+            Step::Press(action) => self.sequence_press(action),
+            Step::Tap(action, _) if !tap_end => self.sequence_press(action),
+            Step::Release(action) | Step::Tap(action, _) => {
+                self.sequence_held.remove(action);
+                self.unhold_action(action, InputOrigin::Sequence)
+            }
+            Step::Axis(axis, value) => {
+                self.sequence_axes.insert(axis.clone());
+                self.set_axis(axis.as_str().into(), *value)
+            }
+            Step::Wait(_) => ActionResult::Ok,
+        };
+        if !result.going_out() {
+            log_warn!("Sequence step {} ({}) failed: {:?}", index, step.describe(), result);
+            self.finish_sequence(false, &format!("step {} failed: {:?}", index, result));
+        }
+    }
+
+    fn sequence_press(&mut self, action: &str) -> ActionResult {
+        // Pressing what the sequence already holds adds no second hold
+        if self.sequence_held.contains(action) {
+            return self.press_action(action, InputOrigin::Sequence);
+        }
+        let result = self.hold_action(action, InputOrigin::Sequence);
+        if result.going_out() {
+            self.sequence_held.insert(action.to_string());
+        }
+        result
+    }
+
+    /// Stop the running sequence and let go of whatever it has down.
+    fn finish_sequence(&mut self, completed: bool, reason: &str) {
+        let Some((_, mut runner)) = self.running_sequence.take() else {
+            return;
+        };
+        runner.stop();
+
+        // Buttons and axes come back to rest in one report
+        let controller = self.virtual_controller.clone();
+        let batch = controller.as_ref().map(|controller| controller.batch());
+        for action in std::mem::take(&mut self.sequence_held) {
+            self.unhold_action(&action, InputOrigin::Sequence);
+        }
+        let axes = std::mem::take(&mut self.sequence_axes);
+        if let Some(controller) = &controller {
+            for axis in axes {
+                // A pad that is shutting down has nothing left to zero
+                let _ = controller.set_axis(&axis, 0.0);
+            }
+        }
+        drop(batch);
+
+        if completed {
+            log_info!("Sequence finished");
+        } else {
+            log_warn!("Sequence stopped: {}", reason);
+        }
+        let args = [completed.to_variant(), GString::from(reason).to_variant()];
+        self.emit("sequence_finished", &args);
+    }
+
+    fn schedule_auto_zero(&mut self) {
+        let delay = Duration::from_millis(self.auto_zero_delay_ms.max(0) as u64);
+        self.auto_zero_at = Some(Instant::now() + delay);
+        log_info!("Climb released, zero in {} ms", delay.as_millis());
+        let args = [StringName::from("zero").to_variant(), delay.as_secs_f64().to_variant()];
+        self.emit("auto_action_scheduled", &args);
+    }
+
+    fn cancel_auto_zero(&mut self) {
+        if self.auto_zero_at.take().is_some() {
+            log_info!("Scheduled zero cancelled");
+        }
+    }
+
+    fn fire_auto_zero(&mut self) {
+        self.auto_zero_at = None;
+        let tap = Duration::from_millis(self.auto_zero_tap_ms.max(0) as u64);
+        let result = self.start_tap("zero", InputOrigin::Macro, tap);
+        if !result.going_out() {
+            log_warn!("Scheduled zero not sent: {:?}", result);
+        }
+        let args = [StringName::from("zero").to_variant(), result.to_variant()];
+        self.emit("auto_action_fired", &args);
+    }
+
+    fn finish_score(&mut self, completed: bool) {
+        let Some(score) = self.score.take() else {
+            return;
+        };
+        if !completed {
+            log_warn!("Scoring at {} aborted", score.level);
+        }
+        let args = [StringName::from(score.level.as_str()).to_variant(), completed.to_variant()];
+        self.emit("score_finished", &args);
+    }
+
+    fn emit_button_held(&mut self) {
+        let interval = Duration::from_secs_f64(1.0 / self.button_held_rate_hz);
+        if self.last_button_held.elapsed() < interval {
+            return;
+        }
+        self.last_button_held = Instant::now();
+
+        let held: Vec<(String, f64)> = self
+            .held_since
+            .iter()
+            .map(|(name, since)| (name.clone(), since.elapsed().as_secs_f64()))
+            .collect();
+        for (name, elapsed) in held {
+            let args = [StringName::from(name.as_str()).to_variant(), elapsed.to_variant()];
+            self.emit("button_held", &args);
+        }
+    }
+
+    /// Seconds `name` has been down on the controller, 0 if it isn't.
+    /// Latched toggle buttons count as held.
+    fn get_hold_duration(&self, name: GString) -> f64 {
+        self.held_since
+            .get(&name.to_string())
+            .map_or(0.0, |since| since.elapsed().as_secs_f64())
+    }
+
+    /// Put the last action labels back to text_no_action.
+    fn clear_last_action(&mut self) {
+        self.last_action = None;
+        self.update_last_action_labels();
+    }
+
+    fn update_last_action_labels(&mut self) {
+        self.last_action_refresh = Instant::now();
+
+        let (action_text, time_text) = match &self.last_action {
+            Some((action, origin, at)) => {
+                let fill = |format: &GString| {
+                    format
+                        .to_string()
+                        .replace("{action}", action)
+                        .replace("{origin}", origin.as_str())
+                        .replace("{age}", &format!("{:.1}", at.elapsed().as_secs_f64()))
+                };
+                (fill(&self.last_action_format), fill(&self.last_action_time_format))
+            }
+            None => (self.text_no_action.to_string(), self.text_no_action.to_string()),
+        };
+
+        if let Some(label) = self.last_action_label.as_mut() {
+            label.set_text(action_text.as_str());
+        }
+        if let Some(label) = self.last_action_time_label.as_mut() {
+            label.set_text(time_text.as_str());
+        }
+    }
+
+    fn get_log_level(&self) -> LogLevel {
+        logging::level()
+    }
+
+    /// Sets the level for every node in the process, see log_level.
+    fn set_log_level(&mut self, level: LogLevel) {
+        if level != logging::level() {
+            log_info!("Log level {:?} -> {:?}, for every node", logging::level(), level);
+        }
+        self.log_level = level;
+        logging::set_level(level);
+    }
+
+    fn in_editor() -> bool {
+        Engine::singleton().is_editor_hint()
+    }
+
+    /// Everything the editor should warn about.
+    fn configuration_problems(&self) -> Vec<String> {
+        let (bindings, mut problems) = self.resolve_button_bindings();
+        problems.extend(self.resolve_range_bindings().1);
+
+        if bindings.is_empty() && self.input_action_bindings.is_empty() && self.button_box_bindings.is_empty() {
+            problems.push("No buttons are bound to actions".into());
+        }
+
+        for (input_action, button) in self.input_action_bindings.iter_shared() {
+            if !Self::is_known_action(&button.to_string()) {
+                problems.push(format!("Input action {} is bound to unknown action \"{}\"", input_action, button));
+            }
+        }
+
+        for (index, button) in self.button_box_bindings.iter_shared() {
+            if !Self::is_known_button(&button.to_string()) {
+                problems.push(format!("Button box button {} is bound to unknown action \"{}\"", index, button));
+            }
+        }
+
+        for (index, (action, button)) in bindings.iter().enumerate() {
+            let earlier = bindings[..index]
+                .iter()
+                .find(|(_, other)| other.instance_id() == button.instance_id());
+            if let Some((other_action, _)) = earlier {
+                problems.push(format!("{} is bound to both {} and {}", button.get_name(), other_action, action));
+            }
+        }
+
+        if let Err(e) = parse_endpoint(&self.ping_address.to_string()) {
+            problems.push(format!("ping_address is invalid: {}", e));
+        }
+        if let Some((value, reason)) = &self.rejected_endpoint {
+            problems.push(format!("Ping target \"{}\" was rejected ({}), nothing is probed", value, reason));
+        }
+
+        let ports = [
+            ("ping_port", self.ping_port, true),
+            ("status_server_port", self.status_server_port, self.status_server_enabled),
+            ("remote_server_port", self.remote_server_port, self.remote_server_enabled),
+            ("command_port", self.command_port, !self.command_address.is_empty()),
+            ("halsim_port", self.halsim_port, self.output_mode == OutputMode::HalSim),
+        ];
+        for (name, port, used) in ports {
+            if used && !(1..=65535).contains(&port) {
+                problems.push(format!("{} {} is out of range (1-65535)", name, port));
+            }
+        }
+
+        if !(MIN_PING_TIMEOUT_MS..=MAX_PING_TIMEOUT_MS).contains(&self.ping_timeout_ms) {
+            problems.push(format!(
+                "ping_timeout_ms must be between {} and {}",
+                MIN_PING_TIMEOUT_MS, MAX_PING_TIMEOUT_MS
+            ));
+        }
+        if self.ping_interval < Duration::from_secs(1) {
+            problems.push("Ping interval must be at least 1 second".into());
+        }
+        if self.failures_before_disconnect < 1 || self.successes_before_connect < 1 {
+            problems.push("Hysteresis counts must be at least 1".into());
+        }
+        if self.discovery_attempts_per_sec < 1 || self.discovery_concurrency < 1 {
+            problems.push("Discovery rate and concurrency must be at least 1".into());
+        }
+        if self.error_report_interval_secs <= 0.0 {
+            problems.push("error_report_interval_secs must be positive".into());
+        }
+
+        for (name, secs) in self.cooldown.iter_shared() {
+            if let Err(e) = variant_duration(&secs, 1.0, "seconds") {
+                problems.push(format!("Cooldown for {} {}", name, e));
+            }
+        }
+        for (name, hold_ms) in self.hold_to_activate.iter_shared() {
+            if let Err(e) = variant_duration(&hold_ms, 0.001, "milliseconds") {
+                problems.push(format!("hold_to_activate for {} {}", name, e));
+            }
+        }
+
+        problems
+    }
+
+    fn ping_tcp_server(&mut self) {
+        if self.simulating_connection_loss {
+            return;
+        }
+
+        // Try to connect to the TCP server
+        match self.override_mode {
+            OverrideMode::ForceConnected => {
+                self.set_connected(true);
+                return;
+            }
+            OverrideMode::ForceDisconnected => {
+                self.set_connected(false);
+                return;
+            }
+            OverrideMode::Normal => {}
+        }
+
+        // Never probe a substitute for a target that didn't parse
+        if let Some((value, reason)) = &self.rejected_endpoint {
+            let message = format!("Invalid ping target \"{}\": {}", value, reason);
+            self.ping_stats.record_failure(ConnectionErrorKind::InvalidAddress);
+            self.set_ping_error(ConnectionErrorKind::InvalidAddress, message);
+            return;
+        }
+        if let Err(e) = parse_endpoint(&self.ping_address.to_string()) {
+            self.ping_stats.record_failure(ConnectionErrorKind::InvalidAddress);
+            self.set_ping_error(ConnectionErrorKind::InvalidAddress, format!("Invalid ping address: {}", e));
+            return;
+        }
+
+        let port = match u16::try_from(self.ping_port) {
+            Ok(port) if port != 0 => port,
+            _ => {
+                self.ping_stats.record_failure(ConnectionErrorKind::InvalidAddress);
+                self.set_ping_error(ConnectionErrorKind::InvalidAddress, format!("Invalid ping port: {}", self.ping_port));
+                return;
+            }
+        };
+
+        // Never silently fall back to the default route
+        let local_address = match self.parse_local_bind_address() {
+            Ok(local_address) => local_address,
+            Err(message) => {
+                self.ping_stats.record_failure(ConnectionErrorKind::InvalidAddress);
+                self.set_ping_error(ConnectionErrorKind::InvalidAddress, message);
+                return;
+            }
+        };
+
+        // The probe itself runs on the worker, the result is picked up in process()
+        let request = PingRequest {
+            host: self.ping_address.to_string(),
+            port,
+            timeout: self.ping_timeout(),
+            persistent: self.persistent_connection,
+            local_address,
+            verification: self.verification(),
+            fresh: self.fresh_probe_pending,
+            time_sync: self.time_sync_enabled && self.clock_offset.wants_sample(TIME_SYNC_INTERVAL),
+        };
+        if self.ping_worker.as_mut().is_some_and(|worker| worker.request(request)) {
+            self.fresh_probe_pending = false;
+        }
+
+        // Services are checked on the same schedule, in parallel on their own worker
+        self.check_services(local_address);
+    }
+
+    fn verification(&self) -> Option<Verification> {
+        let expected = self.expected_banner.to_string();
+        match self.verification_mode {
+            VerificationMode::None => None,
+            VerificationMode::SshBanner => Some(Verification::SshBanner(expected)),
+            VerificationMode::Http => Some(Verification::Http(expected)),
+        }
+    }
+
+    fn apply_verification(&mut self, verification: Option<Result<(), String>>) {
+        if self.verification_mode == VerificationMode::None {
+            self.verified = false;
+            self.last_verification_failure = None;
+            return;
+        }
+
+        match verification {
+            Some(Ok(())) => {
+                if !self.verified {
+                    log_info!("Ping target verified as the robot");
+                }
+                self.verified = true;
+                self.last_verification_failure = None;
+            }
+            Some(Err(reason)) => {
+                self.verified = false;
+
+                // Only signal when the reason changes, not on every ping
+                if self.last_verification_failure.as_deref() != Some(reason.as_str()) {
+                    log_warn!("Ping target verification failed: {}", reason);
+                    self.record_error(format!("Verification failed: {}", reason));
+                    self.emit("verification_failed", &[GString::from(reason.as_str()).to_variant()]);
+                    self.last_verification_failure = Some(reason);
+                }
+            }
+            // An already open connection was checked, keep the previous result
+            None => {}
+        }
+    }
+
+    /// Scan the local /24 subnets for the robot on a background thread.
+    /// Emits robot_discovered for each hit and discovery_finished at the end.
+    /// Returns false if a scan is already running or there's nothing to scan.
+    fn discover_robot(&mut self) -> bool {
+        if self.discovery.is_some() {
+            log_warn!("Robot discovery is already running");
+            return false;
+        }
+
+        let bind_address = match self.parse_local_bind_address() {
+            Ok(bind_address) => bind_address,
+            Err(message) => {
+                log_error!("{}", message);
+                return false;
+            }
+        };
+
+        // Only scan the robot-facing NIC when one is configured
+        let local_addresses: Vec<Ipv4Addr> = match bind_address {
+            Some(IpAddr::V4(ip)) => vec![ip],
+            _ => Ip::singleton()
+                .get_local_addresses()
+                .as_slice()
+                .iter()
+                .filter_map(|addr| addr.to_string().parse::<Ipv4Addr>().ok())
+                .filter(|ip| !ip.is_loopback() && !ip.is_unspecified())
+                .collect(),
+        };
+
+        if local_addresses.is_empty() {
+            log_warn!("No local IPv4 address to scan from");
+            return false;
+        }
+
+        let mut ports: Vec<u16> = self
+            .discovery_ports
+            .as_slice()
+            .iter()
+            .filter_map(|port| u16::try_from(*port).ok())
+            .filter(|port| *port != 0)
+            .collect();
+        if ports.is_empty() {
+            match u16::try_from(self.ping_port) {
+                Ok(port) if port != 0 => ports.push(port),
+                _ => {
+                    log_error!("No valid port to scan for");
+                    return false;
+                }
+            }
+        }
+
+        log_info!("Scanning {:?} for the robot on ports {:?}", local_addresses, ports);
+        self.discovery_adopted = false;
+        self.discovery = Some(Discovery::start(DiscoveryConfig {
+            local_addresses,
+            ports,
+            timeout: DISCOVERY_TIMEOUT,
+            concurrency: self.discovery_concurrency.clamp(1, 64) as usize,
+            attempts_per_sec: self.discovery_attempts_per_sec.clamp(1, 1000) as u32,
+            bind_address,
+            verification: self.verification(),
+        }));
+        true
+    }
+
+    fn cancel_discovery(&mut self) {
+        if let Some(discovery) = &self.discovery {
+            discovery.cancel();
+        }
+    }
+
+    fn is_discovering(&self) -> bool {
+        self.discovery.is_some()
+    }
+
+    fn poll_discovery(&mut self) {
+        let Some(discovery) = &self.discovery else {
+            return;
+        };
+
+        for event in discovery.poll() {
+            match event {
+                DiscoveryEvent::Found { addr, verified } => {
+                    log_info!("Found robot candidate at {} (verified: {})", addr, verified);
+                    let address = GString::from(addr.to_string().as_str());
+                    self.emit("robot_discovered", &[address.to_variant(), verified.to_variant()]);
+
+                    // Without a verification mode there's nothing more to check
+                    let trusted = verified || self.verification_mode == VerificationMode::None;
+                    if self.discovery_auto_adopt && trusted && !self.discovery_adopted {
+                        self.adopt_discovered(addr);
+                    }
+                }
+                DiscoveryEvent::Finished { cancelled } => {
+                    log_info!("Robot discovery finished");
+                    self.discovery = None;
+                    self.emit("discovery_finished", &[cancelled.to_variant()]);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn adopt_discovered(&mut self, addr: std::net::SocketAddr) {
+        log_info!("Adopting {} as the ping target", addr);
+        self.discovery_adopted = true;
+        self.set_ping_address(GString::from(addr.ip().to_string().as_str()));
+        self.set_ping_port(addr.port() as i64);
+        self.cancel_discovery();
+
+        self.ping_tcp_server();
+        self.last_ping_time = Instant::now();
+    }
+
+    fn window_minimized() -> bool {
+        DisplayServer::singleton().window_get_mode() == WindowMode::MINIMIZED
+    }
+
+    fn set_probing_paused(&mut self, paused: bool) {
+        if !self.pause_when_hidden || self.probing_paused == paused {
+            return;
+        }
+
+        self.probing_paused = paused;
+        if paused {
+            log_info!("App hidden, pausing network probes");
+        } else {
+            // Refresh right away instead of showing a stale state until the next interval
+            log_info!("App visible again, resuming network probes");
+            self.ping_tcp_server();
+            self.last_ping_time = Instant::now();
+        }
+    }
+
+    fn parse_local_bind_address(&self) -> Result<Option<IpAddr>, String> {
+        let text = self.local_bind_address.to_string();
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(None);
+        }
+
+        text.parse::<IpAddr>()
+            .map(Some)
+            .map_err(|_| format!("Invalid local_bind_address: {}", text))
+    }
+
+    fn service_config(&self) -> Vec<(String, u16, bool)> {
+        let mut config = Vec::new();
+
+        for (key, value) in self.services.iter_shared() {
+            let name = key.to_string();
+            let (port, required) = if let Ok(port) = value.try_to::<i64>() {
+                (port, false)
+            } else if let Ok(entry) = value.try_to::<Dictionary>() {
+                let port = entry.get("port").and_then(|p| p.try_to::<i64>().ok()).unwrap_or(0);
+                let required = entry.get("required").and_then(|r| r.try_to::<bool>().ok()).unwrap_or(false);
+                (port, required)
+            } else {
+                log_warn!("Invalid service entry for {}", name);
+                continue;
+            };
+
+            match u16::try_from(port) {
+                Ok(port) if port != 0 => config.push((name, port, required)),
+                _ => log_warn!("Invalid port {} for service {}", port, name),
+            }
+        }
+
+        config
+    }
+
+    fn device_config(&self) -> Vec<(String, String, u16, bool)> {
+        let mut config = Vec::new();
+
+        for entry in self.devices.iter_shared() {
+            let name = entry.get("name").map(|n| n.to_string()).unwrap_or_default();
+            let address = entry.get("address").map(|a| a.to_string()).unwrap_or_default();
+            let port = entry.get("port").and_then(|p| p.try_to::<i64>().ok()).unwrap_or(0);
+            let required = entry.get("required").and_then(|r| r.try_to::<bool>().ok()).unwrap_or(false);
+
+            if name.is_empty() || address.is_empty() {
+                log_warn!("Device entries need a name and an address");
+                continue;
+            }
+
+            match u16::try_from(port) {
+                Ok(port) if port != 0 => config.push((name, address, port, required)),
+                _ => log_warn!("Invalid port {} for device {}", port, name),
+            }
+        }
+
+        config
+    }
+
+    fn check_services(&mut self, local_address: Option<IpAddr>) {
+        // Services live on the ping target's host, which isn't known
+        if self.rejected_endpoint.is_some() {
+            return;
+        }
+        let config = self.service_config();
+        let device_config = self.device_config();
+
+        // Forget services that were removed from the configuration
+        self.service_status.retain(|name, _| config.iter().any(|(n, _, _)| n == name));
+
+        let host = self.ping_address.to_string();
+        let mut checks = Vec::new();
+        for (name, port, required) in config {
+            self.service_status
+                .entry(name.clone())
+                .or_insert(ServiceStatus {
+                    up: false,
+                    required,
+                    latency: None,
+                    last_checked: None,
+                })
+                .required = required;
+            checks.push(ServiceCheck {
+                name,
+                host: host.clone(),
+                port,
+                local_address,
+                device: false,
+            });
+        }
+
+        self.device_status.retain(|name, _| device_config.iter().any(|(n, _, _, _)| n == name));
+        for (name, address, port, required) in device_config {
+            self.device_status
+                .entry(name.clone())
+                .or_insert(ServiceStatus {
+                    up: false,
+                    required,
+                    latency: None,
+                    last_checked: None,
+                })
+                .required = required;
+            checks.push(ServiceCheck {
+                name,
+                host: address,
+                port,
+                local_address,
+                device: true,
+            });
+        }
+
+        let timeout = self.ping_timeout();
+        if let Some(monitor) = self.service_monitor.as_mut() {
+            monitor.request(checks, timeout);
+        }
+    }
+
+    fn apply_service_results(&mut self, results: Vec<ServiceResult>) {
+        let now = SystemTime::now();
+
+        for result in results {
+            if result.device {
+                self.apply_device_result(result, now);
+                continue;
+            }
+
+            let Some(status) = self.service_status.get_mut(&result.name) else {
+                continue;
+            };
+
+            let up = result.latency.is_some();
+            let changed = status.up != up || status.last_checked.is_none();
+            status.up = up;
+            status.latency = result.latency;
+            status.last_checked = Some(now);
+
+            if changed {
+                if !up && status.required {
+                    log_warn!("Required service {} is down", result.name);
+                }
+                self.emit(
+                    "service_status_changed",
+                    &[GString::from(result.name.as_str()).to_variant(), up.to_variant()],
+                );
+            }
+        }
+
+        self.refresh_connected();
+    }
+
+    fn apply_device_result(&mut self, result: ServiceResult, now: SystemTime) {
+        let Some(status) = self.device_status.get_mut(&result.name) else {
+            return;
+        };
+
+        let up = result.latency.is_some();
+        let changed = status.up != up || status.last_checked.is_none();
+        status.up = up;
+        status.latency = result.latency;
+        status.last_checked = Some(now);
+
+        if changed {
+            if up {
+                log_info!("Device {} is reachable", result.name);
+            } else if status.required {
+                log_warn!("Required device {} is down", result.name);
+            }
+            self.emit(
+                "device_connection_changed",
+                &[GString::from(result.name.as_str()).to_variant(), up.to_variant()],
+            );
+        }
+    }
+
+    /// Whether every required device answered its last check.
+    fn get_devices_ok(&self) -> bool {
+        self.device_status.values().filter(|s| s.required).all(|s| s.up)
+    }
+
+    fn required_services_up(&self) -> bool {
+        self.service_status.values().filter(|s| s.required).all(|s| s.up)
+    }
+
+    /// Connected means the ping target answered and every required service is up.
+    fn refresh_connected(&mut self) {
+        if self.override_mode != OverrideMode::Normal || self.simulating_connection_loss {
+            return;
+        }
+
+        let connected = self.primary_up && self.required_services_up();
+        self.set_connected(connected);
+    }
+
+    fn get_service_status(&self) -> Dictionary {
+        Self::status_dictionary(&self.service_status)
+    }
+
+    /// Per-device state, keyed by device name.
+    fn get_device_status(&self) -> Dictionary {
+        Self::status_dictionary(&self.device_status)
+    }
+
+    fn status_dictionary(statuses: &HashMap<String, ServiceStatus>) -> Dictionary {
+        let mut status = Dictionary::new();
+
+        for (name, service) in statuses {
+            let last_checked = service
+                .last_checked
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(-1.0, |d| d.as_secs_f64());
+
+            let mut entry = Dictionary::new();
+            entry.set("up", service.up);
+            entry.set("required", service.required);
+            entry.set("latency_ms", duration_ms(service.latency).unwrap_or(-1.0));
+            entry.set("last_checked", last_checked);
+            status.set(name.as_str(), entry);
+        }
+
+        status
+    }
+
+    /// Accepts a bare host or "host:port"; a port included here replaces ping_port.
+    fn set_ping_address(&mut self, address: GString) {
+        self.explicit_config.mark(ConfigField::PingAddress);
+        self.change_endpoint_to(&address.to_string(), self.ping_port);
+    }
+
+    fn set_ping_port(&mut self, port: i64) {
+        self.explicit_config.mark(ConfigField::PingPort);
+        self.change_endpoint(self.ping_address.to_string(), port);
+    }
+
+    fn set_endpoint(&mut self, endpoint: GString) {
+        self.explicit_config.mark(ConfigField::PingAddress);
+        self.explicit_config.mark(ConfigField::PingPort);
+        self.change_endpoint_to(&endpoint.to_string(), self.ping_port);
+    }
+
+    /// change_endpoint() to a bare host or "host:port", on `port` unless
+    /// the address has its own.
+    fn change_endpoint_to(&mut self, address: &str, port: i64) {
+        match parse_endpoint(address) {
+            Ok((host, own_port)) => self.change_endpoint(host, own_port.map_or(port, i64::from)),
+            Err(e) => self.reject_endpoint(address, &e.to_string()),
+        }
+    }
+
+    /// Switch the ping target. Probes still out against the old one are
+    /// ignored, the stats start over and the new one is probed right away.
+    fn change_endpoint(&mut self, host: String, port: i64) {
+        if !(1..=u16::MAX as i64).contains(&port) {
+            self.reject_endpoint(&format_endpoint(&host, port), "port out of range");
+            return;
+        }
+        if self.rejected_endpoint.take().is_some() {
+            log_info!("Ping target {} accepted, probing again", format_endpoint(&host, port));
+            self.fresh_probe_pending = true;
+            self.next_ping_delay = Duration::ZERO;
+        }
+        if host == self.ping_address.to_string() && port == self.ping_port {
+            return;
+        }
+
+        let old = self.endpoint.clone();
+        self.ping_address = GString::from(host.as_str());
+        self.ping_port = port;
+        self.sync_endpoint();
+        let new = self.endpoint.clone();
+
+        if self.running {
+            log_info!("Ping target changed from {} to {}", old, new);
+            self.reset_ping_stats();
+            self.reset_robot_clock_offset();
+            self.consecutive_failures = 0;
+            self.consecutive_successes = 0;
+            self.last_indicator_state = None;
+
+            // Same as a network change: drop the old connection and probe now
+            self.fresh_probe_pending = true;
+            self.next_ping_delay = Duration::ZERO;
+        }
+        self.emit("endpoint_changed", &[old.to_variant(), new.to_variant()]);
+    }
+
+    /// Refuse a ping target that doesn't parse. Probing stops until a good
+    /// one is set: carrying on against the previous target would report
+    /// a connection to something the operator no longer asked for.
+    fn reject_endpoint(&mut self, value: &str, reason: &str) {
+        log_warn!("Invalid ping target \"{}\": {}. Not probing until it's fixed", value, reason);
+        self.rejected_endpoint = Some((value.to_string(), reason.to_string()));
+        let message = format!("Invalid ping target \"{}\": {}", value, reason);
+        if self.running {
+            self.ping_stats.record_failure(ConnectionErrorKind::InvalidAddress);
+            self.set_ping_error(ConnectionErrorKind::InvalidAddress, message);
+        } else {
+            self.last_error = GString::from(message.as_str());
+            self.last_error_kind = ConnectionErrorKind::InvalidAddress;
+        }
+    }
+
+    fn sync_endpoint(&mut self) {
+        let endpoint = format_endpoint(&self.ping_address.to_string(), self.ping_port);
+        self.endpoint = GString::from(endpoint.as_str());
+    }
+
+    fn ping_timeout(&self) -> Duration {
+        let ms = self.ping_timeout_ms.clamp(MIN_PING_TIMEOUT_MS, MAX_PING_TIMEOUT_MS);
+        Duration::from_millis(ms as u64)
+    }
+
+    fn set_ping_timeout_ms(&mut self, timeout_ms: i64) {
+        // Takes effect on the next ping, the request carries its own timeout
+        self.ping_timeout_ms = timeout_ms.clamp(MIN_PING_TIMEOUT_MS, MAX_PING_TIMEOUT_MS);
+        self.explicit_config.mark(ConfigField::PingTimeout);
+    }
+
+    fn get_ping_stats(&self) -> Dictionary {
+        let stats = &self.ping_stats;
+
+        // Every kind is always present so the shape doesn't change between calls
+        let mut failures = Dictionary::new();
+        for kind in FAILURE_KINDS {
+            failures.set(format!("{:?}", kind).as_str(), stats.failures[kind as usize]);
+        }
+
+        let mut result = Dictionary::new();
+        result.set("interval_secs", self.ping_interval.as_secs_f64());
+        result.set("timeout_ms", self.ping_timeout().as_millis() as i64);
+        result.set("attempts", stats.attempts);
+        result.set("successes", stats.successes);
+        result.set("failures", stats.attempts - stats.successes);
+        result.set("failures_by_kind", failures);
+        result.set("loss_pct", if stats.attempts == 0 {
+            0.0
+        } else {
+            f64::from(stats.attempts - stats.successes) * 100.0 / f64::from(stats.attempts)
+        });
+        result.set("samples", stats.samples);
+        result.set("last_ms", duration_ms(stats.last).unwrap_or(-1.0));
+        result.set("min_ms", duration_ms(stats.min).unwrap_or(-1.0));
+        result.set("avg_ms", duration_ms(stats.average()).unwrap_or(-1.0));
+        result.set("max_ms", duration_ms(stats.max).unwrap_or(-1.0));
+        result.set("p95_ms", duration_ms(stats.percentile(95.0)).unwrap_or(-1.0));
+        result.set("consecutive_failures", self.consecutive_failures);
+        result.set("current_error_repeats", self.error_aggregator.total_repeats);
+        result.set("consecutive_successes", self.consecutive_successes);
+        result
+    }
+
+    /// Clear the ping counters and latency figures.
+    fn reset_ping_stats(&mut self) {
+        self.ping_stats = PingStats::default();
+    }
+
+    fn apply_ping_result(&mut self, result: PingResult) {
+        // Results that were in flight when a simulation or override started are stale
+        if self.override_mode != OverrideMode::Normal || self.simulating_connection_loss {
+            return;
+        }
+
+        // So are ones against a target that has since been replaced
+        if result.request.host != self.ping_address.to_string() || i64::from(result.request.port) != self.ping_port {
+            log_debug!("Ignoring a probe of {}:{}, the target changed", result.request.host, result.request.port);
+            return;
+        }
+
+        self.schedule_next_ping(result.outcome.is_ok());
+
+        if self.prematch.is_some() {
+            match &result.outcome {
+                Ok(success) => match success.latency {
+                    Some(latency) => {
+                        let ms = latency.as_secs_f64() * 1000.0;
+                        let passed = ms <= self.prematch_max_latency_ms;
+                        let detail = format!("{:.0} ms (limit {:.0} ms)", ms, self.prematch_max_latency_ms);
+                        self.finish_prematch_latency(passed, detail);
+                    }
+                    None => self.finish_prematch_latency(false, "Probe reported no latency".into()),
+                },
+                Err(_) => self.finish_prematch_latency(false, "Probe failed".into()),
+            }
+        }
+
+        // This probe was already running when the network changed
+        if self.fresh_probe_pending {
+            self.next_ping_delay = Duration::ZERO;
+        }
+
+        match result.outcome {
+            Ok(_) => {
+                self.consecutive_failures = 0;
+                self.consecutive_successes += 1;
+            }
+            Err(_) => {
+                self.consecutive_successes = 0;
+                self.consecutive_failures += 1;
+            }
+        }
+
+        let target = format!("{}:{}", result.request.host, result.request.port);
+        let was_connected = self.connected;
+        let log_entry = json!({
+            "address": target,
+            "resolved": match &result.outcome {
+                Ok(success) => Some(success.addr.to_string()),
+                Err(PingFailure::Connect(addr, _)) => Some(addr.to_string()),
+                Err(PingFailure::Resolve(_)) => None,
+            },
+            "latency_ms": match &result.outcome {
+                Ok(success) => duration_ms(success.latency),
+                Err(_) => None,
+            },
+            "error_kind": match &result.outcome {
+                Ok(_) => None,
+                Err(PingFailure::Resolve(_)) => Some(String::from("Resolve")),
+                Err(PingFailure::Connect(_, e)) => Some(format!("{:?}", e.kind())),
+            },
+            "error": match &result.outcome {
+                Ok(_) => None,
+                Err(PingFailure::Resolve(message)) => Some(message.clone()),
+                Err(PingFailure::Connect(_, e)) => Some(e.to_string()),
+            },
+        });
+        log_debug!("Ping result: {}", log_entry);
+
+        match result.outcome {
+            Ok(success) => {
+                self.ping_stats.attempts += 1;
+                self.ping_stats.successes += 1;
+                if let Some(latency) = success.latency {
+                    self.ping_stats.record(latency);
+                }
+                if let Some(summary) = self.error_aggregator.flush() {
+                    log_warn!("{}", summary);
+                }
+                self.last_error = GString::new();
+                self.last_error_kind = ConnectionErrorKind::None;
+                self.apply_verification(success.verification);
+                if let Some(sample) = success.clock_sample {
+                    self.apply_clock_sample(sample);
+                }
+                self.local_address = success
+                    .local_addr
+                    .map(|addr| GString::from(addr.ip().to_string().as_str()))
+                    .unwrap_or_default();
+                if !self.primary_up && self.consecutive_successes >= Self::hysteresis_count(self.successes_before_connect) {
+                    log_info!("TCP connection established with {} ({})", target, success.addr);
+                    self.primary_up = true;
+                }
+                if self.primary_up {
+                    self.radio_probe = None;
+                    self.set_network_diagnosis(NetworkDiagnosis::Ok);
+                }
+                self.refresh_connected();
+            }
+            Err(PingFailure::Resolve(message)) => {
+                self.ping_stats.record_failure(ConnectionErrorKind::Resolve);
+                self.apply_ping_failure(ConnectionErrorKind::Resolve, message);
+            }
+            Err(PingFailure::Connect(addr, e)) => {
+                let kind = ConnectionErrorKind::from_io(e.kind());
+                self.ping_stats.record_failure(kind);
+                self.apply_ping_failure(kind, format!("TCP connection to {} ({}) failed: {}", target, addr, e));
+            }
+        }
+
+        if !self.primary_up {
+            self.diagnose_failure();
+        }
+
+        // Probe again quickly while the results disagree with the current state
+        let in_doubt = if self.primary_up {
+            self.consecutive_failures > 0
+        } else {
+            self.consecutive_successes > 0
+        };
+        if in_doubt {
+            self.next_ping_delay = self.next_ping_delay.min(BURST_PROBE_INTERVAL);
+        }
+
+        // Transitions are always logged, individual pings only in verbose mode
+        if self.connected != was_connected {
+            let event = if self.connected { "connected" } else { "disconnected" };
+            self.log_connection_event(event, log_entry);
+        } else if self.connection_log_verbose {
+            self.log_connection_event("ping", log_entry);
+        }
+    }
+
+    fn open_connection_log(&mut self) {
+        if self.connection_log.is_some() {
+            return;
+        }
+
+        let dir = ProjectSettings::singleton().globalize_path(CONNECTION_LOG_DIR).to_string();
+        let stamp = Time::singleton().get_datetime_string_from_system().to_string().replace(':', "-");
+        let log = EventLog::open(dir.into(), &format!("connection_{}.jsonl", stamp));
+        log_info!("Logging connection events to {}", log.path().display());
+        self.connection_log = Some(log);
+    }
+
+    /// Who and where, for telling files from different events apart. Unset
+    /// fields are empty strings rather than missing.
+    fn metadata_json(&self) -> serde_json::Value {
+        json!({
+            "team_number": self.team_number,
+            "event_code": self.event_code.to_string(),
+            "match_label": self.match_label.to_string(),
+        })
+    }
+
+    /// Label the coming match, e.g. "Q42" from a text field before it
+    /// starts.
+    fn set_match_label(&mut self, label: GString) {
+        let label = GString::from(label.to_string().trim());
+        if label == self.match_label {
+            return;
+        }
+        log_info!("Match label \"{}\" -> \"{}\"", self.match_label, label);
+        self.match_label = label;
+    }
+
+    fn log_connection_event(&self, event: &str, mut entry: serde_json::Value) {
+        let Some(log) = &self.connection_log else {
+            return;
+        };
+
+        let wall_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |d| d.as_secs_f64());
+
+        entry["event"] = json!(event);
+        entry["metadata"] = self.metadata_json();
+        entry["wall_time"] = json!(wall_time);
+        entry["monotonic_secs"] = json!(self.start_time.elapsed().as_secs_f64());
+        if self.apply_clock_offset_to_logs {
+            if let Some(offset) = self.clock_offset.offset_secs() {
+                entry["robot_time"] = json!(wall_time + offset);
+            }
+        }
+        log.write(entry);
+    }
+
+    /// Absolute path of this session's connection log, empty if logging is off.
+    fn get_log_path(&self) -> GString {
+        self.connection_log
+            .as_ref()
+            .map(|log| GString::from(log.path().to_string_lossy().as_ref()))
+            .unwrap_or_default()
+    }
+
+    fn schedule_next_ping(&mut self, succeeded: bool) {
+        if !self.persistent_connection {
+            self.next_ping_delay = self.ping_interval;
+            return;
+        }
+
+        // Check a healthy connection often, back off while reconnecting fails
+        if succeeded {
+            self.reconnect_backoff = MIN_RECONNECT_BACKOFF;
+            self.next_ping_delay = PERSISTENT_CHECK_INTERVAL;
+        } else {
+            self.next_ping_delay = self.reconnect_backoff;
+            self.reconnect_backoff = (self.reconnect_backoff * 2).min(self.ping_interval);
+        }
+    }
+
+    /// Addresses of the laptop's network interfaces as of the last check.
+    fn get_local_addresses(&self) -> PackedStringArray {
+        self.local_addresses.iter().map(|addr| GString::from(addr.as_str())).collect()
+    }
+
+    fn check_network_interfaces(&mut self) {
+        self.last_interface_check = Instant::now();
+
+        let addresses = Self::read_local_addresses();
+        if addresses == self.local_addresses {
+            return;
+        }
+
+        log_info!("Network interfaces changed: {}", addresses.join(", "));
+        self.local_addresses = addresses;
+
+        // Re-resolve and probe right away instead of waiting out the interval
+        self.fresh_probe_pending = true;
+        self.next_ping_delay = Duration::ZERO;
+
+        let addresses = self.get_local_addresses();
+        self.emit("network_interfaces_changed", &[addresses.to_variant()]);
+    }
+
+    fn read_local_addresses() -> Vec<String> {
+        let mut addresses: Vec<String> = Ip::singleton()
+            .get_local_addresses()
+            .as_slice()
+            .iter()
+            .map(|addr| addr.to_string())
+            .collect();
+        addresses.sort();
+        addresses
+    }
+
+    fn radio_ip(&self) -> Option<Ipv4Addr> {
+        let text = self.radio_address.to_string();
+        let text = text.trim();
+        if !text.is_empty() {
+            return text.parse().ok();
+        }
+
+        radio_address_for_team(u32::try_from(self.team_number).ok()?)
+    }
+
+    /// Work out whether the laptop is on the robot network at all, and if so
+    /// whether the radio answers while the RIO doesn't.
+    fn diagnose_failure(&mut self) {
+        if self.radio_probe.is_some() {
+            return;
+        }
+
+        let Some(radio) = self.radio_ip() else {
+            self.set_network_diagnosis(NetworkDiagnosis::Unknown);
+            return;
+        };
+
+        let bind_address = self.parse_local_bind_address().ok().flatten();
+        let on_robot_subnet = match bind_address {
+            Some(IpAddr::V4(ip)) => same_subnet(ip, radio),
+            Some(IpAddr::V6(_)) => false,
+            None => Ip::singleton()
+                .get_local_addresses()
+                .as_slice()
+                .iter()
+                .filter_map(|addr| addr.to_string().parse::<Ipv4Addr>().ok())
+                .any(|ip| same_subnet(ip, radio)),
+        };
+
+        if !on_robot_subnet {
+            self.set_network_diagnosis(NetworkDiagnosis::NotOnRobotNetwork);
+            return;
+        }
+
+        self.radio_probe = Some(RadioProbe::start(radio, bind_address, self.ping_timeout()));
+    }
+
+    fn set_network_diagnosis(&mut self, diagnosis: NetworkDiagnosis) {
+        if self.network_diagnosis == diagnosis {
+            return;
+        }
+
+        self.network_diagnosis = diagnosis;
+        match diagnosis {
+            NetworkDiagnosis::RioDown => log_warn!("Radio answers but the robot doesn't, check the RIO"),
+            NetworkDiagnosis::NotOnRobotNetwork => log_warn!("Not on the robot network, check the radio connection"),
+            _ => {}
+        }
+        self.emit("diagnosis_changed", &[diagnosis.to_variant()]);
+    }
+
+    fn apply_clock_sample(&mut self, sample: Result<ClockSample, String>) {
+        match sample {
+            Ok(sample) => {
+                let was_known = self.clock_offset.offset_secs().is_some();
+                self.clock_offset.add(sample);
+                self.last_time_sync_error = None;
+                if let (false, Some(offset)) = (was_known, self.clock_offset.offset_secs()) {
+                    log_info!("Robot clock offset is {:.0} ms", offset * 1000.0);
+                }
+            }
+            Err(reason) => {
+                // Only report when the reason changes, not on every ping
+                if self.last_time_sync_error.as_deref() != Some(reason.as_str()) {
+                    log_warn!("Robot clock sample failed: {}", reason);
+                    self.last_time_sync_error = Some(reason);
+                }
+            }
+        }
+    }
+
+    /// Offset to add to local time to get the robot's clock, 0 while unknown.
+    fn get_robot_clock_offset_ms(&self) -> f64 {
+        self.clock_offset.offset_secs().map_or(0.0, |offset| offset * 1000.0)
+    }
+
+    fn get_robot_clock_offset_known(&self) -> bool {
+        self.clock_offset.offset_secs().is_some()
+    }
+
+    /// Feed a clock sample from another source (e.g. NT server time), all
+    /// times in seconds since the Unix epoch.
+    fn add_robot_time_sample(&mut self, robot_unix_secs: f64, sent_unix_secs: f64, received_unix_secs: f64) {
+        let sample = ClockSample::from_times(robot_unix_secs, sent_unix_secs, received_unix_secs);
+        self.apply_clock_sample(sample);
+    }
+
+    /// Forget the clock samples, e.g. after the robot rebooted.
+    fn reset_robot_clock_offset(&mut self) {
+        self.clock_offset.reset();
+    }
+
+    fn hysteresis_count(count: i64) -> u32 {
+        count.clamp(1, u32::MAX as i64) as u32
+    }
+
+    /// Take the link down once enough probes in a row have failed, until
+    /// then only the error is recorded.
+    fn apply_ping_failure(&mut self, kind: ConnectionErrorKind, message: String) {
+        if self.primary_up && self.consecutive_failures < Self::hysteresis_count(self.failures_before_disconnect) {
+            self.note_ping_error(kind, message);
+            return;
+        }
+
+        self.set_ping_error(kind, message);
+    }
+
+    fn note_ping_error(&mut self, kind: ConnectionErrorKind, message: String) {
+        let interval = Duration::from_secs_f64(self.error_report_interval_secs.max(0.0));
+        if let Some(line) = self.error_aggregator.note(&message, interval) {
+            log_warn!("{}", line);
+        }
+
+        // Signal the first of each kind right away, repeats only go to the log
+        let new_kind = self.last_error_kind != kind;
+        self.last_error = GString::from(message.as_str());
+        self.last_error_kind = kind;
+        if new_kind {
+            let args = [kind.to_variant(), self.last_error.to_variant()];
+            self.emit("connection_error", &args);
+        }
+        self.record_error(message);
+    }
+
+    /// Record why the last probe failed and mark the link down.
+    fn set_ping_error(&mut self, kind: ConnectionErrorKind, message: String) {
+        self.note_ping_error(kind, message);
+        self.local_address = GString::new();
+        self.verified = false;
+        self.primary_up = false;
+        self.set_connected(false);
+    }
+
+    fn set_connected(&mut self, connected: bool) {
+        if self.connected == connected {
+            return;
+        }
+
+        self.connected = connected;
+        let previous_duration = self.connection_history.record(connected);
+
+        if self.match_recording {
+            let now = self.match_secs();
+            match self.match_outages.last_mut() {
+                Some((_, end @ None)) if connected => *end = Some(now),
+                _ if !connected => self.match_outages.push((now, None)),
+                _ => {}
+            }
+            self.record_match_event("connection", json!({ "connected": connected }));
+        }
+        self.emit(
+            "connection_changed",
+            &[connected.to_variant(), previous_duration.as_secs_f64().to_variant()],
+        );
+
+        if !connected {
+            self.on_connection_lost();
+        }
+        self.update_buttons_disabled();
+        self.update_connection_alert();
+    }
+
+    /// Raise or clear the out-of-app alerts to match the connection.
+    fn update_connection_alert(&mut self) {
+        if self.connected {
+            self.restore_window_title();
+            return;
+        }
+
+        // Deliberately stopped or overridden, nothing to alert about
+        if self.override_mode != OverrideMode::Normal || !self.running {
+            return;
+        }
+
+        if self.alert_window_title && self.saved_window_title.is_none() {
+            if let Some(mut window) = self.node().get_window() {
+                let title = window.get_title();
+                window.set_title(&GString::from(format!("{}{}", title, self.alert_title_suffix)));
+                self.saved_window_title = Some(title);
+            }
+        }
+
+        if self.alert_request_attention {
+            DisplayServer::singleton().window_request_attention();
+        }
+
+        if let Some(sound) = self.alert_sound.clone() {
+            let mut player = match self.alert_player.clone() {
+                Some(player) => player,
+                None => {
+                    let player = AudioStreamPlayer::new_alloc();
+                    self.add_child(player.clone().upcast());
+                    self.alert_player = Some(player.clone());
+                    player
+                }
+            };
+            player.set_stream(&sound);
+            self.call_later(player.upcast(), "play", &[]);
+        }
+    }
+
+    fn restore_window_title(&mut self) {
+        if let Some(title) = self.saved_window_title.take() {
+            if let Some(mut window) = self.node().get_window() {
+                window.set_title(&title);
+            }
+        }
+    }
+
+    fn set_disable_buttons_when_disconnected(&mut self, disable: bool) {
+        self.disable_buttons_when_disconnected = disable;
+        self.update_buttons_disabled();
+    }
+
+    /// Disable or restore the bound buttons to match the connection state
+    /// and the disabled actions.
+    fn update_buttons_disabled(&mut self) {
+        self.update_action_buttons_disabled();
+
+        if !self.disable_buttons_when_disconnected || self.connected {
+            for mut button in self.buttons_disabled_while_disconnected.drain(..) {
+                // Still greyed out for its action
+                if self.action_disabled_buttons.iter().any(|(_, greyed)| *greyed == button) {
+                    continue;
+                }
+                if button.is_instance_valid() {
+                    button.set_disabled(false);
+                }
+            }
+            return;
+        }
+
+        for binding in &self.action_bindings {
+            if !binding.node.is_instance_valid() {
+                continue;
+            }
+            let Ok(mut button) = binding.node.clone().try_cast::<BaseButton>() else {
+                continue;
+            };
+            if button.is_disabled() {
+                continue;
+            }
+
+            button.set_disabled(true);
+            self.buttons_disabled_while_disconnected.push(button);
+        }
+    }
+
+    /// Whether the bound buttons of `action` should show as disabled.
+    fn action_greyed(&self, action: &str) -> bool {
+        (self.gray_out_disabled_actions && self.action_disabled(action))
+            || self.endgame_locked(action)
+            || self.on_standby()
+            || self.auto_lockout_active()
+    }
+
+    /// Whether `action` is held back until endgame right now.
+    fn endgame_locked(&self, action: &str) -> bool {
+        if self.endgame_lockout_overridden || !self.endgame_only_actions.contains(&GString::from(action)) {
+            return false;
+        }
+        match self.match_phase {
+            MatchPhase::PreMatch => self.lock_endgame_actions_without_match,
+            MatchPhase::Auto | MatchPhase::Teleop => true,
+            MatchPhase::Endgame | MatchPhase::PostMatch => false,
+        }
+    }
+
+    fn time_until_endgame(&self) -> Duration {
+        if !matches!(self.match_phase, MatchPhase::Auto | MatchPhase::Teleop) {
+            return Duration::ZERO;
+        }
+        let durations = self.match_durations();
+        let endgame_at = (durations.auto + durations.teleop).saturating_sub(durations.endgame);
+        endgame_at.saturating_sub(self.match_timer.elapsed().unwrap_or_default())
+    }
+
+    /// Let endgame-only actions through for the rest of this match.
+    fn override_endgame_lockout(&mut self) {
+        log_warn!("Endgame lockout overridden");
+        self.endgame_lockout_overridden = true;
+        self.update_buttons_disabled();
+    }
+
+    /// Grey out the buttons of disabled actions and give back the rest.
+    fn update_action_buttons_disabled(&mut self) {
+        let previous = std::mem::take(&mut self.action_disabled_buttons);
+        for (action, mut button) in previous {
+            if !button.is_instance_valid() {
+                continue;
+            }
+            if self.action_greyed(&action) {
+                self.action_disabled_buttons.push((action, button));
+                continue;
+            }
+            if !self.buttons_disabled_while_disconnected.contains(&button) {
+                button.set_disabled(false);
+            }
+        }
+
+        for binding in &self.action_bindings {
+            if !self.action_greyed(&binding.action) || !binding.node.is_instance_valid() {
+                continue;
+            }
+            let Ok(mut button) = binding.node.clone().try_cast::<BaseButton>() else {
+                continue;
+            };
+            if self.action_disabled_buttons.iter().any(|(_, greyed)| *greyed == button) {
+                continue;
+            }
+            // Disabled for some other reason, not ours to give back
+            if button.is_disabled() && !self.buttons_disabled_while_disconnected.contains(&button) {
+                continue;
+            }
+
+            button.set_disabled(true);
+            self.action_disabled_buttons.push((binding.action.clone(), button));
+        }
+    }
+
+    /// Turn an action off or back on. A disabled action is rejected from
+    /// every origin with press_blocked, and is released if held.
+    fn set_action_enabled(&mut self, name: GString, enabled: bool) {
+        let name = name.to_string();
+        if !Self::is_known_action(&name) {
+            log_warn!("Cannot enable or disable unknown action \"{}\"", name);
+            return;
+        }
+
+        let changed = if enabled {
+            self.disabled_actions.remove(&name)
+        } else {
+            self.disabled_actions.insert(name.clone())
+        };
+        if !changed {
+            return;
+        }
+
+        log_info!("Action {} {}", name, if enabled { "enabled" } else { "disabled" });
+        if !enabled {
+            self.action_turned_off(&name);
+        }
+        self.update_buttons_disabled();
+    }
+
+    /// Let go of `name` and drop anything waiting to press it.
+    fn action_turned_off(&mut self, name: &str) {
+        self.drop_action(name);
+        if self.pending_confirmation.as_ref().is_some_and(|(pending, _)| *pending == name) {
+            self.cancel_confirmation("disabled");
+        }
+        if self.pending_coach.as_ref().is_some_and(|pending| pending.action == name) {
+            self.cancel_coach_confirmation("disabled");
+        }
+    }
+
+    /// Turned off by set_action_enabled() or left out of the active set.
+    fn action_disabled(&self, name: &str) -> bool {
+        self.disabled_actions.contains(name)
+            || self.action_set_allowed.as_ref().is_some_and(|allowed| !allowed.contains(name))
+    }
+
+    fn is_action_enabled(&self, name: GString) -> bool {
+        !self.action_disabled(&name.to_string())
+    }
+
+    fn get_disabled_actions(&self) -> PackedStringArray {
+        let mut names: Vec<&str> = Self::known_actions().filter(|name| self.action_disabled(name)).collect();
+        names.sort();
+        names.into_iter().map(GString::from).collect()
+    }
+
+    /// Leave only the actions in action_sets[`name`] live, or all of them
+    /// for "default". Anything held that the set turns off is let go of.
+    /// set_action_enabled() still applies on top.
+    fn activate_action_set(&mut self, name: GString) -> godot::global::Error {
+        let name = name.to_string();
+        let allowed = if name == "default" {
+            None
+        } else {
+            let Some(actions) = self.action_sets.get(name.as_str()) else {
+                log_warn!("No action set \"{}\"", name);
+                return godot::global::Error::ERR_DOES_NOT_EXIST;
+            };
+            let Some(actions) = variant_strings(&actions) else {
+                log_warn!("Action set \"{}\" is not a list of actions", name);
+                return godot::global::Error::ERR_INVALID_DATA;
+            };
+            let mut allowed = HashSet::new();
+            for action in actions {
+                if !Self::is_known_action(&action) {
+                    log_warn!("Action set \"{}\" lists unknown action \"{}\"", name, action);
+                    continue;
+                }
+                allowed.insert(action);
+            }
+            Some(allowed)
+        };
+
+        if name == self.active_action_set && allowed == self.action_set_allowed {
+            return godot::global::Error::OK;
+        }
+        let was_disabled: HashSet<&str> =
+            Self::known_actions().filter(|action| self.action_disabled(action)).collect();
+        let previous = std::mem::replace(&mut self.active_action_set, name.clone());
+        self.action_set_allowed = allowed;
+        log_info!("Action set {} -> {}", previous, name);
+
+        let turned_off: Vec<&str> = Self::known_actions()
+            .filter(|action| self.action_disabled(action) && !was_disabled.contains(action))
+            .collect();
+        for action in turned_off {
+            self.action_turned_off(action);
+        }
+        self.update_buttons_disabled();
+
+        let args = [
+            StringName::from(name.as_str()).to_variant(),
+            StringName::from(previous.as_str()).to_variant(),
+        ];
+        self.emit("action_set_changed", &args);
+        godot::global::Error::OK
+    }
+
+    /// Activate the set phase_action_sets names for `phase`, if any. A set
+    /// picked by hand stays until the next phase change.
+    fn apply_phase_action_set(&mut self, phase: &str) {
+        let Some(set) = self.phase_action_sets.get(phase) else {
+            return;
+        };
+        let set = set.to_string();
+        if self.activate_action_set(set.as_str().into()) != godot::global::Error::OK {
+            return;
+        }
+        let args = [StringName::from(phase).to_variant(), StringName::from(set.as_str()).to_variant()];
+        self.emit("phase_actions_applied", &args);
+    }
+
+    fn get_active_action_set(&self) -> GString {
+        self.active_action_set.as_str().into()
+    }
+
+    fn get_time_in_current_state(&self) -> f64 {
+        self.connection_history.time_in_current_state().as_secs_f64()
+    }
+
+    fn get_total_downtime(&self) -> f64 {
+        self.connection_history.total_downtime().as_secs_f64()
+    }
+
+    /// Transitions this session, oldest first. Each entry has `connected`,
+    /// `wall_time`, `monotonic_secs` and `previous_duration` (seconds).
+    fn get_connection_history(&self) -> Array<Dictionary> {
+        let mut history = Array::new();
+
+        for transition in self.connection_history.transitions() {
+            let mut entry = Dictionary::new();
+            entry.set("connected", transition.connected);
+            entry.set("wall_time", transition.wall_time);
+            entry.set("monotonic_secs", transition.monotonic_secs);
+            entry.set("previous_duration", transition.previous_duration.as_secs_f64());
+            history.push(&entry);
+        }
+
+        history
+    }
+
+    /// Clear uptime/downtime totals and the transition history, e.g. between matches.
+    fn reset_connection_stats(&mut self) {
+        self.connection_history.reset();
+    }
+
+    fn on_connection_lost(&mut self) {
+        // Never leave a button asserted across a link drop
+        self.neutralize_inputs("disconnected");
+
+        // Whatever mode the robot was in is no longer known
+        self.robot_mode = "unknown".into();
+
+        if self.lock_inputs_on_disconnect && !self.inputs_locked {
+            log_warn!("Connection lost, inputs locked until re-armed");
+            self.inputs_locked = true;
+        }
+    }
+
+    /// Release everything. `reason` is passed on to inputs_neutralized.
+    /// Cooldowns keep running: the presses behind them already went out,
+    /// so a disconnect or focus loss mustn't let them be sent again early.
+    fn neutralize_inputs(&mut self, reason: &str) {
+        // Only worth a match event if something was held or cancelled
+        let had_input = !self.held_since.is_empty()
+            || !self.action_holders.is_empty()
+            || !self.pending_taps.is_empty()
+            || !self.pending_holds.is_empty()
+            || self.pending_confirmation.is_some()
+            || self.pending_coach.is_some()
+            || self.running_sequence.is_some()
+            || self.score.is_some()
+            || self.auto_zero_at.is_some()
+            || self.selected_level.to_string() != "none"
+            || self.selected_face.to_string() != "none"
+            || self.hp_signal_release_at.is_some();
+
+        if let Some(controller) = &self.virtual_controller {
+            controller.neutralize();
+        }
+        if self.running_sequence.is_some() {
+            self.finish_sequence(false, reason);
+        }
+        self.pending_acks.clear();
+        self.pending_taps.clear();
+        self.held_since.clear();
+        self.action_holders.clear();
+        self.held_input_actions.clear();
+        self.held_box_buttons.clear();
+        self.touch_pointers.clear();
+        self.focus_held = None;
+        self.clear_mirrored_press(None);
+        let holds: Vec<String> = self.pending_holds.keys().cloned().collect();
+        for name in holds {
+            self.cancel_hold(&name);
+        }
+        self.cancel_confirmation(reason);
+        self.cancel_coach_confirmation(reason);
+        self.unpress_radio_groups();
+        self.finish_score(false);
+        self.cancel_auto_zero();
+        if self.selected_level.to_string() != "none" {
+            self.set_selected_level("none".into());
+        }
+        // The controller's spare buttons were cleared with everything else
+        if self.selected_face.to_string() != "none" {
+            self.set_face("none".into());
+        }
+        self.hp_signal_release_at = None;
+        if had_input {
+            self.record_match_event("neutralized", json!({ "reason": reason }));
+        }
+        self.emit("inputs_neutralized", &[StringName::from(reason).to_variant()]);
+    }
+
+    /// Put the interface back in a known state mid-match: everything let
+    /// go of, macros, taps, confirmations and sequences cancelled, level
+    /// and face cleared, toggles unlatched and cooldowns reset. Counters,
+    /// the match clock and the connection are left alone. Cheap enough to
+    /// call every frame; the controller update itself goes out on its
+    /// worker thread.
+    fn quick_reset(&mut self) {
+        log_info!("Quick reset");
+        self.neutralize_inputs("quick_reset");
+        self.clear_cooldowns();
+        self.unlatch_toggles();
+        self.emit("interface_reset", &[]);
+    }
+
+    /// Show every bound toggle button as off, without it sending a release.
+    fn unlatch_toggles(&mut self) {
+        for binding in &self.action_bindings {
+            let Ok(mut button) = binding.node.clone().try_cast::<BaseButton>() else {
+                continue;
+            };
+            if button.is_instance_valid() && button.is_toggle_mode() && button.is_pressed() {
+                button.set_pressed_no_signal(false);
+            }
+        }
+    }
+
+    /// Nobody can see the buttons any more, so let go of everything. Regaining
+    /// focus never brings the old presses back.
+    fn on_focus_lost(&mut self, reason: &str) {
+        // Focus out and pause tend to arrive together, act on the first
+        if !self.window_focused {
+            return;
+        }
+        self.window_focused = false;
+
+        if !self.running {
+            return;
+        }
+
+        self.neutralize_inputs(reason);
+        if self.lock_inputs_on_focus_loss && !self.inputs_locked {
+            log_warn!("Window lost focus, inputs locked until re-armed");
+            self.inputs_locked = true;
+        }
+    }
+
+    /// Unlock inputs after a disconnect or focus lockout. Only works while
+    /// connected and focused.
+    fn rearm_inputs(&mut self) -> bool {
+        if !self.connected {
+            log_warn!("Cannot re-arm inputs while disconnected");
+            return false;
+        }
+        if !self.window_focused {
+            log_warn!("Cannot re-arm inputs while the window is out of focus");
+            return false;
+        }
+
+        self.inputs_locked = false;
+        true
+    }
+
+    fn record_action(&mut self, name: &str, pressed: bool, origin: InputOrigin) {
+        if self.recent_actions.len() >= MAX_RECENT_ACTIONS {
+            self.recent_actions.pop_front();
+        }
+        self.recent_actions.push_back((Instant::now(), name.to_string(), pressed, origin));
+    }
+
+    fn record_error(&mut self, message: String) {
+        if self.recent_errors.len() >= MAX_RECENT_ERRORS {
+            self.recent_errors.pop_front();
+        }
+        self.recent_errors.push_back((Instant::now(), message));
+    }
+
+    /// Check everything that has to be right before a match: connection
+    /// (up and verified), latency (a fresh probe under
+    /// prematch_max_latency_ms), controller (ready in expected_user_index),
+    /// bindings (every action on a button), command_link (connected, if
+    /// configured) and config (no configuration problems). Returns check
+    /// name -> { passed, detail } right away; latency stays pending until
+    /// its probe comes back, and prematch_check_finished has the full report.
+    fn run_prematch_check(&mut self) -> Dictionary {
+        let mut report = Dictionary::new();
+
+        let verified = self.verification_mode == VerificationMode::None || self.verified;
+        let detail = if !self.running {
+            "Not started".to_string()
+        } else if !self.connected {
+            format!("Not connected: {}", self.last_error)
+        } else if !verified {
+            "Connected but not verified".to_string()
+        } else {
+            format!("Connected to {}", self.endpoint)
+        };
+        report.set("connection", Self::prematch_entry(self.running && self.connected && verified, &detail));
+
+        let controller = self.virtual_controller.as_ref();
+        let user_index = controller.and_then(|c| c.user_index()).map_or(-1, i64::from);
+        let (passed, detail) = if self.dry_run {
+            (false, "Dry run, nothing reaches the robot".to_string())
+        } else if !controller.is_some_and(|c| c.is_running()) {
+            (false, "Controller not ready".to_string())
+        } else if self.output_mode == OutputMode::HalSim {
+            (true, format!("Sim joystick {}", self.halsim_joystick))
+        } else if self.expected_user_index >= 0 && user_index != self.expected_user_index {
+            (false, format!("In XInput slot {}, expected {}", user_index, self.expected_user_index))
+        } else {
+            (true, format!("In XInput slot {}", user_index))
+        };
+        report.set("controller", Self::prematch_entry(passed, &detail));
+
+        let (bindings, problems) = self.resolve_button_bindings();
+        let unbound: Vec<&str> = BUTTON_MAPPING
+            .iter()
+            .map(|(action, _)| *action)
+            .filter(|action| {
+                !bindings
+                    .iter()
+                    .any(|(bound, button)| bound == action && button.is_instance_valid() && button.is_inside_tree())
+            })
+            .collect();
+        let detail = if !unbound.is_empty() {
+            format!("Not bound: {}", unbound.join(", "))
+        } else if !problems.is_empty() {
+            problems.join("; ")
+        } else {
+            format!("All {} actions bound", BUTTON_MAPPING.len())
+        };
+        report.set("bindings", Self::prematch_entry(unbound.is_empty() && problems.is_empty(), &detail));
+
+        let (passed, detail) = if self.command_address.is_empty() {
+            (true, "Not configured")
+        } else if self.command_link_connected {
+            (true, "Connected")
+        } else {
+            (false, "Not connected")
+        };
+        report.set("command_link", Self::prematch_entry(passed, detail));
+
+        let problems = self.configuration_problems();
+        let detail = if problems.is_empty() { "OK".to_string() } else { problems.join("; ") };
+        report.set("config", Self::prematch_entry(problems.is_empty(), &detail));
+
+        // Latency needs a probe of its own, which comes back in process()
+        let probe_problem = if !self.running {
+            Some("Not started")
+        } else if self.override_mode != OverrideMode::Normal || self.simulating_connection_loss {
+            Some("Connection is overridden, nothing to measure")
+        } else {
+            None
+        };
+        match probe_problem {
+            Some(detail) => {
+                report.set("latency", Self::prematch_entry(false, detail));
+                self.prematch = None;
+                self.finish_prematch(report.clone());
+            }
+            None => {
+                report.set("latency", Self::prematch_entry(false, "Pending"));
+                let wait = Duration::from_millis(self.ping_timeout_ms.max(0) as u64) + PREMATCH_PROBE_GRACE;
+                self.prematch = Some((report.clone(), Instant::now() + wait));
+                self.fresh_probe_pending = true;
+                self.next_ping_delay = Duration::ZERO;
+            }
+        }
+
+        report
+    }
+
+    fn prematch_entry(passed: bool, detail: &str) -> Dictionary {
+        let mut entry = Dictionary::new();
+        entry.set("passed", passed);
+        entry.set("detail", detail);
+        entry
+    }
+
+    fn finish_prematch_latency(&mut self, passed: bool, detail: String) {
+        let Some((mut report, _)) = self.prematch.take() else {
+            return;
+        };
+        report.set("latency", Self::prematch_entry(passed, &detail));
+        self.finish_prematch(report);
+    }
+
+    fn finish_prematch(&mut self, report: Dictionary) {
+        let mut failed = Vec::new();
+        for (name, entry) in report.iter_shared() {
+            let passed = entry
+                .try_to::<Dictionary>()
+                .ok()
+                .and_then(|entry| entry.get("passed"))
+                .is_some_and(|passed| passed.booleanize());
+            if !passed {
+                failed.push(name.to_string());
+            }
+        }
+
+        if failed.is_empty() {
+            log_info!("Pre-match check passed");
+        } else {
+            log_warn!("Pre-match check failed: {}", failed.join(", "));
+        }
+        self.emit("prematch_check_finished", &[failed.is_empty().to_variant(), report.to_variant()]);
+    }
+
+    /// Everything a status page needs in one call. Keys are stable:
+    ///
+    /// - `running`, `connected`, `uptime_secs`
+    /// - `connection`: `state` ("connected", "disconnected",
+    ///   "forced_connected", "forced_disconnected" or "simulated_loss"),
+    ///   `override_active`, `address`, `port`, `local_address`, `latency_ms`
+    ///   (-1 if unknown), `quality` ("good", "degraded" or "down"),
+    ///   `last_error`, `last_error_kind`
+    /// - `controller`: `ready`, `thread_alive`, `state` ("ok", "restarting"
+    ///   or "failed"), `restarts`, `backend` ("Vigem", "HalSim" or "DryRun"),
+    ///   `dry_run`, `mode`, `face`, `action_set`, `authority` ("active", "standby" or "off"),
+    ///   `user_index` (XInput slot or sim joystick, -1 if unknown or dry
+    ///   run), `pressed` (PackedStringArray), `axes` (axis name -> value),
+    ///   `inputs_locked`
+    /// - `config`: `ping_interval_secs`, `ping_timeout_ms`,
+    ///   `persistent_connection`, `button_mapping` (action -> Xbox button)
+    /// - `counters`: `presses`, `pings`, `ping_failures`
+    /// - `metadata`: `team_number`, `event_code`, `match_label`
+    fn get_status(&self) -> Dictionary {
+        let controller = self.virtual_controller.as_ref();
+
+        let state = if self.simulating_connection_loss {
+            "simulated_loss"
+        } else if self.override_mode == OverrideMode::ForceConnected {
+            "forced_connected"
+        } else if self.override_mode == OverrideMode::ForceDisconnected {
+            "forced_disconnected"
+        } else if self.connected {
+            "connected"
+        } else {
+            "disconnected"
+        };
+
+        // Any recent failure or a slow tail counts against the link
+        let quality = if !self.connected {
+            "down"
+        } else if self.consecutive_failures > 0
+            || self.ping_stats.percentile(95.0).is_some_and(|p95| p95 > DEGRADED_LATENCY)
+        {
+            "degraded"
+        } else {
+            "good"
+        };
+
+        let mut connection = Dictionary::new();
+        connection.set("state", state);
+        connection.set("override_active", self.override_mode != OverrideMode::Normal);
+        connection.set("address", self.ping_address.clone());
+        connection.set("port", self.ping_port);
+        connection.set("local_address", self.local_address.clone());
+        connection.set("latency_ms", duration_ms(self.ping_stats.last).unwrap_or(-1.0));
+        connection.set("quality", quality);
+        connection.set("last_error", self.last_error.clone());
+        connection.set("last_error_kind", self.last_error_kind);
+
+        let user_index = match self.output_mode {
+            _ if self.dry_run => -1,
+            OutputMode::Vigem => controller.and_then(|c| c.user_index()).map_or(-1, i64::from),
+            OutputMode::HalSim => self.halsim_joystick,
+        };
+        let pressed: PackedStringArray = controller
+            .map(|c| c.pressed_buttons())
+            .unwrap_or_default()
+            .into_iter()
+            .map(GString::from)
+            .collect();
+        let values = controller.map(|c| c.axes()).unwrap_or_default();
+        let mut axes = Dictionary::new();
+        for (name, value) in AXIS_NAMES.iter().zip(values) {
+            axes.set(*name, value);
+        }
+
+        let mut controller_status = Dictionary::new();
+        controller_status.set("ready", controller.is_some_and(|c| c.is_running()));
+        controller_status.set("thread_alive", controller.is_some_and(|c| c.thread_alive()));
+        controller_status.set("state", self.controller_state);
+        controller_status.set("restarts", self.controller_restarts);
+        let backend = if self.dry_run {
+            "DryRun".to_string()
+        } else {
+            format!("{:?}", self.output_mode)
+        };
+        controller_status.set("backend", backend.as_str());
+        controller_status.set("dry_run", self.dry_run);
+        controller_status.set("mode", self.mode.clone());
+        controller_status.set("face", self.selected_face.clone());
+        controller_status.set("authority", self.authority_state());
+        controller_status.set("action_set", self.active_action_set.as_str());
+        controller_status.set("user_index", user_index);
+        controller_status.set("pressed", pressed);
+        controller_status.set("axes", axes);
+        controller_status.set("inputs_locked", self.inputs_locked);
+
+        let mut mapping = Dictionary::new();
+        for (action, button) in BUTTON_MAPPING {
+            mapping.set(action, button);
+        }
+
+        let mut config = Dictionary::new();
+        config.set("ping_interval_secs", self.ping_interval.as_secs_f64());
+        config.set("ping_timeout_ms", self.ping_timeout().as_millis() as i64);
+        config.set("persistent_connection", self.persistent_connection);
+        config.set("button_mapping", mapping);
+
+        let mut counters = Dictionary::new();
+        counters.set("presses", self.presses_this_session);
+        counters.set("pings", self.ping_stats.attempts);
+        counters.set("ping_failures", self.ping_stats.attempts - self.ping_stats.successes);
+
+        let mut status = Dictionary::new();
+        status.set("running", self.running);
+        status.set("connected", self.connected);
+        status.set("uptime_secs", self.start_time.elapsed().as_secs_f64());
+        status.set("connection", connection);
+        status.set("controller", controller_status);
+        status.set("config", config);
+        status.set("counters", counters);
+
+        let mut metadata = Dictionary::new();
+        metadata.set("team_number", self.team_number);
+        metadata.set("event_code", self.event_code.clone());
+        metadata.set("match_label", self.match_label.clone());
+        status.set("metadata", metadata);
+        status
+    }
+
+    fn status_json(&self) -> String {
+        let pressed = self
+            .virtual_controller
+            .as_ref()
+            .map(|controller| controller.pressed_buttons())
+            .unwrap_or_default();
+        let spare = self
+            .virtual_controller
+            .as_ref()
+            .map(|controller| controller.spare_buttons())
+            .unwrap_or_default();
+        let spare_bits = spare.iter().fold(0, |bits, button| bits | xinput_bit(button));
+
+        let mapping: serde_json::Map<String, serde_json::Value> = BUTTON_MAPPING
+            .iter()
+            .map(|(action, button)| (action.to_string(), json!(button)))
+            .collect();
+
+        let actions: Vec<serde_json::Value> = self
+            .recent_actions
+            .iter()
+            .map(|(time, name, pressed, origin)| json!({
+                "age_secs": time.elapsed().as_secs_f64(),
+                "action": name,
+                "pressed": pressed,
+                "origin": origin.as_str(),
+            }))
+            .collect();
+
+        let errors: Vec<serde_json::Value> = self
+            .recent_errors
+            .iter()
+            .map(|(time, message)| json!({
+                "age_secs": time.elapsed().as_secs_f64(),
+                "message": message,
+            }))
+            .collect();
+
+        let status_map = |statuses: &HashMap<String, ServiceStatus>| -> serde_json::Map<String, serde_json::Value> {
+            statuses
+                .iter()
+                .map(|(name, service)| (name.clone(), json!({
+                    "up": service.up,
+                    "required": service.required,
+                    "latency_ms": duration_ms(service.latency),
+                })))
+                .collect()
+        };
+        let services = status_map(&self.service_status);
+        let devices = status_map(&self.device_status);
+
+        json!({
+            "metadata": self.metadata_json(),
+            "connected": self.connected,
+            "force_connected": self.override_mode == OverrideMode::ForceConnected,
+            "override_mode": format!("{:?}", self.override_mode),
+            "simulating_connection_loss": self.simulating_connection_loss,
+            "robot_clock_offset_ms": self.clock_offset.offset_secs().map(|offset| offset * 1000.0),
+            "ping": {
+                "address": self.ping_address.to_string(),
+                "local_address": self.local_address.to_string(),
+                "verified": self.verified,
+                "port": self.ping_port,
+                "interval_secs": self.ping_interval.as_secs_f64(),
+                "persistent": self.persistent_connection,
+                "paused": self.probing_paused,
+                "timeout_ms": self.ping_timeout().as_millis() as u64,
+                "last_error": self.last_error.to_string(),
+                "last_error_kind": format!("{:?}", self.last_error_kind),
+            },
+            "latency": {
+                "last_ms": duration_ms(self.ping_stats.last),
+                "min_ms": duration_ms(self.ping_stats.min),
+                "avg_ms": duration_ms(self.ping_stats.average()),
+                "max_ms": duration_ms(self.ping_stats.max),
+                "samples": self.ping_stats.samples,
+            },
+            "controller": {
+                "ready": self.virtual_controller.as_ref().is_some_and(|c| c.is_running()),
+                "thread_alive": self.virtual_controller.as_ref().is_some_and(|c| c.thread_alive()),
+                "state": self.controller_state,
+                "restarts": self.controller_restarts,
+                "output_mode": format!("{:?}", self.output_mode),
+                "dry_run": self.dry_run,
+                "mode": self.mode.to_string(),
+                "face": self.selected_face.to_string(),
+                "authority": self.authority_state(),
+                "action_set": self.active_action_set,
+                "sim_connected": self.sim_output.as_ref().is_some_and(|sim| sim.is_connected()),
+                "bitmask": spare_bits | button_bitmask(&pressed),
+                "spare_buttons": spare,
+                "pressed": pressed,
+                "inputs_locked": self.inputs_locked,
+            },
+            "services": services,
+            "devices": devices,
+            "button_mapping": mapping,
+            "uptime_secs": self.start_time.elapsed().as_secs_f64(),
+            "connection": {
+                "time_in_current_state_secs": self.connection_history.time_in_current_state().as_secs_f64(),
+                "total_uptime_secs": self.connection_history.total_uptime().as_secs_f64(),
+                "total_downtime_secs": self.connection_history.total_downtime().as_secs_f64(),
+            },
+            "last_errors": errors,
+            "recent_actions": actions,
+        })
+        .to_string()
+    }
+
+    /// Start the HTTP status endpoint on `status_server_port`.
+    /// Returns false if it is already running or the port can't be bound.
+    fn start_status_server(&mut self) -> bool {
+        let port = match u16::try_from(self.status_server_port) {
+            Ok(port) => port,
+            Err(_) => {
+                log_error!("Invalid status server port: {}", self.status_server_port);
+                return false;
+            }
+        };
+
+        match self.status_server.start(port) {
+            Ok(()) => {
+                self.status_server.update(self.status_json());
+                self.last_status_update = Instant::now();
+                log_info!("Status server listening on port {}", port);
+                true
+            }
+            Err(e) => {
+                log_error!("Failed to start status server: {}", e);
+                false
+            }
+        }
+    }
+
+    fn stop_status_server(&mut self) {
+        self.status_server.stop();
+    }
+
+    fn poll_remote_server(&mut self) {
+        for event in self.remote_server.poll() {
+            match event {
+                RemoteEvent::Press { client, button } => {
+                    let result = self.source_pressed(&button, InputOrigin::Remote);
+                    let reply = json!({
+                        "type": "result",
+                        "cmd": "press",
+                        "button": button,
+                        "ok": result.going_out(),
+                        "error": format!("{:?}", result),
+                    });
+                    self.remote_server.send_to(client, &reply.to_string());
+                }
+                RemoteEvent::Release { client, button } => {
+                    let result = self.source_released(&button, InputOrigin::Remote);
+                    let reply = json!({
+                        "type": "result",
+                        "cmd": "release",
+                        "button": button,
+                        "ok": result == ActionResult::Ok,
+                        "error": format!("{:?}", result),
+                    });
+                    self.remote_server.send_to(client, &reply.to_string());
+                }
+                RemoteEvent::Heartbeat(heartbeat) => self.on_peer_heartbeat(heartbeat),
+                RemoteEvent::AuthorityRequested => self.on_authority_requested(),
+                RemoteEvent::Confirm { client, id, accept } => self.on_coach_reply(client, id, accept),
+            }
+        }
+
+        // Let clients know whenever the state changes
+        let state = self.remote_state_json();
+        if state != self.last_remote_state {
+            self.remote_server.broadcast_state(state.clone());
+            self.last_remote_state = state;
+        }
+    }
+
+    fn remote_state_json(&self) -> String {
+        let pressed = self
+            .virtual_controller
+            .as_ref()
+            .map(|controller| controller.pressed_buttons())
+            .unwrap_or_default();
+
+        json!({
+            "type": "state",
+            "connected": self.connected,
+            "pressed": pressed,
+        })
+        .to_string()
+    }
+
+    /// Start the WebSocket server on `remote_server_port`.
+    /// Returns false if it is already running or the port can't be bound.
+    fn start_remote_server(&mut self) -> bool {
+        let port = match u16::try_from(self.remote_server_port) {
+            Ok(port) => port,
+            Err(_) => {
+                log_error!("Invalid remote server port: {}", self.remote_server_port);
+                return false;
+            }
+        };
+
+        match self.remote_server.start(port, self.remote_auth_token.to_string()) {
+            Ok(()) => {
+                self.last_remote_state.clear();
+                log_info!("Remote server listening on port {}", port);
+                true
+            }
+            Err(e) => {
+                log_error!("Failed to start remote server: {}", e);
+                false
+            }
+        }
+    }
+
+    fn stop_remote_server(&mut self) {
+        for event in self.remote_server.stop() {
+            if let RemoteEvent::Release { button, .. } = event {
+                self.source_released(&button, InputOrigin::Remote);
+            }
+        }
+    }
+
+    /// Connect to the coprocessor at `command_address`:`command_port`,
+    /// replacing any existing link. Returns false if the target is invalid.
+    fn start_command_link(&mut self) -> bool {
+        self.stop_command_link();
+
+        let port = match u16::try_from(self.command_port) {
+            Ok(port) if port != 0 => port,
+            _ => {
+                log_error!("Invalid command port: {}", self.command_port);
+                return false;
+            }
+        };
+
+        if self.command_address.is_empty() {
+            log_error!("No command address set");
+            return false;
+        }
+
+        let timeout = Duration::from_millis(self.ping_timeout_ms as u64);
+        self.command_link = Some(CommandLink::start(self.command_address.to_string(), port, timeout));
+        log_info!("Command link to {}", format_endpoint(&self.command_address.to_string(), port as i64));
+        true
+    }
+
+    fn stop_command_link(&mut self) {
+        self.stop_command_link_by(Instant::now() + SHUTDOWN_TIMEOUT);
+    }
+
+    fn stop_command_link_by(&mut self, deadline: Instant) {
+        let Some(mut link) = self.command_link.take() else {
+            return;
+        };
+
+        link.shutdown_by(deadline);
+        for event in link.poll() {
+            self.apply_command_event(event);
+        }
+        self.command_link_connected = false;
+    }
+
+    /// Send `command` to the coprocessor as one line of JSON. Commands are
+    /// queued while the link is down; command_sent or command_send_failed
+    /// reports what happened to each one.
+    fn send_command(&mut self, command: Dictionary) -> bool {
+        let line = Json::stringify(&command.to_variant()).to_string();
+
+        let queued = match &self.command_link {
+            Some(link) => link.send(line),
+            None => false,
+        };
+
+        if !queued {
+            let args = [command.to_variant(), "command link not running".to_variant()];
+            self.emit("command_send_failed", &args);
+        }
+        queued
+    }
+
+    fn poll_command_link(&mut self) {
+        let events = match &self.command_link {
+            Some(link) => link.poll(),
+            None => return,
+        };
+
+        for event in events {
+            self.apply_command_event(event);
+        }
+    }
+
+    fn apply_command_event(&mut self, event: CommandEvent) {
+        match event {
+            CommandEvent::Connected(connected) => {
+                self.command_link_connected = connected;
+                if connected {
+                    log_info!("Command link connected");
+                    self.publish_alliance();
+                    self.publish_mode();
+                    self.publish_value(self.face_topic.clone(), self.selected_face.clone());
+                } else {
+                    log_warn!("Command link lost, reconnecting");
+                }
+            }
+            CommandEvent::Sent(line) => {
+                let args = [Self::json_dictionary(&line).to_variant()];
+                self.emit("command_sent", &args);
+            }
+            CommandEvent::Failed { command, reason } => {
+                log_warn!("Command not sent ({}): {}", reason, command);
+                let args = [Self::json_dictionary(&command).to_variant(), reason.to_variant()];
+                self.emit("command_send_failed", &args);
+            }
+            CommandEvent::Response(line) => match Json::parse_string(line.as_str()).try_to::<Dictionary>() {
+                Ok(response) => {
+                    self.emit("command_response", &[response.to_variant()]);
+                }
+                Err(_) => log_warn!("Ignoring malformed command response: {}", line),
+            },
+        }
+    }
+
+    fn json_dictionary(line: &str) -> Dictionary {
+        Json::parse_string(line).try_to::<Dictionary>().unwrap_or_default()
+    }
+
+    /// Tear down whatever controller output exists and set it up again for
+    /// the current output mode.
+    fn init_controller(&mut self) -> bool {
+        if let Some(mut sim) = self.sim_output.take() {
+            sim.close();
+        }
+        // If this was the last handle the pad is unplugged before trying again
+        self.virtual_controller = None;
+
+        let result = match self.output_mode {
+            _ if self.dry_run => Ok(SharedController::state_only()),
+            OutputMode::Vigem => SharedController::vigem(),
+            OutputMode::HalSim if self.start_sim_output() => Ok(SharedController::state_only()),
+            OutputMode::HalSim => Err("Invalid simulation output settings".to_string()),
+        };
+
+        match result {
+            Ok(controller) => {
+                if self.dry_run {
+                    log_info!("Dry run, presses are logged but not sent anywhere");
+                } else {
+                    log_info!("Virtual controller initialized");
+                }
+                self.virtual_controller = Some(controller);
+                self.controller_restart_failures = 0;
+                self.set_controller_state("ok");
+                self.apply_spare_bits();
+                self.record_match_event("controller", json!({ "ready": true, "dry_run": self.dry_run }));
+                true
+            }
+            Err(reason) => {
+                log_error!("Failed to initialize virtual controller: {}", reason);
+                self.record_match_event("controller", json!({ "ready": false, "error": reason }));
+                self.record_error(format!("Failed to initialize virtual controller: {}", reason));
+                self.emit("controller_init_failed", &[GString::from(reason).to_variant()]);
+                false
+            }
+        }
+    }
+
+    /// Switching while running lets go of everything and swaps the output
+    /// in place, so nothing pressed in one mode carries over to the other.
+    fn set_dry_run(&mut self, dry_run: bool) {
+        if self.dry_run == dry_run {
+            return;
+        }
+        self.dry_run = dry_run;
+        log_info!("Dry run {}", if dry_run { "on" } else { "off" });
+
+        if self.running {
+            self.neutralize_inputs("dry_run_changed");
+            if let Some(controller) = &self.virtual_controller {
+                controller.neutralize();
+            }
+            self.init_controller();
+        }
+        self.last_indicator_state = None;
+    }
+
+    /// Try to bring the virtual controller up again, e.g. after starting
+    /// ViGEm. Any pressed buttons are released.
+    fn retry_controller_init(&mut self) -> bool {
+        if !self.running {
+            log_warn!("Not started, the controller is plugged in by start()");
+            return false;
+        }
+        if let Some(controller) = &self.virtual_controller {
+            controller.neutralize();
+        }
+        for (name, origin, _) in std::mem::take(&mut self.pending_taps) {
+            self.action_holders.release(&name, origin);
+        }
+        self.init_controller()
+    }
+
+    /// Notice a control thread that died (e.g. panicked) and plug the pad
+    /// in again with the same buttons and axes held. After
+    /// controller_max_restarts failures in a row it stays "failed" until
+    /// retry_controller_init() or another node brings it back.
+    fn check_controller_thread(&mut self) {
+        let Some(controller) = self.virtual_controller.clone() else {
+            return;
+        };
+        if controller.thread_alive() {
+            if self.controller_state != "ok" {
+                self.controller_restart_failures = 0;
+                self.set_controller_state("ok");
+            }
+            return;
+        }
+        if self.controller_state == "failed" || Instant::now() < self.next_controller_restart {
+            return;
+        }
+
+        if self.controller_state == "ok" {
+            log_error!("Virtual controller thread died, restarting it");
+            self.record_error("Virtual controller thread died".to_string());
+            self.record_match_event("controller", json!({ "ready": false, "error": "thread died" }));
+            self.emit("controller_thread_died", &[]);
+        }
+
+        match controller.restart() {
+            Ok(()) => {
+                self.controller_restarts += 1;
+                self.controller_restart_failures = 0;
+                log_info!("Virtual controller thread restarted");
+                self.record_match_event("controller", json!({ "ready": true, "restarted": true }));
+                self.set_controller_state("ok");
+            }
+            Err(reason) => {
+                self.controller_restart_failures += 1;
+                self.next_controller_restart = Instant::now() + CONTROLLER_RESTART_DELAY;
+                if i64::from(self.controller_restart_failures) >= self.controller_max_restarts.max(1) {
+                    log_error!(
+                        "Giving up on the virtual controller after {} failed restarts: {}",
+                        self.controller_restart_failures,
+                        reason
+                    );
+                    self.record_error(format!("Virtual controller failed: {}", reason));
+                    self.set_controller_state("failed");
+                } else {
+                    log_warn!("Failed to restart the virtual controller: {}", reason);
+                    self.set_controller_state("restarting");
+                }
+            }
+        }
+    }
+
+    fn set_controller_state(&mut self, state: &'static str) {
+        if self.controller_state == state {
+            return;
+        }
+        self.controller_state = state;
+        self.emit("controller_state_changed", &[GString::from(state).to_variant()]);
+    }
+
+    fn is_controller_ready(&self) -> bool {
+        self.virtual_controller.as_ref().is_some_and(|c| c.is_running())
+    }
+
+    fn start_sim_output(&mut self) -> bool {
+        let port = match u16::try_from(self.halsim_port) {
+            Ok(port) if port != 0 => port,
+            _ => {
+                log_error!("Invalid halsim port: {}", self.halsim_port);
+                return false;
+            }
+        };
+
+        let Ok(joystick) = u8::try_from(self.halsim_joystick) else {
+            log_error!("Invalid halsim joystick: {}", self.halsim_joystick);
+            return false;
+        };
+
+        self.sim_output = Some(SimJoystickOutput::new(&self.halsim_address.to_string(), port, joystick));
+        true
+    }
+
+    fn is_known_button(name: &str) -> bool {
+        BUTTON_MAPPING.iter().any(|(action, _)| *action == name)
+    }
+
+    /// A controller action or hp_signal.
+    fn is_known_action(name: &str) -> bool {
+        name == HP_SIGNAL || Self::is_known_button(name)
+    }
+
+    fn known_actions() -> impl Iterator<Item = &'static str> {
+        BUTTON_MAPPING.iter().map(|(name, _)| *name).chain([HP_SIGNAL])
+    }
+
+    /// Send a press through to the virtual controller.
+    /// Every input source goes through here so they all get the same checks.
+    /// Scripts get the result back instead of a warning, so they can call
+    /// this every frame.
+    fn press_action(&mut self, name: &str, origin: InputOrigin) -> ActionResult {
+        if origin.is_operator()
+            && self.running_sequence.is_some()
+            && self.sequence_input_policy == SequenceInputPolicy::Abort
+        {
+            log_info!("{} pressed ({}), stopping the sequence", name, origin.as_str());
+            self.finish_sequence(false, "operator_input");
+        }
+        let result = self.forward_press(name, origin, false);
+        self.press_finished(name, origin, result);
+        result
+    }
+
+    /// Feedback for a press that went out or was turned away.
+    fn press_finished(&mut self, name: &str, origin: InputOrigin, result: ActionResult) {
+        // Only on-screen taps buzz, the operator is already touching the device
+        if origin == InputOrigin::Ui {
+            match result {
+                ActionResult::Ok if self.haptics_on_press => self.vibrate(self.haptics_press_ms),
+                ActionResult::NotConnected | ActionResult::InputsLocked | ActionResult::Vetoed
+                    if self.haptics_on_reject =>
+                {
+                    self.vibrate(self.haptics_reject_ms)
+                }
+                _ => {}
+            }
+        }
+
+        if matches!(
+            result,
+            ActionResult::NotConnected
+                | ActionResult::InputsLocked
+                | ActionResult::CoolingDown
+                | ActionResult::ActionDisabled
+                | ActionResult::EndgameLockout
+                | ActionResult::Standby
+                | ActionResult::AutoLockout
+                | ActionResult::SequenceRunning
+                | ActionResult::Vetoed
+        ) {
+            self.press_denied(name);
+        }
+    }
+
+    /// A press was turned away: count it and play denied_sound.
+    fn press_denied(&mut self, name: &str) {
+        *self.blocked_counts.entry(name.to_string()).or_insert(0) += 1;
+        self.record_match_event("blocked", json!({ "action": name }));
+        if let Some(sound) = self.denied_sound.clone() {
+            self.play_feedback_sound("denied", sound);
+        }
+    }
+
+    /// Play `sound` on the player kept for `kind`, following sound_overlap
+    /// if it's still going.
+    fn play_feedback_sound(&mut self, kind: &'static str, sound: Gd<AudioStream>) {
+        let mut player = match self.feedback_players.get(kind) {
+            Some(player) if player.is_instance_valid() => player.clone(),
+            _ => {
+                let player = AudioStreamPlayer::new_alloc();
+                self.add_child(player.clone().upcast());
+                self.feedback_players.insert(kind, player.clone());
+                player
+            }
+        };
+
+        if player.is_playing() && self.sound_overlap == SoundOverlap::Skip {
+            return;
+        }
+        player.set_stream(&sound);
+        self.call_later(player.upcast(), "play", &[]);
+    }
+
+    /// Checks and sends a press. `filtered` once the press filter has let
+    /// it through, see press_filter_answered.
+    fn forward_press(&mut self, name: &str, origin: InputOrigin, filtered: bool) -> ActionResult {
+        if let Err(result) = self.check_press(name, origin) {
+            let blocked = match result {
+                ActionResult::CoolingDown => {
+                    Some(("cooldown", self.cooldown_remaining(name).unwrap_or_default()))
+                }
+                ActionResult::ActionDisabled => Some(("disabled", Duration::ZERO)),
+                ActionResult::EndgameLockout => Some(("endgame_lockout", self.time_until_endgame())),
+                ActionResult::Standby => Some(("standby", Duration::ZERO)),
+                ActionResult::AutoLockout => Some(("autonomous", Duration::ZERO)),
+                ActionResult::SequenceRunning => Some(("sequence", Duration::ZERO)),
+                _ => None,
+            };
+            if let Some((reason, remaining)) = blocked {
+                let args = [
+                    StringName::from(name).to_variant(),
+                    remaining.as_secs_f64().to_variant(),
+                    StringName::from(reason).to_variant(),
+                ];
+                self.emit("press_blocked", &args);
+            }
+            return result;
+        }
+        if self.virtual_controller.is_none() {
+            return ActionResult::ControllerNotReady;
+        }
+
+        if name == HP_SIGNAL {
+            if !filtered && self.hold_for_filter(name, origin) {
+                return ActionResult::Filtering;
+            }
+            self.fire_hp_signal(origin);
+            return ActionResult::Ok;
+        }
+
+        // Holding a button that is already down changes nothing
+        if self.is_output_down(name) {
+            return ActionResult::Ok;
+        }
+
+        if !filtered && self.hold_for_filter(name, origin) {
+            return ActionResult::Filtering;
+        }
+
+        let button = self.output_button(name);
+        if let Some(controller) = &self.virtual_controller {
+            if controller.set_button(&button, true).is_err() {
+                return ActionResult::ControllerNotReady;
+            }
+        }
+        self.press_sent(name, origin);
+        ActionResult::Ok
+    }
+
+    /// Hold the press back for the press filter, if one is set. Returns
+    /// whether it was.
+    fn hold_for_filter(&mut self, name: &str, origin: InputOrigin) -> bool {
+        if self.press_filter.is_none() {
+            return false;
+        }
+        self.filter_checks.push_back(FilterCheck {
+            action: name.to_string(),
+            origin,
+            undoing: self.undoing,
+        });
+        true
+    }
+
+    /// The next press waiting on the press filter, and the filter to ask.
+    /// The node asks it after the call that made the press has returned,
+    /// so the filter can read and call the node like any other script.
+    fn next_filter_check(&mut self) -> Option<(FilterCheck, Option<Callable>)> {
+        let check = self.filter_checks.pop_front()?;
+        Some((check, self.press_filter.clone()))
+    }
+
+    /// Send or drop a press once the filter has answered. Anything but a
+    /// bool back, e.g. after a script error, vetoes it.
+    fn press_filter_answered(&mut self, check: FilterCheck, answer: Variant) {
+        let FilterCheck { action: name, origin, undoing } = check;
+
+        // Let go of while it waited, e.g. neutralized
+        if name != HP_SIGNAL && !self.action_holders.is_held(&name) {
+            log_debug!("{} was let go of before the press filter answered", name);
+            return;
+        }
+
+        let allowed = match answer.try_to::<bool>() {
+            Ok(allowed) => allowed,
+            Err(_) => {
+                log_error!("Press filter returned {} for {}, expected a bool. Vetoing", answer, name);
+                false
+            }
+        };
+        let result = if allowed {
+            self.undoing = undoing;
+            let result = self.forward_press(&name, origin, true);
+            self.undoing = false;
+            result
+        } else {
+            log_info!("Press of {} vetoed by the press filter ({})", name, origin.as_str());
+            self.emit("press_vetoed", &[StringName::from(name.as_str()).to_variant()]);
+            ActionResult::Vetoed
+        };
+        self.press_finished(&name, origin, result);
+
+        if result != ActionResult::Ok && name != HP_SIGNAL {
+            // The hold and any tap release were waiting on this press
+            self.pending_taps.retain(|(tapped, tapper, _)| *tapped != name || *tapper != origin);
+            self.action_holders.release(&name, origin);
+        }
+    }
+
+    /// Check every press with `filter(action_name, origin)` before it's
+    /// sent, false vetoes it. The filter is asked once the call that made
+    /// the press returns, which gets Filtering back, so it can read and
+    /// call this node. A null Callable removes the filter.
+    ///
+    /// ```gdscript
+    /// # No coral from the high level while the arm is stowed
+    /// iface.set_press_filter(func(action, _origin):
+    ///     return action != "coral" or iface.selected_level != "high" or arm_ready)
+    /// ```
+    fn set_press_filter(&mut self, filter: Callable) {
+        self.press_filter = filter.is_valid().then_some(filter);
+    }
+
+    /// Whether a press of `name` would go out now, logging why not unless
+    /// it's from a script.
+    fn check_press(&self, name: &str, origin: InputOrigin) -> Result<(), ActionResult> {
+        let quiet = origin == InputOrigin::Script;
+
+        if !Self::is_known_action(name) {
+            if !quiet {
+                log_warn!("Unknown button {} ({})", name, origin.as_str());
+            }
+            return Err(ActionResult::UnknownButton);
+        }
+
+        // Remote presses of these wait in source_pressed for the
+        // confirmation, they never go out directly
+        if origin == InputOrigin::Remote
+            && (self.confirm_actions.contains(&GString::from(name))
+                || self.coach_confirm_actions.contains(&GString::from(name)))
+        {
+            log_warn!("{} needs confirmation, not sending it for a remote client", name);
+            return Err(ActionResult::Pending);
+        }
+
+        if self.action_disabled(name) {
+            if !quiet {
+                log_debug!("{} is disabled ({})", name, origin.as_str());
+            }
+            return Err(ActionResult::ActionDisabled);
+        }
+
+        if self.endgame_locked(name) {
+            if !quiet {
+                log_warn!("{} is locked out until endgame ({})", name, origin.as_str());
+            }
+            return Err(ActionResult::EndgameLockout);
+        }
+
+        if self.on_standby() {
+            if !quiet {
+                log_warn!("On standby, the other instance has authority, not sending {} ({})", name, origin.as_str());
+            }
+            return Err(ActionResult::Standby);
+        }
+
+        if self.auto_lockout_active() {
+            if !quiet {
+                log_warn!("Robot is in {}, not sending {} ({})", self.robot_mode, name, origin.as_str());
+            }
+            return Err(ActionResult::AutoLockout);
+        }
+
+        if origin.is_operator()
+            && self.running_sequence.is_some()
+            && self.sequence_input_policy == SequenceInputPolicy::Block
+        {
+            if !quiet {
+                log_info!("Sequence running, not sending {} ({})", name, origin.as_str());
+            }
+            return Err(ActionResult::SequenceRunning);
+        }
+
+        if !self.connected {
+            if !quiet {
+                log_warn!("Not connected, cannot send button press {} ({})", name, origin.as_str());
+            }
+            return Err(ActionResult::NotConnected);
+        }
+
+        if self.inputs_locked {
+            if !quiet {
+                log_warn!("Inputs locked, re-arm before sending {} ({})", name, origin.as_str());
+            }
+            return Err(ActionResult::InputsLocked);
+        }
+
+        if let Some(remaining) = self.cooldown_remaining(name) {
+            if !quiet {
+                log_debug!("{} cooling down for {:.1}s ({})", name, remaining.as_secs_f64(), origin.as_str());
+            }
+            return Err(ActionResult::CoolingDown);
+        }
+
+        if self.virtual_controller.is_none() {
+            return Err(ActionResult::ControllerNotReady);
+        }
+        Ok(())
+    }
+
+    /// Bookkeeping for a press that has been set on the controller.
+    fn press_sent(&mut self, name: &str, origin: InputOrigin) {
+        self.held_since.insert(name.to_string(), Instant::now());
+        if name == "climb" {
+            self.cancel_auto_zero();
+        }
+
+        let count = self.action_counts.entry(name.to_string()).or_insert(0);
+        *count += 1;
+        let args = [StringName::from(name).to_variant(), count.to_variant()];
+        self.emit("action_count_changed", &args);
+        self.record_match_event("press", json!({ "action": name, "origin": origin.as_str() }));
+        self.track_cycles(name);
+
+        if !self.undoing {
+            self.undo_history.retain(|pressed| pressed != name);
+            if self.undo_history.len() >= MAX_UNDO_HISTORY {
+                self.undo_history.pop_front();
+            }
+            self.undo_history.push_back(name.to_string());
+        }
+        self.last_action = Some((name.to_string(), origin, Instant::now()));
+        self.update_last_action_labels();
+
+        let sound = self
+            .action_press_sounds
+            .get(name)
+            .and_then(|sound| sound.try_to::<Gd<AudioStream>>().ok())
+            .or_else(|| self.press_sound.clone());
+        if let Some(sound) = sound {
+            self.play_feedback_sound("press", sound);
+        }
+
+        match self.cooldown.get(name).map(|secs| variant_duration(&secs, 1.0, "seconds")) {
+            Some(Ok(Some(cooldown))) => match Instant::now().checked_add(cooldown) {
+                Some(until) => {
+                    self.cooldown_until.insert(name.to_string(), until);
+                }
+                None => log_warn!("Cooldown for {} is too long, ignoring it", name),
+            },
+            Some(Err(e)) => log_warn!("Cooldown for {} {}, ignoring it", name, e),
+            Some(Ok(None)) | None => {}
+        }
+
+        log_info!("Button {} pressed ({})", name, origin.as_str());
+        self.presses_this_session += 1;
+        self.record_action(name, true, origin);
+
+        if origin != InputOrigin::Ui {
+            self.mirror_press(name);
+        }
+
+        if self.ack_enabled {
+            self.await_ack(name);
+        }
+
+        let args = [StringName::from(name).to_variant(), StringName::from(origin.as_str()).to_variant()];
+        self.emit("action_pressed", &args);
+    }
+
+    /// Send a release through to the virtual controller. Only for callers
+    /// that have already let go of their hold in action_holders, i.e.
+    /// source_released, unhold_action and drop_action, so no other source
+    /// holding the action loses it.
+    fn release_action(&mut self, name: &str, origin: InputOrigin) -> ActionResult {
+        // A tap, it lets go by itself
+        if name == HP_SIGNAL {
+            return ActionResult::Ok;
+        }
+        if !Self::is_known_button(name) {
+            return ActionResult::UnknownButton;
+        }
+
+        self.clear_mirrored_press(Some(name));
+
+        // Releases go through whatever the connection is doing, only
+        // presses are gated. A release held back here would leave the
+        // button asserted on the robot once the link comes back
+        if self.virtual_controller.is_none() {
+            return ActionResult::ControllerNotReady;
+        }
+
+        if !self.is_output_down(name) {
+            return ActionResult::Ok;
+        }
+
+        let button = self.output_button(name);
+        if let Some(controller) = &self.virtual_controller {
+            // Refused only while shutting down, when nothing goes out anyway
+            let _ = controller.set_button(&button, false);
+        }
+        self.release_sent(name, origin);
+        ActionResult::Ok
+    }
+
+    /// Hold `name` for a script, macro or sequence, counted with the other
+    /// sources so it stays down while any of them holds it. Skips the
+    /// hold-to-activate and confirmation steps, which are for the operator.
+    fn hold_action(&mut self, name: &str, origin: InputOrigin) -> ActionResult {
+        // A tap, nothing to hold
+        if name == HP_SIGNAL {
+            return self.press_action(name, origin);
+        }
+        if !self.action_holders.hold(name, origin) && self.is_output_down(name) {
+            return ActionResult::Ok;
+        }
+
+        let result = self.press_action(name, origin);
+        if !result.going_out() {
+            self.action_holders.release(name, origin);
+        }
+        result
+    }
+
+    /// Let go of a hold_action() hold, releasing `name` once nothing else
+    /// holds it.
+    fn unhold_action(&mut self, name: &str, origin: InputOrigin) -> ActionResult {
+        if !self.action_holders.release(name, origin) {
+            return ActionResult::Ok;
+        }
+        self.release_action(name, origin)
+    }
+
+    /// Send a human-player signal that passed check_press: count it, start
+    /// its cooldown and put it out on the button and topic.
+    fn fire_hp_signal(&mut self, origin: InputOrigin) {
+        let count = self.action_counts.entry(HP_SIGNAL.to_string()).or_insert(0);
+        *count += 1;
+        let count = *count;
+        let args = [StringName::from(HP_SIGNAL).to_variant(), count.to_variant()];
+        self.emit("action_count_changed", &args);
+
+        if self.hp_signal_cooldown_secs > 0.0 {
+            let until = Instant::now() + Duration::from_secs_f64(self.hp_signal_cooldown_secs);
+            self.cooldown_until.insert(HP_SIGNAL.to_string(), until);
+        }
+
+        if self.hp_signal_bit().is_some() {
+            let tap = Duration::from_millis(self.hp_signal_tap_ms.max(0) as u64);
+            self.hp_signal_release_at = Some(Instant::now() + tap);
+            self.apply_spare_bits();
+        }
+        self.publish_value(self.hp_signal_topic.clone(), GString::from(count.to_string()));
+        if self.ack_enabled {
+            self.await_ack(HP_SIGNAL);
+        }
+
+        log_info!("Human player signal #{} sent ({})", count, origin.as_str());
+        self.record_match_event("press", json!({ "action": HP_SIGNAL, "origin": origin.as_str() }));
+        self.track_cycles(HP_SIGNAL);
+        if let Some(sound) = self.press_sound.clone() {
+            self.play_feedback_sound("press", sound);
+        }
+        self.emit("hp_signal_sent", &[count.to_variant()]);
+    }
+
+    /// The hp_signal count and cooldown belong to one match.
+    fn reset_hp_signal(&mut self) {
+        self.cooldown_until.remove(HP_SIGNAL);
+        if self.action_counts.remove(HP_SIGNAL).is_some() {
+            let args = [StringName::from(HP_SIGNAL).to_variant(), 0i64.to_variant()];
+            self.emit("action_count_changed", &args);
+        }
+    }
+
+    /// Bookkeeping for a release that has been set on the controller.
+    fn release_sent(&mut self, name: &str, origin: InputOrigin) {
+        self.held_since.remove(name);
+        if name == "climb" && self.auto_zero_after_climb {
+            self.schedule_auto_zero();
+        }
+        if let Some(sound) = self.release_sound.clone() {
+            self.play_feedback_sound("release", sound);
+        }
+        log_info!("Button {} released ({})", name, origin.as_str());
+        self.record_action(name, false, origin);
+        self.record_match_event("release", json!({ "action": name, "origin": origin.as_str() }));
+
+        let args = [StringName::from(name).to_variant(), StringName::from(origin.as_str()).to_variant()];
+        self.emit("action_released", &args);
+    }
+
+    /// Release `old`, held by `holder`, and press `new` for `origin` in the
+    /// same controller report, as a radio group switching selection. Falls
+    /// back to a separate release and press when something else holds
+    /// either action or the press needs a hold, confirmation or the press
+    /// filter first.
+    fn switch_action(&mut self, old: &str, new: &str, holder: InputOrigin, origin: InputOrigin) {
+        let held_elsewhere = self.action_holders.count(old) != 1 || self.action_holders.is_held(new);
+        let deferred = self.hold_duration(new).is_some()
+            || self.confirm_actions.contains(&GString::from(new))
+            || self.press_filter.is_some();
+        let old_down = self.is_output_down(old);
+
+        if held_elsewhere || deferred || !old_down || self.check_press(new, origin).is_err() {
+            self.source_released(old, holder);
+            self.source_pressed(new, origin);
+            return;
+        }
+
+        let (old_button, new_button) = (self.output_button(old), self.output_button(new));
+        if let Some(controller) = &self.virtual_controller {
+            if controller.set_buttons(&[(old_button.as_str(), false), (new_button.as_str(), true)]).is_err() {
+                return;
+            }
+        }
+
+        self.action_holders.remove(old);
+        self.action_holders.hold(new, origin);
+        self.clear_mirrored_press(Some(old));
+        self.release_sent(old, origin);
+        self.press_sent(new, origin);
+        if origin == InputOrigin::Ui && self.haptics_on_press {
+            self.vibrate(self.haptics_press_ms);
+        }
+    }
+
+    /// Show a press that didn't come from the screen on the action's bound
+    /// buttons. set_pressed_no_signal doesn't emit button_down or toggled, so
+    /// this never feeds back into the pipeline.
+    fn mirror_press(&mut self, name: &str) {
+        for binding in &self.action_bindings {
+            if binding.action != name || !binding.node.is_instance_valid() {
+                continue;
+            }
+            let Ok(mut button) = binding.node.clone().try_cast::<BaseButton>() else {
+                continue;
+            };
+            if button.is_pressed() {
+                continue;
+            }
+
+            // Only toggle buttons keep a pressed state, lend the mode until release
+            let was_toggle = button.is_toggle_mode();
+            if !was_toggle {
+                button.set_toggle_mode(true);
+            }
+            button.set_pressed_no_signal(true);
+            self.mirrored_presses.push(MirroredPress {
+                action: name.to_string(),
+                button,
+                was_toggle,
+            });
+        }
+    }
+
+    /// Put mirrored buttons back as they were, for one action or all of them.
+    fn clear_mirrored_press(&mut self, name: Option<&str>) {
+        let (cleared, kept): (Vec<_>, Vec<_>) = self
+            .mirrored_presses
+            .drain(..)
+            .partition(|mirrored| match name {
+                Some(name) => mirrored.action == name,
+                None => true,
+            });
+        self.mirrored_presses = kept;
+
+        for mut mirrored in cleared {
+            if !mirrored.button.is_instance_valid() {
+                continue;
+            }
+            mirrored.button.set_pressed_no_signal(false);
+            if !mirrored.was_toggle {
+                mirrored.button.set_toggle_mode(false);
+            }
+        }
+    }
+
+    /// Move an analog axis (see AXIS_NAMES), e.g. from an on-screen stick.
+    /// Sticks take -1 to 1, triggers 0 to 1.
+    fn set_axis(&mut self, axis: StringName, value: f64) -> ActionResult {
+        if self.on_standby() {
+            return ActionResult::Standby;
+        }
+        if self.auto_lockout_active() {
+            return ActionResult::AutoLockout;
+        }
+        if !self.connected {
+            return ActionResult::NotConnected;
+        }
+        if self.inputs_locked {
+            return ActionResult::InputsLocked;
+        }
+        let Some(controller) = &self.virtual_controller else {
+            return ActionResult::ControllerNotReady;
+        };
+
+        match controller.set_axis(&axis.to_string(), value) {
+            Ok(()) => ActionResult::Ok,
+            Err(InputError::UnknownAxis) => ActionResult::UnknownAxis,
+            Err(InputError::ShuttingDown) => ActionResult::ControllerNotReady,
+        }
+    }
+
+    /// Press `name` through the same checks as the on-screen buttons.
+    fn press_button(&mut self, name: StringName) -> ActionResult {
+        self.hold_action(&name.to_string(), InputOrigin::Script)
+    }
+
+    /// Signal the human player, same as a press of "hp_signal".
+    fn send_hp_signal(&mut self) -> ActionResult {
+        self.press_action(HP_SIGNAL, InputOrigin::Script)
+    }
+
+    /// Let go of a press_button() press, and of a tap of `name` that is
+    /// still running. It stays down while anything else holds it.
+    fn release_button(&mut self, name: StringName) -> ActionResult {
+        let name = name.to_string();
+        let (ended, running): (Vec<_>, Vec<_>) =
+            self.pending_taps.drain(..).partition(|(tapped, ..)| *tapped == name);
+        self.pending_taps = running;
+        for (name, origin, _) in ended {
+            self.unhold_action(&name, origin);
+        }
+        self.unhold_action(&name, InputOrigin::Script)
+    }
+
+    /// Press `name` and release it again after `duration_ms`.
+    fn tap_button(&mut self, name: StringName, duration_ms: i64) -> ActionResult {
+        let duration = Duration::from_millis(duration_ms.max(0) as u64);
+        self.start_tap(&name.to_string(), InputOrigin::Script, duration)
+    }
+
+    /// Press `name` and let go after `duration`, the tap holding it like
+    /// any other source. Tapping again while a tap is running only moves
+    /// its release.
+    fn start_tap(&mut self, name: &str, origin: InputOrigin, duration: Duration) -> ActionResult {
+        let running = self.pending_taps.iter().any(|(tapped, ..)| tapped == name);
+        let result = if running {
+            self.press_action(name, origin)
+        } else {
+            self.hold_action(name, origin)
+        };
+        if !result.going_out() {
+            return result;
+        }
+
+        let release_at = Instant::now() + duration;
+        match self.pending_taps.iter_mut().find(|(tapped, ..)| tapped == name) {
+            Some(tap) => tap.2 = release_at,
+            None => self.pending_taps.push((name.to_string(), origin, release_at)),
+        }
+        result
+    }
+
+    /// Tap the inverse of the most recent press, per inverse_actions.
+    /// Calling it again undoes the press before that, and so on. Does
+    /// nothing if that press has no inverse.
+    fn undo_last_action(&mut self) -> ActionResult {
+        let Some(original) = self.undo_history.back().cloned() else {
+            return ActionResult::NothingToUndo;
+        };
+        let Some(inverse) = self.inverse_actions.get(original.as_str()).map(|inverse| inverse.to_string()) else {
+            log_warn!("No inverse configured for {}, nothing undone", original);
+            return ActionResult::NoInverse;
+        };
+
+        self.undoing = true;
+        let result = self.tap_button(StringName::from(inverse.as_str()), self.undo_tap_ms);
+        self.undoing = false;
+        if !result.going_out() {
+            return result;
+        }
+
+        log_info!("Undid {} with {}", original, inverse);
+        self.undo_history.pop_back();
+        let args = [StringName::from(original.as_str()).to_variant(), StringName::from(inverse.as_str()).to_variant()];
+        self.emit("action_undone", &args);
+        result
+    }
+
+    fn release_finished_taps(&mut self) {
+        let now = Instant::now();
+        let (finished, pending): (Vec<_>, Vec<_>) =
+            self.pending_taps.drain(..).partition(|(.., at)| now >= *at);
+        self.pending_taps = pending;
+
+        for (name, origin, _) in finished {
+            self.unhold_action(&name, origin);
+        }
+    }
+
+    /// Buzz a handheld device. A no-op where Godot has no haptics, and
+    /// dropped if another buzz just went out so rapid taps don't queue up.
+    fn vibrate(&mut self, duration_ms: i64) {
+        if duration_ms <= 0 || self.last_vibration.is_some_and(|last| last.elapsed() < HAPTIC_MIN_INTERVAL) {
+            return;
+        }
+
+        self.last_vibration = Some(Instant::now());
+        Input::singleton().vibrate_handheld_ex().duration_ms(duration_ms as i32).done();
+    }
+
+    fn set_alliance(&mut self, alliance: GString) {
+        let alliance = alliance.to_string().to_lowercase();
+        if !ALLIANCES.contains(&alliance.as_str()) {
+            log_warn!("Unknown alliance \"{}\", expected red, blue or unknown", alliance);
+            return;
+        }
+        self.alliance_manual = true;
+        self.apply_alliance(alliance);
+    }
+
+    /// Go back to the alliance the FMS reports.
+    fn clear_alliance_override(&mut self) {
+        self.alliance_manual = false;
+        self.apply_alliance(self.detected_alliance.clone());
+    }
+
+    /// For bridges forwarding FMSInfo/IsRedAlliance. Ignored while the
+    /// alliance is set by hand.
+    fn report_fms_alliance(&mut self, is_red: bool) {
+        self.detected_alliance = if is_red { "red" } else { "blue" }.to_string();
+        if !self.alliance_manual {
+            self.apply_alliance(self.detected_alliance.clone());
+        }
+    }
+
+    fn apply_alliance(&mut self, alliance: String) {
+        if self.alliance.to_string() == alliance {
+            return;
+        }
+        log_info!("Alliance {} -> {}", self.alliance, alliance);
+        self.alliance = GString::from(alliance.as_str());
+        self.publish_alliance();
+        self.emit("alliance_changed", &[StringName::from(alliance.as_str()).to_variant()]);
+    }
+
+    /// Send the alliance to the robot, again on every command link connect.
+    fn publish_alliance(&mut self) {
+        self.publish_value(self.alliance_topic.clone(), self.alliance.clone());
+    }
+
+    fn publish_mode(&mut self) {
+        self.publish_value(self.mode_topic.clone(), self.mode.clone());
+    }
+
+    fn publish_value(&mut self, topic: GString, value: GString) {
+        if self.command_link.is_none() || topic.is_empty() {
+            return;
+        }
+        let mut command = Dictionary::new();
+        command.set("publish", topic);
+        command.set("value", value);
+        self.send_command(command);
+    }
+
+    /// Select a reef face, "A" to "F", or "none". The spare buttons for the
+    /// old face are swapped for the new face's in a single report.
+    fn set_face(&mut self, face: GString) {
+        let new = face.to_string().to_uppercase();
+        let new = if new.is_empty() || new == "NONE" { "none".to_string() } else { new };
+        if new != "none" && !REEF_FACES.contains(&new.as_str()) {
+            log_warn!("Unknown reef face \"{}\", expected A to F or none", face);
+            return;
+        }
+        self.face_deselect_pending = false;
+
+        let old = self.selected_face.to_string();
+        if old == new {
+            return;
+        }
+        self.selected_face = GString::from(new.as_str());
+        log_info!("Reef face {} -> {}", old, new);
+
+        self.apply_spare_bits();
+        self.sync_face_buttons();
+        self.publish_value(self.face_topic.clone(), self.selected_face.clone());
+        let args = [StringName::from(new.as_str()).to_variant(), StringName::from(old.as_str()).to_variant()];
+        self.emit("face_changed", &args);
+    }
+
+    /// Spare buttons that encode `face` per face_button_bits.
+    fn face_bits(&self, face: &str) -> Vec<&'static str> {
+        let Some(buttons) = self.face_button_bits.get(face) else {
+            return Vec::new();
+        };
+        let Some(buttons) = variant_strings(&buttons) else {
+            log_warn!("Face {} is not a list of spare buttons", face);
+            return Vec::new();
+        };
+        let mut spare = Vec::new();
+        for button in buttons {
+            let button = button.to_uppercase();
+            match SPARE_BUTTONS.iter().find(|spare| **spare == button) {
+                Some(spare_button) => spare.push(*spare_button),
+                None => log_warn!("Face {} uses {}, which isn't a spare button", face, button),
+            }
+        }
+        spare
+    }
+
+    /// The spare button hp_signal is tapped on, if it's set and valid.
+    fn hp_signal_bit(&self) -> Option<&'static str> {
+        let button = self.hp_signal_button.to_string().to_uppercase();
+        if button.is_empty() {
+            return None;
+        }
+        let spare = SPARE_BUTTONS.iter().find(|spare| **spare == button).copied();
+        if spare.is_none() {
+            log_warn!("hp_signal_button {} isn't a spare button", button);
+        }
+        spare
+    }
+
+    /// Put the face bits, plus hp_signal while it's tapped, on the pad
+    /// in one go.
+    fn apply_spare_bits(&mut self) {
+        if self.face_button_bits.is_empty() && self.hp_signal_button.is_empty() {
+            return;
+        }
+        // Standby and lockouts keep the pad neutral
+        let mut bits = Vec::new();
+        if !self.on_standby() && !self.auto_lockout_active() {
+            bits = self.face_bits(&self.selected_face.to_string());
+            if self.hp_signal_release_at.is_some() {
+                bits.extend(self.hp_signal_bit());
+            }
+        }
+        if let Some(controller) = &self.virtual_controller {
+            let _ = controller.set_spare_buttons(&bits);
+        }
+    }
+
+    /// Bind six toggle buttons (face → node path) as a radio group for
+    /// the face selection. Unpressing the selected one selects none.
+    fn bind_face_buttons(&mut self, buttons: Dictionary) -> godot::global::Error {
+        let mut members = Vec::new();
+        for (face, path) in buttons.iter_shared() {
+            let face = face.to_string().to_uppercase();
+            if !REEF_FACES.contains(&face.as_str()) {
+                log_warn!("Cannot bind unknown reef face \"{}\"", face);
+                return godot::global::Error::ERR_INVALID_PARAMETER;
+            }
+            let path = path.try_to::<NodePath>().unwrap_or_default();
+            let Some(button) = self.node()
+                .get_node_or_null(&path)
+                .and_then(|node| node.try_cast::<BaseButton>().ok())
+            else {
+                log_warn!("Cannot bind face {}: {} is not a button", face, path);
+                return godot::global::Error::ERR_DOES_NOT_EXIST;
+            };
+            members.push((face, button));
+        }
+
+        self.unbind_face_buttons();
+
+        let mut button_group = ButtonGroup::new_gd();
+        button_group.set_allow_unpress(true);
+        let base_obj = self.node();
+        for (face, mut button) in members {
+            button.set_toggle_mode(true);
+            button.set_button_group(&button_group);
+            button.set_pressed_no_signal(face == self.selected_face.to_string());
+
+            let callable = Callable::from_object_method(&base_obj, "on_face_button_toggled")
+                .bind(&[GString::from(face.as_str()).to_variant()]);
+            let result = button.connect("toggled", &callable);
+            if result != godot::global::Error::OK {
+                log_error!("Failed to connect toggled for face {}: {:?}", face, result);
+                continue;
+            }
+            self.face_buttons.push((face, button, callable));
+        }
+        godot::global::Error::OK
+    }
+
+    fn unbind_face_buttons(&mut self) {
+        for (_, mut button, callable) in self.face_buttons.drain(..) {
+            if button.is_instance_valid() && button.is_connected("toggled", &callable) {
+                button.disconnect("toggled", &callable);
+            }
+        }
+        self.face_deselect_pending = false;
+    }
+
+    fn on_face_button_toggled(&mut self, toggled_on: bool, face: GString) {
+        if toggled_on {
+            self.set_face(face);
+        } else if self.selected_face == face {
+            self.face_deselect_pending = true;
+        }
+    }
+
+    fn sync_face_buttons(&mut self) {
+        let selected = self.selected_face.to_string();
+        for (face, button, _) in self.face_buttons.iter_mut() {
+            if button.is_instance_valid() && button.is_pressed() != (*face == selected) {
+                button.set_pressed_no_signal(*face == selected);
+            }
+        }
+    }
+
+    /// Switch game-piece mode. Anything held whose button changes in the
+    /// new mode is released first, so no bit is left behind under the old
+    /// mapping.
+    fn set_mode(&mut self, mode: GString) {
+        let new = mode.to_string().to_lowercase();
+        if new.is_empty() {
+            log_warn!("Mode name can't be empty");
+            return;
+        }
+        let old = self.mode.to_string();
+        if old == new {
+            return;
+        }
+
+        let remapped: Vec<String> = self
+            .held_since
+            .keys()
+            .chain(self.action_holders.actions())
+            .filter(|action| self.output_button_in(&old, action) != self.output_button_in(&new, action))
+            .cloned()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        // All of them let go in the same report
+        let batch = self.virtual_controller.as_ref().map(|controller| controller.batch());
+        for action in remapped {
+            log_info!("Releasing {} before switching to {} mode", action, new);
+            self.drop_action(&action);
+        }
+        drop(batch);
+
+        self.mode = GString::from(new.as_str());
+        log_info!("Mode {} -> {}", old, new);
+        self.publish_mode();
+        let args = [StringName::from(new.as_str()).to_variant(), StringName::from(old.as_str()).to_variant()];
+        self.emit("mode_changed", &args);
+    }
+
+    /// Controller button `action` drives in the current mode, named by the
+    /// BUTTON_MAPPING action that owns it.
+    fn output_button(&self, action: &str) -> String {
+        self.output_button_in(&self.mode.to_string(), action)
+    }
+
+    fn output_button_in(&self, mode: &str, action: &str) -> String {
+        let Some(xbox_button) = self
+            .mode_mappings
+            .get(mode)
+            .and_then(|mapping| mapping.try_to::<Dictionary>().ok())
+            .and_then(|mapping| mapping.get(action))
+        else {
+            return action.to_string();
+        };
+
+        let xbox_button = xbox_button.to_string().to_uppercase();
+        match BUTTON_MAPPING.iter().find(|(_, button)| *button == xbox_button) {
+            Some((owner, _)) => owner.to_string(),
+            None => {
+                log_warn!("{} mode maps {} to {}, which isn't a mapped button", mode, action, xbox_button);
+                action.to_string()
+            }
+        }
+    }
+
+    /// Whether the button `action` drives is down on the controller.
+    fn is_output_down(&self, action: &str) -> bool {
+        let button = self.output_button(action);
+        self.virtual_controller
+            .as_ref()
+            .is_some_and(|controller| controller.pressed_buttons().iter().any(|pressed| *pressed == button))
+    }
+
+    fn on_button_pressed(&mut self, button_name: StringName) {
+        self.source_pressed(&button_name.to_string(), InputOrigin::Ui);
+    }
+
+    fn handle_button_box(&mut self, event: Gd<InputEventJoypadButton>) {
+        let index = event.get_button_index().ord() as i64;
+        let Some(button) = self.button_box_bindings.get(index).map(|button| button.to_string()) else {
+            return;
+        };
+
+        if event.is_pressed() {
+            if self.held_box_buttons.insert(index) {
+                self.source_pressed(&button, InputOrigin::ButtonBox);
+            }
+        } else if self.held_box_buttons.remove(&index) {
+            self.source_released(&button, InputOrigin::ButtonBox);
+        }
+
+        if let Some(mut viewport) = self.node().get_viewport() {
+            viewport.set_input_as_handled();
+        }
+    }
+
+    /// Input::joy_connection_changed. Unplugging the button box lets go of
+    /// everything it was holding.
+    fn on_joy_connection_changed(&mut self, device: i64, connected: bool) {
+        if device != self.button_box_device || self.button_box_bindings.is_empty() {
+            return;
+        }
+
+        if connected {
+            log_info!("Button box connected: {}", Input::singleton().get_joy_name(device as i32));
+        } else {
+            log_warn!("Button box disconnected");
+            let held: Vec<i64> = self.held_box_buttons.drain().collect();
+            for index in held {
+                if let Some(button) = self.button_box_bindings.get(index) {
+                    self.source_released(&button.to_string(), InputOrigin::ButtonBox);
+                }
+            }
+        }
+        self.emit("button_box_connection_changed", &[connected.to_variant()]);
+    }
+
+    /// A source at the controls (on-screen button, key, button box or
+    /// remote client) went down. The action is pressed by the first
+    /// source to hold it, after any hold or confirmation it needs.
+    fn source_pressed(&mut self, name: &str, origin: InputOrigin) -> ActionResult {
+        if self.level_select_mode && !self.applying_level && LEVELS.contains(&name) {
+            self.set_selected_level(name.into());
+            return ActionResult::Ok;
+        }
+
+        if !self.action_holders.hold(name, origin) {
+            return ActionResult::Ok;
+        }
 
-use godot::{classes::Button, prelude::*};
-use virtual_controller::VirtualController;
+        if self.confirm_actions.contains(&GString::from(name)) {
+            self.request_confirmation(name);
+            return ActionResult::Pending;
+        }
 
-struct FRCInterface;
+        if self.coach_confirm_actions.contains(&GString::from(name)) {
+            self.request_coach_confirmation(name, origin);
+            return ActionResult::Pending;
+        }
 
-#[gdextension]
-unsafe impl ExtensionLibrary for FRCInterface {}
+        if let Some(hold) = self.hold_duration(name) {
+            let now = Instant::now();
+            self.pending_holds.insert(
+                name.to_string(),
+                PendingHold {
+                    started: now,
+                    hold,
+                    last_progress: now,
+                },
+            );
+            self.emit("hold_progress", &[StringName::from(name).to_variant(), 0.0.to_variant()]);
+            return ActionResult::Pending;
+        }
 
-#[derive(GodotClass)]
-#[class(base=Node3D)]
-struct FRCInterfaceBase {
-    #[export]
-    connected: bool,
+        let result = self.press_action(name, origin);
+        if !result.going_out() {
+            self.action_holders.release(name, origin);
+        }
+        result
+    }
 
-    force_connected: bool,
-
-    #[export]
-    climb_button: Option<Gd<Button>>,
-    
-    #[export]
-    zero_button: Option<Gd<Button>>,
-    
-    #[export]
-    intake_button: Option<Gd<Button>>,
-    
-    #[export]
-    high_button: Option<Gd<Button>>,
-    
-    #[export]
-    mid_button: Option<Gd<Button>>,
-    
-    #[export]
-    low_button: Option<Gd<Button>>,
-
-    #[export]
-    coral_button: Option<Gd<Button>>,
-    
-    #[export]
-    intake_alga_button: Option<Gd<Button>>,
-    
-    #[export]
-    drop_alga_button: Option<Gd<Button>>,
-
-    virtual_controller: Option<VirtualController>,
-    
-    // TCP ping fields
-    last_ping_time: Instant,
-    ping_interval: Duration,
+    /// A source at the controls let go. The action is released once no
+    /// source holds it.
+    fn source_released(&mut self, name: &str, origin: InputOrigin) -> ActionResult {
+        // Releasing a level button keeps the selection
+        if self.level_select_mode && !self.applying_level && LEVELS.contains(&name) {
+            return ActionResult::Ok;
+        }
 
-    #[export]
-    ping_address: GString,
+        if !self.action_holders.release(name, origin) {
+            return ActionResult::Ok;
+        }
 
-    #[export]
-    ping_port: i64,
-    
-    // Add the base field
-    base: Base<Node3D>,
-}
+        if self.cancel_hold(name) {
+            return ActionResult::Ok;
+        }
+        if self.coach_confirm_actions.contains(&GString::from(name)) {
+            // Let go before the override, the coach can still confirm
+            if let Some(pending) = self.pending_coach.as_mut().filter(|pending| pending.action == name) {
+                pending.held_since = None;
+            }
+        }
 
-#[godot_api]
-impl INode3D for FRCInterfaceBase {
-    fn init(base: Base<Self::Base>) -> Self {
-        FRCInterfaceBase {
-            connected: false,
-            force_connected: false,
-            climb_button: None,
-            zero_button: None,
-            intake_button: None,
-            high_button: None,
-            mid_button: None,
-            low_button: None,
-            coral_button: None,
-            intake_alga_button: None,
-            drop_alga_button: None,
-            virtual_controller: None,
-            last_ping_time: Instant::now(),
-            ping_interval: Duration::from_secs(15),
-            ping_address: "10.45.33.2".into(),
-            ping_port: 22,
-            base,
+        // Confirmed presses are taps holding it themselves, so this only
+        // lets go once they've ended too
+        self.release_action(name, origin)
+    }
+
+    /// Time left before `name` can be pressed again, if it's cooling down.
+    fn cooldown_remaining(&self, name: &str) -> Option<Duration> {
+        let until = self.cooldown_until.get(name)?;
+        let remaining = until.saturating_duration_since(Instant::now());
+        (!remaining.is_zero()).then_some(remaining)
+    }
+
+    /// Let every action be pressed again straight away.
+    fn clear_cooldowns(&mut self) {
+        self.cooldown_until.clear();
+    }
+
+    /// How long `name` must be held before it's pressed, if it's hold-to-activate.
+    fn hold_duration(&self, name: &str) -> Option<Duration> {
+        let hold_ms = self.hold_to_activate.get(name)?;
+        variant_duration(&hold_ms, 0.001, "milliseconds").unwrap_or_else(|e| {
+            log_warn!("hold_to_activate for {} {}, pressing it straight away", name, e);
+            None
+        })
+    }
+
+    /// Hold back a press until the operator confirms it in the dialog.
+    fn request_confirmation(&mut self, name: &str) {
+        let Some(mut dialog) = self.confirmation_dialog() else {
+            return;
+        };
+
+        // A newer request replaces one still on screen
+        if let Some((previous, _)) = self.pending_confirmation.take() {
+            self.emit_confirmation_cancelled(&previous, "replaced");
         }
+
+        let timeout = Duration::from_secs_f64(self.confirm_timeout_secs.max(0.1));
+        self.pending_confirmation = Some((name.to_string(), Instant::now() + timeout));
+
+        dialog.set_text(&GString::from(format!("Send {}?", name)));
+        self.call_later(dialog.upcast(), "popup_centered", &[]);
+        self.emit("action_confirmation_requested", &[StringName::from(name).to_variant()]);
     }
 
-    fn ready(&mut self) {
-        // Connect button signals
-        self.connect_button_signals();
-        
-        // Initialize the virtual controller
-        let mut controller = VirtualController::new();
-        if controller.initialize() {
-            godot_print!("Virtual controller initialized");
-            self.virtual_controller = Some(controller);
+    /// Ask the remote clients to confirm `name`. A newer request replaces
+    /// one still waiting. Only a press at this station starts the override
+    /// hold, a remote client holding the button still needs the coach.
+    fn request_coach_confirmation(&mut self, name: &str, origin: InputOrigin) {
+        let held_since = (origin != InputOrigin::Remote).then(Instant::now);
+        if let Some(pending) = self.pending_coach.as_mut().filter(|pending| pending.action == name) {
+            // Pressed again while waiting: only restart the override hold
+            if held_since.is_some() {
+                pending.held_since = held_since;
+            }
+            return;
+        }
+        self.cancel_coach_confirmation("replaced");
+
+        let id = self.next_coach_request;
+        self.next_coach_request += 1;
+        let timeout = Duration::from_secs_f64(self.coach_confirm_timeout_secs.max(1.0));
+        self.pending_coach = Some(CoachConfirmation {
+            id,
+            action: name.to_string(),
+            deadline: Instant::now() + timeout,
+            held_since,
+        });
+
+        if self.remote_server.authenticated_clients() == 0 {
+            log_warn!("No remote client to confirm {}, hold it to send anyway", name);
         } else {
-            godot_error!("Failed to initialize virtual controller");
+            log_info!("Waiting on the coach to confirm {}", name);
         }
-        
-        // Perform initial ping
-        self.ping_tcp_server();
+        let request = json!({
+            "type": "confirm_request",
+            "id": id,
+            "action": name,
+            "timeout_secs": timeout.as_secs_f64(),
+        });
+        self.remote_server.broadcast(&request.to_string());
+        self.emit("action_confirmation_requested", &[StringName::from(name).to_variant()]);
     }
 
-    fn process(&mut self, _delta: f64) {
-        // Check if it's time to ping again
-        if self.last_ping_time.elapsed() >= self.ping_interval {
-            self.ping_tcp_server();
-            self.last_ping_time = Instant::now();
+    fn update_coach_confirmation(&mut self) {
+        let Some(pending) = &self.pending_coach else {
+            return;
+        };
+        let override_hold = Duration::from_millis(self.coach_override_hold_ms.max(0) as u64);
+        if pending.held_since.is_some_and(|since| since.elapsed() >= override_hold) {
+            log_warn!("{} sent without the coach, held past the override", pending.action);
+            self.confirm_coach("local_override");
+        } else if Instant::now() >= pending.deadline {
+            let Some(pending) = self.pending_coach.take() else {
+                return;
+            };
+            log_warn!("Coach didn't confirm {} in time, dropped", pending.action);
+            self.remote_server
+                .broadcast(&json!({ "type": "confirm_cancelled", "id": pending.id }).to_string());
+            self.emit("action_confirmation_timeout", &[StringName::from(pending.action.as_str()).to_variant()]);
         }
     }
-    
-    fn exit_tree(&mut self) {
-        // Shutdown the virtual controller
-        if let Some(mut controller) = self.virtual_controller.take() {
-            controller.shutdown();
-        }
-    }
-}
-
-#[godot_api]
-impl FRCInterfaceBase {
-    fn connect_button_signals(&mut self) {
-        // Helper to connect button signals
-        let connect_button = |button: &Option<Gd<Button>>, name: &str, base_obj: &Gd<Node3D>| {
-            if let Some(gd_btn) = button {
-                // Get a mutable reference by cloning and using bind_mut
-                let mut btn = gd_btn.clone();
-                let btn_mut = btn.deref_mut();
-                
-                // Create the StringName for the button name once
-                let button_name = StringName::from(name);
-                let name_variant = button_name.to_variant();
-                
-                // Connect button_down signal
-                let callable_pressed = Callable::from_object_method(base_obj, "on_button_pressed");
-                let bound_callable_pressed = callable_pressed.bind(&[name_variant.clone()]);
-                
-                let result = btn_mut.connect("button_down", &bound_callable_pressed);
-                if result != godot::global::Error::OK {
-                    godot_error!("Failed to connect button_down for {}: {:?}", name, result);
-                }
-                
-                // Connect button_up signal
-                let callable_released = Callable::from_object_method(base_obj, "on_button_released");
-                let bound_callable_released = callable_released.bind(&[name_variant]);
-                
-                let result = btn_mut.connect("button_up", &bound_callable_released);
-                if result != godot::global::Error::OK {
-                    godot_error!("Failed to connect button_up for {}: {:?}", name, result);
-                }
-            }
-        };
-        
-        // Get a reference to this node as a Gd<Node3D>
-        let base = self.base();
-        
-        // Connect all buttons
-        connect_button(&self.climb_button, "climb", &base);
-        connect_button(&self.zero_button, "zero", &base);
-        connect_button(&self.intake_button, "intake", &base);
-        connect_button(&self.high_button, "high", &base);
-        connect_button(&self.mid_button, "mid", &base);
-        connect_button(&self.low_button, "low", &base);
-        connect_button(&self.coral_button, "coral", &base);
-        connect_button(&self.intake_alga_button, "intake_alga", &base);
-        connect_button(&self.drop_alga_button, "drop_alga", &base);
-    }
-    
-    fn ping_tcp_server(&mut self) {
-        // Try to connect to the TCP server
-        if self.force_connected {
-            self.connected = true;
+
+    fn on_coach_reply(&mut self, client: u32, id: u64, accept: bool) {
+        let current = self.pending_coach.as_ref().is_some_and(|pending| pending.id == id);
+        let reply = json!({ "type": "result", "cmd": "confirm", "id": id, "ok": current });
+        self.remote_server.send_to(client, &reply.to_string());
+        if !current {
             return;
         }
 
-        match TcpStream::connect_timeout(
-            &format!("{}:{}", self.ping_address, self.ping_port)
-                .parse()
-                .unwrap_or_else(|_| {
-                    godot_error!("Invalid address format");
-                    std::net::SocketAddr::from(([127, 0, 0, 1], 22))
-                }),
-            Duration::from_secs(2),
-        ) {
-            Ok(_) => {
-                if !self.connected {
-                    godot_print!("TCP connection established with {}:{}", self.ping_address, self.ping_port);
-                    self.connected = true;
-                }
+        if accept {
+            self.confirm_coach("coach");
+        } else if let Some(pending) = self.pending_coach.take() {
+            log_info!("Coach turned down {}", pending.action);
+            self.remote_server
+                .broadcast(&json!({ "type": "confirm_cancelled", "id": pending.id }).to_string());
+            self.emit_confirmation_cancelled(&pending.action, "rejected");
+        }
+    }
+
+    /// Send the pending action as a tap. `by` is "coach" or "local_override".
+    fn confirm_coach(&mut self, by: &str) {
+        let Some(pending) = self.pending_coach.take() else {
+            return;
+        };
+        let resolved = json!({ "type": "confirm_resolved", "id": pending.id, "by": by });
+        self.remote_server.broadcast(&resolved.to_string());
+
+        let tap = Duration::from_millis(self.confirm_tap_ms.max(0) as u64);
+        self.start_tap(&pending.action, InputOrigin::Ui, tap);
+    }
+
+    /// Drop the press waiting on the coach, e.g. on neutralize.
+    fn cancel_coach_confirmation(&mut self, reason: &str) {
+        let Some(pending) = self.pending_coach.take() else {
+            return;
+        };
+        self.remote_server
+            .broadcast(&json!({ "type": "confirm_cancelled", "id": pending.id }).to_string());
+        self.emit_confirmation_cancelled(&pending.action, reason);
+    }
+
+    /// The exported dialog, or one made on first use. Its signals are
+    /// connected the first time it's seen.
+    fn confirmation_dialog(&mut self) -> Option<Gd<ConfirmationDialog>> {
+        let dialog = match self.confirm_dialog.clone() {
+            Some(dialog) if dialog.is_instance_valid() => dialog,
+            Some(_) => {
+                log_warn!("Confirmation dialog was freed, not sending the action");
+                return None;
             }
-            Err(e) => {
-                if self.connected {
-                    match e.kind() {
-                        ErrorKind::TimedOut => {
-                            godot_warn!("TCP connection timed out with {}:{}", self.ping_address, self.ping_port);
-                        }
-                        ErrorKind::ConnectionRefused => {
-                            godot_warn!("TCP connection refused by {}:{}", self.ping_address, self.ping_port);
-                        }
-                        _ => {
-                            godot_warn!("TCP connection error with {}:{}: {}", self.ping_address, self.ping_port, e);
-                        }
-                    }
-                    self.connected = false;
-                }
+            None => {
+                let mut dialog = ConfirmationDialog::new_alloc();
+                dialog.set_title("Confirm action");
+                self.add_child(dialog.clone().upcast());
+                self.confirm_dialog = Some(dialog.clone());
+                dialog
+            }
+        };
+
+        let this = self.node();
+        for (signal, method) in [("confirmed", "on_confirmation_confirmed"), ("canceled", "on_confirmation_canceled")] {
+            let callable = Callable::from_object_method(&this, method);
+            if !dialog.is_connected(signal, &callable) {
+                dialog.clone().connect(signal, &callable);
             }
         }
+
+        Some(dialog)
     }
-    
-    #[func]
-    fn on_button_pressed(&mut self, button_name: StringName) {
-        if !self.connected {
-            godot_warn!("Not connected, cannot send button press");
+
+    fn on_confirmation_confirmed(&mut self) {
+        let Some((name, deadline)) = self.pending_confirmation.take() else {
+            return;
+        };
+        if Instant::now() >= deadline {
+            self.emit_confirmation_cancelled(&name, "timeout");
             return;
         }
-        
-        if let Some(controller) = &self.virtual_controller {
-            controller.set_button(&button_name.to_string(), true);
+
+        // Sent as a tap so nothing stays latched behind the dialog
+        let tap = Duration::from_millis(self.confirm_tap_ms.max(0) as u64);
+        self.start_tap(&name, InputOrigin::Ui, tap);
+    }
+
+    fn on_confirmation_canceled(&mut self) {
+        if let Some((name, _)) = self.pending_confirmation.take() {
+            self.emit_confirmation_cancelled(&name, "cancelled");
         }
     }
-    
-    #[func]
-    fn on_button_released(&mut self, button_name: StringName) {
-        if !self.connected {
+
+    /// Drop a confirmation still on screen, e.g. on timeout or neutralize.
+    fn cancel_confirmation(&mut self, reason: &str) {
+        let Some((name, _)) = self.pending_confirmation.take() else {
             return;
+        };
+
+        if let Some(dialog) = self.confirm_dialog.as_mut() {
+            if dialog.is_instance_valid() {
+                dialog.hide();
+            }
         }
-        
-        if let Some(controller) = &self.virtual_controller {
-            controller.set_button(&button_name.to_string(), false);
+        self.emit_confirmation_cancelled(&name, reason);
+    }
+
+    fn emit_confirmation_cancelled(&mut self, name: &str, reason: &str) {
+        let args = [StringName::from(name).to_variant(), StringName::from(reason).to_variant()];
+        self.emit("action_confirmation_cancelled", &args);
+    }
+
+    /// Report progress on held buttons and press the ones held long enough.
+    fn update_pending_holds(&mut self) {
+        let now = Instant::now();
+        let mut progress = Vec::new();
+        let mut finished = Vec::new();
+
+        for (name, hold) in self.pending_holds.iter_mut() {
+            let held = now.duration_since(hold.started);
+            if held >= hold.hold {
+                finished.push(name.clone());
+            } else if now.duration_since(hold.last_progress) >= HOLD_PROGRESS_INTERVAL {
+                hold.last_progress = now;
+                progress.push((name.clone(), held.as_secs_f64() / hold.hold.as_secs_f64()));
+            }
+        }
+
+        for (name, fraction) in progress {
+            let args = [StringName::from(name.as_str()).to_variant(), fraction.to_variant()];
+            self.emit("hold_progress", &args);
+        }
+
+        for name in finished {
+            self.pending_holds.remove(&name);
+            let args = [StringName::from(name.as_str()).to_variant(), 1.0.to_variant()];
+            self.emit("hold_progress", &args);
+            self.press_action(&name, InputOrigin::Ui);
+        }
+    }
+
+    /// Drop a hold that hasn't fired yet. Returns whether there was one.
+    fn cancel_hold(&mut self, name: &str) -> bool {
+        if self.pending_holds.remove(name).is_none() {
+            return false;
+        }
+
+        self.emit("hold_cancelled", &[StringName::from(name).to_variant()]);
+        true
+    }
+
+    /// Touch mode press on a bound button, one hold per finger. Releases are
+    /// picked up in input() so a finger that slides off still lets go.
+    fn on_button_gui_input(&mut self, event: Gd<InputEvent>, button_name: StringName, button: Gd<BaseButton>) {
+        if button.is_disabled() {
+            return;
+        }
+
+        let pointer = if let Ok(touch) = event.clone().try_cast::<InputEventScreenTouch>() {
+            if !touch.is_pressed() {
+                return;
+            }
+            touch.get_index()
+        } else if let Ok(click) = event.try_cast::<InputEventMouseButton>() {
+            // Touches also arrive as emulated clicks, only real mice count
+            if !click.is_pressed() || click.get_device() == EMULATED_MOUSE_DEVICE || click.get_button_index() != MouseButton::LEFT {
+                return;
+            }
+            MOUSE_POINTER
+        } else {
+            return;
+        };
+
+        if self.touch_pointers.contains_key(&pointer) {
+            return;
+        }
+
+        let name = button_name.to_string();
+        self.touch_pointers.insert(pointer, name.clone());
+        self.source_pressed(&name, InputOrigin::Ui);
+    }
+
+    /// ui_accept on a focused press/release button holds its action until
+    /// ui_accept is released. Taken before the GUI so the button doesn't
+    /// also emit its own press. Returns whether the event was used.
+    fn handle_focus_accept(&mut self, event: &Gd<InputEvent>) -> bool {
+        if !event.is_action("ui_accept") || event.is_echo() {
+            return false;
+        }
+
+        if !event.is_pressed() {
+            let Some(name) = self.focus_held.take() else {
+                return false;
+            };
+            self.source_released(&name, InputOrigin::Ui);
+            return true;
+        }
+
+        if self.focus_held.is_some() {
+            return true;
+        }
+        let Some(focused) = self.node().get_viewport().and_then(|viewport| viewport.gui_get_focus_owner()) else {
+            return false;
+        };
+
+        let focused_id = focused.instance_id();
+        let Some(name) = self
+            .action_bindings
+            .iter()
+            .find(|binding| {
+                binding.node.is_instance_valid()
+                    && binding.node.instance_id() == focused_id
+                    && binding.connections.iter().all(|(signal, _)| *signal != "toggled")
+            })
+            .map(|binding| binding.action.clone())
+        else {
+            return false;
+        };
+        if focused.clone().try_cast::<BaseButton>().is_ok_and(|button| button.is_disabled()) {
+            return false;
+        }
+
+        // Shares the refcount with touch and mouse, so both at once is one press
+        self.focus_held = Some(name.clone());
+        self.source_pressed(&name, InputOrigin::Ui);
+        true
+    }
+
+    /// Toggle buttons act as latched actions, held while toggled on.
+    fn on_button_toggled(&mut self, toggled_on: bool, button_name: StringName) {
+        if toggled_on {
+            self.on_button_pressed(button_name);
+        } else {
+            self.on_button_released(button_name);
         }
     }
 
-    #[func]
+    fn on_button_released(&mut self, button_name: StringName) {
+        self.source_released(&button_name.to_string(), InputOrigin::Ui);
+    }
+
+    /// Switch between Normal and ForceConnected.
     fn toggle_force_connected(&mut self) {
-        self.force_connected = !self.force_connected;
-        if self.force_connected {
-            self.connected = true;
+        self.set_override_mode(if self.override_mode == OverrideMode::ForceConnected {
+            OverrideMode::Normal
         } else {
-            self.ping_tcp_server();
+            OverrideMode::ForceConnected
+        });
+    }
+
+    fn set_override_mode(&mut self, mode: OverrideMode) {
+        self.override_mode = mode;
+        self.arm_override_expiry();
+
+        if Self::in_editor() || !self.running {
+            return;
+        }
+        if mode != OverrideMode::Normal {
+            log_warn!("Connection override active: {:?}", mode);
         }
+
+        // A running simulation wins, the override applies once it ends
+        if self.simulating_connection_loss {
+            return;
+        }
+
+        // Report the real state as soon as possible when going back to normal
+        self.ping_tcp_server();
+        self.last_ping_time = Instant::now();
+    }
+
+    fn arm_override_expiry(&mut self) {
+        let expires = self.override_mode == OverrideMode::ForceConnected && self.force_connected_expiry_secs > 0.0;
+        self.override_expires =
+            expires.then(|| Instant::now() + Duration::from_secs_f64(self.force_connected_expiry_secs));
+    }
+
+    /// Pretend the link dropped for `duration_secs` (until restored if <= 0
+    /// or too long to time), going through the same path as a real failed
+    /// ping. Real probing is suspended until the simulation ends; this takes
+    /// precedence over override_mode.
+    fn simulate_connection_loss(&mut self, duration_secs: f64) {
+        log_warn!("SIMULATED connection loss started");
+        self.simulating_connection_loss = true;
+        self.simulation_end = if duration_secs > 0.0 {
+            Duration::try_from_secs_f64(duration_secs)
+                .ok()
+                .and_then(|duration| Instant::now().checked_add(duration))
+        } else {
+            None
+        };
+
+        self.set_ping_error(ConnectionErrorKind::Other, "Simulated connection loss".into());
+    }
+
+    /// End a simulated connection loss and go back to real probing.
+    fn simulate_connection_restore(&mut self) {
+        if !self.simulating_connection_loss {
+            return;
+        }
+
+        log_warn!("SIMULATED connection loss ended");
+        self.simulating_connection_loss = false;
+        self.simulation_end = None;
+
+        // Report the real state (or the override) as soon as possible
+        self.ping_tcp_server();
+        self.last_ping_time = Instant::now();
+    }
+}
+
+/// What the core can't do itself while the node is busy with a call: signal
+/// handlers and tree notifications call back into the node, so these wait for
+/// the call to return.
+enum HostOp {
+    Emit(StringName, Vec<Variant>),
+    AddChild(Gd<Node>),
+    Call(Gd<Object>, StringName, Vec<Variant>),
+}
+
+/// A property of the interface node classes. The value lives in InterfaceCore
+/// and is only reached through the getter and setter the class registers, so
+/// this holds nothing.
+struct CoreProperty<T>(PhantomData<T>);
+
+impl<T> Default for CoreProperty<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Var> GodotConvert for CoreProperty<T> {
+    type Via = T::Via;
+}
+
+// Godot only reads and writes the property through the registered getter and
+// setter. If anything ever went through the field, it would see the type's
+// default and log rather than take the node down
+impl<T: Var> Var for CoreProperty<T>
+where
+    T::Via: Default,
+{
+    fn get_property(&self) -> Self::Via {
+        log_error!("Interface property read without its getter");
+        Self::Via::default()
+    }
+
+    fn set_property(&mut self, _value: Self::Via) {
+        log_error!("Interface property written without its setter, ignored");
     }
-}
\ No newline at end of file
+
+    fn var_hint() -> PropertyHintInfo {
+        T::var_hint()
+    }
+}
+
+impl<T: Export> Export for CoreProperty<T>
+where
+    T::Via: Default,
+{
+    fn export_hint() -> PropertyHintInfo {
+        T::export_hint()
+    }
+}
+
+/// Defines an interface node class on `$base`. FRCInterfaceBase and
+/// FRCInterface2D both come from here, so they have the same exports, signals
+/// and functions and only differ in what they extend. The class only declares
+/// them, everything it does is in InterfaceCore.
+macro_rules! interface_class {
+    (
+        @class $(#[$attr:meta])* $name:ident: $base:ident, $virtual:ident, $notification:ident;
+        properties {
+            $($(#[$($prop_attr:tt)*])? $prop:ident: $prop_ty:ty
+                => $get:ident$(($get_core:ident))? $(, $set:ident$(($set_core:ident))?)?;)*
+        }
+        signals { $($(#[$signal_attr:meta])* $signal:ident($($signal_arg:ident: $signal_ty:ty),*);)* }
+        funcs { $($func:ident($($arg:ident: $arg_ty:ty),*) $(-> $ret:ty)?;)* }
+        const_funcs { $($const_func:ident($($const_arg:ident: $const_arg_ty:ty),*) $(-> $const_ret:ty)?;)* }
+    ) => {
+        $(#[$attr])*
+        #[derive(GodotClass)]
+        // Tool mode only for configuration warnings, nothing runs in the editor
+        #[class(tool, init, base=$base)]
+        struct $name {
+            $(
+                $(#[$($prop_attr)*])?
+                #[var(get = $get $(, set = $set)?)]
+                $prop: CoreProperty<$prop_ty>,
+            )*
+
+            core: InterfaceCore,
+            base: Base<$base>,
+        }
+
+        #[godot_api]
+        impl $virtual for $name {
+            fn ready(&mut self) {
+                self.with_core(|core| core.ready());
+            }
+
+            fn process(&mut self, _delta: f64) {
+                self.with_core(|core| core.process());
+            }
+
+            fn on_notification(&mut self, what: $notification) {
+                self.with_core(|core| core.on_notification(NodeNotification::from(i32::from(what))));
+            }
+
+            fn input(&mut self, event: Gd<InputEvent>) {
+                self.with_core(|core| core.input(event));
+            }
+
+            fn unhandled_input(&mut self, event: Gd<InputEvent>) {
+                self.with_core(|core| core.unhandled_input(event));
+            }
+
+            fn get_configuration_warnings(&self) -> PackedStringArray {
+                self.core().configuration_warnings()
+            }
+
+            fn set_property(&mut self, _property: StringName, _value: Variant) -> bool {
+                // Recheck once the inspector's change has been applied, the value
+                // itself is stored by the normal property setter
+                if InterfaceCore::in_editor() {
+                    self.base_mut().call_deferred("update_configuration_warnings", &[]);
+                }
+                false
+            }
+
+            fn exit_tree(&mut self) {
+                self.with_core(|core| core.exit_tree());
+            }
+        }
+
+        #[godot_api]
+        impl $name {
+            $(
+                $(#[$signal_attr])*
+                #[signal]
+                fn $signal($($signal_arg: $signal_ty),*);
+            )*
+
+            $(
+                // Most properties are Copy, the rest are cheap to clone
+                #[allow(clippy::clone_on_copy)]
+                #[func]
+                fn $get(&self) -> $prop_ty {
+                    interface_class!(@get self, $prop, $get $(, $get_core)?)
+                }
+
+                $(
+                    #[func]
+                    fn $set(&mut self, value: $prop_ty) {
+                        interface_class!(@set self, $prop, $set, value $(, $set_core)?)
+                    }
+                )?
+            )*
+
+            $(
+                #[func]
+                fn $func(&mut self, $($arg: $arg_ty),*) $(-> $ret)? {
+                    self.with_core(|core| core.$func($($arg),*))
+                }
+            )*
+
+            $(
+                #[func]
+                fn $const_func(&self, $($const_arg: $const_arg_ty),*) $(-> $const_ret)? {
+                    self.core().$const_func($($const_arg),*)
+                }
+            )*
+        }
+
+        impl $name {
+            /// The core, told which node it belongs to on first use.
+            fn core(&self) -> &InterfaceCore {
+                self.core.owner.get_or_init(|| self.to_gd().upcast());
+                &self.core
+            }
+
+            /// Run `f` on the core, then emit the signals and make the tree
+            /// changes it left for the node.
+            fn with_core<R>(&mut self, f: impl FnOnce(&mut InterfaceCore) -> R) -> R {
+                self.core();
+                let result = f(&mut self.core);
+                self.run_host_ops();
+
+                // Presses held for the press filter. It runs with the node
+                // free, so it can read and call it
+                while let Some((check, filter)) = self.core.next_filter_check() {
+                    let answer = {
+                        let _guard = self.base_mut();
+                        check.ask(filter.as_ref())
+                    };
+                    self.core.press_filter_answered(check, answer);
+                    self.run_host_ops();
+                }
+                result
+            }
+
+            fn run_host_ops(&mut self) {
+                for op in std::mem::take(&mut self.core.host_ops) {
+                    match op {
+                        HostOp::Emit(signal, args) => {
+                            self.base_mut().emit_signal(&signal, &args);
+                        }
+                        HostOp::AddChild(child) => {
+                            self.base_mut().add_child(&child);
+                        }
+                        HostOp::Call(mut object, method, args) => {
+                            let _guard = self.base_mut();
+                            object.call(&method, &args);
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    // Property accessors: plain ones read and write the core's field, ones
+    // marked (core) go through the core's function of the same name
+    (@get $this:ident, $prop:ident, $get:ident) => {
+        $this.core.$prop.clone()
+    };
+    (@get $this:ident, $prop:ident, $get:ident, core) => {
+        $this.core().$get()
+    };
+    (@set $this:ident, $prop:ident, $set:ident, $value:ident) => {
+        $this.core.$prop = $value
+    };
+    (@set $this:ident, $prop:ident, $set:ident, $value:ident, core) => {
+        $this.with_core(|core| core.$set($value))
+    };
+
+    ($(#[$attr:meta])* $name:ident: $base:ident, $virtual:ident, $notification:ident) => {
+        interface_class! {
+            @class $(#[$attr])* $name: $base, $virtual, $notification;
+            properties {
+                #[export]
+                config: Option<Gd<FRCInterfaceConfig>> => get_config, set_config;
+                #[export]
+                auto_load_on_ready: bool => get_auto_load_on_ready, set_auto_load_on_ready;
+                #[export]
+                auto_start: bool => get_auto_start, set_auto_start;
+                running: bool => get_running;
+                #[export]
+                log_level: LogLevel => get_log_level(core), set_log_level(core);
+                #[export]
+                connected: bool => get_connected, set_connected;
+                #[export]
+                override_mode: OverrideMode => get_override_mode, set_override_mode(core);
+                #[export(range = (0.0, 3600.0))]
+                force_connected_expiry_secs: f64 => get_force_connected_expiry_secs, set_force_connected_expiry_secs;
+                simulating_connection_loss: bool => get_simulating_connection_loss;
+                time_in_current_state: f64 => get_time_in_current_state(core);
+                total_downtime: f64 => get_total_downtime(core);
+                #[export]
+                climb_button: Option<Gd<Node>> => get_climb_button, set_climb_button;
+                #[export]
+                zero_button: Option<Gd<Node>> => get_zero_button, set_zero_button;
+                #[export]
+                intake_button: Option<Gd<Node>> => get_intake_button, set_intake_button;
+                #[export]
+                high_button: Option<Gd<Node>> => get_high_button, set_high_button;
+                #[export]
+                mid_button: Option<Gd<Node>> => get_mid_button, set_mid_button;
+                #[export]
+                low_button: Option<Gd<Node>> => get_low_button, set_low_button;
+                #[export]
+                coral_button: Option<Gd<Node>> => get_coral_button, set_coral_button;
+                #[export]
+                intake_alga_button: Option<Gd<Node>> => get_intake_alga_button, set_intake_alga_button;
+                #[export]
+                drop_alga_button: Option<Gd<Node>> => get_drop_alga_button, set_drop_alga_button;
+                #[export]
+                button_bindings: Array<Dictionary> => get_button_bindings, set_button_bindings(core);
+                #[export]
+                axis_bindings: Array<Dictionary> => get_axis_bindings, set_axis_bindings;
+                #[export]
+                hold_axes_while_disconnected: bool
+                    => get_hold_axes_while_disconnected, set_hold_axes_while_disconnected;
+                #[export]
+                controller_max_restarts: i64 => get_controller_max_restarts, set_controller_max_restarts;
+                #[export(range = (0.0, 60.0))]
+                match_auto_secs: f64 => get_match_auto_secs, set_match_auto_secs;
+                #[export(range = (0.0, 300.0))]
+                match_teleop_secs: f64 => get_match_teleop_secs, set_match_teleop_secs;
+                #[export(range = (0.0, 135.0))]
+                match_endgame_secs: f64 => get_match_endgame_secs, set_match_endgame_secs;
+                #[export]
+                match_sync_to_robot_mode: bool => get_match_sync_to_robot_mode, set_match_sync_to_robot_mode;
+                robot_mode: GString => get_robot_mode;
+                #[export]
+                lockout_during_auto: bool => get_lockout_during_auto, set_lockout_during_auto;
+                #[export]
+                auto_lockout_when_mode_unknown: bool
+                    => get_auto_lockout_when_mode_unknown, set_auto_lockout_when_mode_unknown;
+                #[export]
+                endgame_only_actions: PackedStringArray => get_endgame_only_actions, set_endgame_only_actions;
+                #[export]
+                lock_endgame_actions_without_match: bool
+                    => get_lock_endgame_actions_without_match, set_lock_endgame_actions_without_match;
+                match_phase: MatchPhase => get_match_phase;
+                match_time_remaining: f64 => get_match_time_remaining;
+                #[export]
+                endgame_warning_secs: PackedFloat64Array => get_endgame_warning_secs, set_endgame_warning_secs;
+                #[export]
+                endgame_warning_flash: Option<Gd<CanvasItem>> => get_endgame_warning_flash, set_endgame_warning_flash;
+                #[export(range = (0.1, 10.0))]
+                endgame_warning_flash_secs: f64 => get_endgame_warning_flash_secs, set_endgame_warning_flash_secs;
+                #[export]
+                endgame_warning_sound: Option<Gd<AudioStream>> => get_endgame_warning_sound, set_endgame_warning_sound;
+                #[export]
+                write_match_reports: bool => get_write_match_reports, set_write_match_reports;
+                #[export]
+                alert_window_title: bool => get_alert_window_title, set_alert_window_title;
+                #[export]
+                alert_title_suffix: GString => get_alert_title_suffix, set_alert_title_suffix;
+                #[export]
+                alert_request_attention: bool => get_alert_request_attention, set_alert_request_attention;
+                #[export]
+                alert_sound: Option<Gd<AudioStream>> => get_alert_sound, set_alert_sound;
+                #[export]
+                press_sound: Option<Gd<AudioStream>> => get_press_sound, set_press_sound;
+                #[export]
+                release_sound: Option<Gd<AudioStream>> => get_release_sound, set_release_sound;
+                #[export]
+                denied_sound: Option<Gd<AudioStream>> => get_denied_sound, set_denied_sound;
+                #[export]
+                action_press_sounds: Dictionary => get_action_press_sounds, set_action_press_sounds;
+                #[export]
+                sound_overlap: SoundOverlap => get_sound_overlap, set_sound_overlap;
+                #[export]
+                status_indicator: Option<Gd<CanvasItem>> => get_status_indicator, set_status_indicator;
+                #[export]
+                status_label: Option<Gd<Label>> => get_status_label, set_status_label;
+                #[export]
+                last_action_label: Option<Gd<Label>> => get_last_action_label, set_last_action_label;
+                #[export]
+                last_action_time_label: Option<Gd<Label>> => get_last_action_time_label, set_last_action_time_label;
+                #[export]
+                last_action_format: GString => get_last_action_format, set_last_action_format;
+                #[export]
+                last_action_time_format: GString => get_last_action_time_format, set_last_action_time_format;
+                #[export]
+                text_no_action: GString => get_text_no_action, set_text_no_action;
+                #[export(range = (0.0, 60.0))]
+                button_held_rate_hz: f64 => get_button_held_rate_hz, set_button_held_rate_hz;
+                #[export]
+                color_connected: Color => get_color_connected, set_color_connected;
+                #[export]
+                color_degraded: Color => get_color_degraded, set_color_degraded;
+                #[export]
+                color_disconnected: Color => get_color_disconnected, set_color_disconnected;
+                #[export]
+                color_forced: Color => get_color_forced, set_color_forced;
+                #[export]
+                text_connected: GString => get_text_connected, set_text_connected;
+                #[export]
+                text_degraded: GString => get_text_degraded, set_text_degraded;
+                #[export]
+                text_disconnected: GString => get_text_disconnected, set_text_disconnected;
+                #[export]
+                text_forced: GString => get_text_forced, set_text_forced;
+                #[export]
+                text_forced_disconnected: GString => get_text_forced_disconnected, set_text_forced_disconnected;
+                #[export]
+                text_dry_run: GString => get_text_dry_run, set_text_dry_run;
+                #[export]
+                output_mode: OutputMode => get_output_mode, set_output_mode;
+                #[export]
+                dry_run: bool => get_dry_run, set_dry_run(core);
+                #[export]
+                halsim_address: GString => get_halsim_address, set_halsim_address;
+                #[export]
+                halsim_port: i64 => get_halsim_port, set_halsim_port;
+                #[export(range = (0.0, 5.0))]
+                halsim_joystick: i64 => get_halsim_joystick, set_halsim_joystick;
+                #[export(range = (1.0, 20.0))]
+                failures_before_disconnect: i64 => get_failures_before_disconnect, set_failures_before_disconnect(core);
+                #[export(range = (1.0, 20.0))]
+                successes_before_connect: i64 => get_successes_before_connect, set_successes_before_connect(core);
+                #[export]
+                pause_when_hidden: bool => get_pause_when_hidden, set_pause_when_hidden;
+                probing_paused: bool => get_probing_paused;
+                #[export]
+                persistent_connection: bool => get_persistent_connection, set_persistent_connection(core);
+                #[export]
+                ping_address: GString => get_ping_address, set_ping_address(core);
+                #[export]
+                ping_port: i64 => get_ping_port, set_ping_port(core);
+                #[export]
+                endpoint: GString => get_endpoint, set_endpoint(core);
+                #[export(range = (100.0, 10000.0))]
+                ping_timeout_ms: i64 => get_ping_timeout_ms, set_ping_timeout_ms(core);
+                #[export]
+                verification_mode: VerificationMode => get_verification_mode, set_verification_mode;
+                #[export]
+                expected_banner: GString => get_expected_banner, set_expected_banner;
+                verified: bool => get_verified;
+                #[export]
+                local_bind_address: GString => get_local_bind_address, set_local_bind_address(core);
+                local_address: GString => get_local_address;
+                last_error: GString => get_last_error;
+                last_error_kind: ConnectionErrorKind => get_last_error_kind;
+                #[export]
+                time_sync_enabled: bool => get_time_sync_enabled, set_time_sync_enabled;
+                #[export]
+                apply_clock_offset_to_logs: bool => get_apply_clock_offset_to_logs, set_apply_clock_offset_to_logs;
+                robot_clock_offset_ms: f64 => get_robot_clock_offset_ms(core);
+                robot_clock_offset_known: bool => get_robot_clock_offset_known(core);
+                #[export]
+                team_number: i64 => get_team_number, set_team_number(core);
+                #[export]
+                event_code: GString => get_event_code, set_event_code;
+                #[export]
+                match_label: GString => get_match_label, set_match_label(core);
+                #[export]
+                radio_address: GString => get_radio_address, set_radio_address;
+                network_diagnosis: NetworkDiagnosis => get_network_diagnosis;
+                #[export]
+                lock_inputs_on_disconnect: bool => get_lock_inputs_on_disconnect, set_lock_inputs_on_disconnect;
+                inputs_locked: bool => get_inputs_locked;
+                #[export]
+                lock_inputs_on_focus_loss: bool => get_lock_inputs_on_focus_loss, set_lock_inputs_on_focus_loss;
+                #[export]
+                disable_buttons_when_disconnected: bool
+                    => get_disable_buttons_when_disconnected, set_disable_buttons_when_disconnected(core);
+                #[export]
+                action_sets: Dictionary => get_action_sets, set_action_sets;
+                #[export]
+                phase_action_sets: Dictionary => get_phase_action_sets, set_phase_action_sets;
+                #[export]
+                gray_out_disabled_actions: bool => get_gray_out_disabled_actions, set_gray_out_disabled_actions;
+                #[export]
+                services: Dictionary => get_services, set_services(core);
+                #[export]
+                devices: Array<Dictionary> => get_devices, set_devices(core);
+                devices_ok: bool => get_devices_ok(core);
+                #[export(range = (1.0, 300.0))]
+                error_report_interval_secs: f64 => get_error_report_interval_secs, set_error_report_interval_secs;
+                #[export]
+                discovery_ports: PackedInt32Array => get_discovery_ports, set_discovery_ports;
+                #[export]
+                discovery_auto_adopt: bool => get_discovery_auto_adopt, set_discovery_auto_adopt;
+                #[export]
+                discovery_attempts_per_sec: i64 => get_discovery_attempts_per_sec, set_discovery_attempts_per_sec;
+                #[export]
+                discovery_concurrency: i64 => get_discovery_concurrency, set_discovery_concurrency;
+                #[export]
+                connection_log_enabled: bool => get_connection_log_enabled, set_connection_log_enabled;
+                #[export]
+                connection_log_verbose: bool => get_connection_log_verbose, set_connection_log_verbose;
+                #[export]
+                status_server_enabled: bool => get_status_server_enabled, set_status_server_enabled;
+                #[export]
+                status_server_port: i64 => get_status_server_port, set_status_server_port;
+                #[export]
+                remote_server_enabled: bool => get_remote_server_enabled, set_remote_server_enabled;
+                #[export]
+                remote_server_port: i64 => get_remote_server_port, set_remote_server_port;
+                #[export]
+                remote_auth_token: GString => get_remote_auth_token, set_remote_auth_token;
+                #[export]
+                authority_enabled: bool => get_authority_enabled, set_authority_enabled;
+                #[export]
+                authority_peer_url: GString => get_authority_peer_url, set_authority_peer_url;
+                #[export]
+                authority_priority: i64 => get_authority_priority, set_authority_priority;
+                #[export]
+                authority_start_active: bool => get_authority_start_active, set_authority_start_active;
+                #[export]
+                authority_failover: bool => get_authority_failover, set_authority_failover;
+                #[export(range = (0.5, 30.0))]
+                authority_timeout_secs: f64 => get_authority_timeout_secs, set_authority_timeout_secs;
+                #[export]
+                standby_banner: Option<Gd<CanvasItem>> => get_standby_banner, set_standby_banner;
+                authority_active: bool => get_authority_active;
+                #[export]
+                ack_enabled: bool => get_ack_enabled, set_ack_enabled;
+                #[export]
+                ack_topics: Dictionary => get_ack_topics, set_ack_topics;
+                #[export(range = (50.0, 10000.0))]
+                ack_timeout_ms: i64 => get_ack_timeout_ms, set_ack_timeout_ms(core);
+                #[export]
+                ack_seq_topic_prefix: GString => get_ack_seq_topic_prefix, set_ack_seq_topic_prefix;
+                #[export]
+                ack_error_color: Color => get_ack_error_color, set_ack_error_color;
+                #[export]
+                alliance: GString => get_alliance, set_alliance(core);
+                #[export]
+                alliance_topic: GString => get_alliance_topic, set_alliance_topic;
+                selected_face: GString => get_selected_face, set_face(core);
+                #[export]
+                face_topic: GString => get_face_topic, set_face_topic;
+                #[export]
+                face_button_bits: Dictionary => get_face_button_bits, set_face_button_bits;
+                #[export]
+                hp_signal_cooldown_secs: f64 => get_hp_signal_cooldown_secs, set_hp_signal_cooldown_secs;
+                #[export]
+                hp_signal_button: GString => get_hp_signal_button, set_hp_signal_button;
+                #[export]
+                hp_signal_tap_ms: i64 => get_hp_signal_tap_ms, set_hp_signal_tap_ms;
+                #[export]
+                hp_signal_topic: GString => get_hp_signal_topic, set_hp_signal_topic;
+                #[export]
+                mode: GString => get_mode, set_mode(core);
+                #[export]
+                mode_mappings: Dictionary => get_mode_mappings, set_mode_mappings(core);
+                #[export]
+                mode_topic: GString => get_mode_topic, set_mode_topic;
+                #[export]
+                inverse_actions: Dictionary => get_inverse_actions, set_inverse_actions;
+                #[export(range = (1.0, 5000.0))]
+                undo_tap_ms: i64 => get_undo_tap_ms, set_undo_tap_ms;
+                #[export]
+                reset_counts_on_match_start: bool => get_reset_counts_on_match_start, set_reset_counts_on_match_start;
+                #[export]
+                cycle_pairs: VariantArray => get_cycle_pairs, set_cycle_pairs;
+                #[export]
+                hold_to_activate: Dictionary => get_hold_to_activate, set_hold_to_activate;
+                #[export]
+                cooldown: Dictionary => get_cooldown, set_cooldown(core);
+                #[export]
+                confirm_actions: PackedStringArray => get_confirm_actions, set_confirm_actions;
+                #[export]
+                confirm_dialog: Option<Gd<ConfirmationDialog>> => get_confirm_dialog, set_confirm_dialog;
+                #[export(range = (1.0, 5000.0))]
+                confirm_tap_ms: i64 => get_confirm_tap_ms, set_confirm_tap_ms;
+                #[export(range = (1.0, 120.0))]
+                confirm_timeout_secs: f64 => get_confirm_timeout_secs, set_confirm_timeout_secs;
+                #[export]
+                coach_confirm_actions: PackedStringArray => get_coach_confirm_actions, set_coach_confirm_actions;
+                #[export(range = (1.0, 120.0))]
+                coach_confirm_timeout_secs: f64 => get_coach_confirm_timeout_secs, set_coach_confirm_timeout_secs;
+                #[export(range = (100.0, 10000.0))]
+                coach_override_hold_ms: i64 => get_coach_override_hold_ms, set_coach_override_hold_ms;
+                #[export]
+                haptics_on_press: bool => get_haptics_on_press, set_haptics_on_press;
+                #[export(range = (1.0, 500.0))]
+                haptics_press_ms: i64 => get_haptics_press_ms, set_haptics_press_ms;
+                #[export]
+                haptics_on_ack: bool => get_haptics_on_ack, set_haptics_on_ack;
+                #[export(range = (1.0, 500.0))]
+                haptics_ack_ms: i64 => get_haptics_ack_ms, set_haptics_ack_ms;
+                #[export]
+                haptics_on_reject: bool => get_haptics_on_reject, set_haptics_on_reject;
+                #[export(range = (1.0, 500.0))]
+                haptics_reject_ms: i64 => get_haptics_reject_ms, set_haptics_reject_ms;
+                #[export]
+                input_action_bindings: Dictionary => get_input_action_bindings, set_input_action_bindings;
+                #[export]
+                button_box_device: i64 => get_button_box_device, set_button_box_device;
+                #[export]
+                button_box_bindings: Dictionary => get_button_box_bindings, set_button_box_bindings;
+                #[export]
+                touch_binding_mode: bool => get_touch_binding_mode, set_touch_binding_mode;
+                #[export]
+                focus_activation: bool => get_focus_activation, set_focus_activation;
+                #[export]
+                level_select_mode: bool => get_level_select_mode, set_level_select_mode;
+                selected_level: GString => get_selected_level, set_selected_level(core);
+                #[export(range = (0.0, 5000.0))]
+                score_settle_ms: i64 => get_score_settle_ms, set_score_settle_ms;
+                #[export(range = (1.0, 5000.0))]
+                score_tap_ms: i64 => get_score_tap_ms, set_score_tap_ms;
+                #[export]
+                score_clears_level: bool => get_score_clears_level, set_score_clears_level;
+                #[export]
+                sequence_input_policy: SequenceInputPolicy => get_sequence_input_policy, set_sequence_input_policy;
+                #[export]
+                auto_zero_after_climb: bool => get_auto_zero_after_climb, set_auto_zero_after_climb;
+                #[export(range = (0.0, 10000.0))]
+                auto_zero_delay_ms: i64 => get_auto_zero_delay_ms, set_auto_zero_delay_ms;
+                #[export(range = (1.0, 5000.0))]
+                auto_zero_tap_ms: i64 => get_auto_zero_tap_ms, set_auto_zero_tap_ms;
+                #[export(range = (1.0, 1000.0))]
+                prematch_max_latency_ms: f64 => get_prematch_max_latency_ms, set_prematch_max_latency_ms;
+                #[export(range = (-1.0, 3.0))]
+                expected_user_index: i64 => get_expected_user_index, set_expected_user_index;
+                #[export]
+                command_address: GString => get_command_address, set_command_address;
+                #[export]
+                command_port: i64 => get_command_port, set_command_port;
+                command_link_connected: bool => get_command_link_connected;
+            }
+            signals {
+                connection_changed(connected: bool, previous_duration: f64);
+                phase_changed(phase: MatchPhase);
+                level_changed(level: StringName, previous: StringName);
+                alliance_changed(alliance: StringName);
+                /// `reason` is "started", "requested", "released", "handed_over",
+                /// "failover" or "split_brain".
+                authority_changed(active: bool, reason: StringName);
+                mode_changed(mode: StringName, previous: StringName);
+                action_set_changed(name: StringName, previous: StringName);
+                /// phase_action_sets switched to `set` as `phase` started.
+                phase_actions_applied(phase: StringName, set: StringName);
+                /// Step `index` of the running sequence has started.
+                sequence_step(index: i64, description: GString);
+                /// `reason` is "finished", "stopped", "operator_input", why a step
+                /// failed, or the reason the inputs were neutralized.
+                sequence_finished(completed: bool, reason: GString);
+                face_changed(face: StringName, previous: StringName);
+                /// The human player was signalled, `count` times this match. Too
+                /// soon after the last one gives press_blocked with "cooldown".
+                hp_signal_sent(count: i64);
+                /// A cycle from cycle_pairs finished, `duration` in seconds.
+                cycle_completed(label: GString, duration: f64);
+                scouting_csv_written(path: GString);
+                scouting_csv_failed(path: GString, error: GString);
+                action_count_changed(name: StringName, count: i64);
+                /// An automatic tap of `name` will fire in `delay_secs`.
+                auto_action_scheduled(name: StringName, delay_secs: f64);
+                /// `result` is the ActionResult of the press, which goes through the
+                /// usual checks.
+                auto_action_fired(name: StringName, result: ActionResult);
+                /// `report` is check name -> { passed, detail }, as returned by
+                /// run_prematch_check() but with the latency probe filled in.
+                prematch_check_finished(all_passed: bool, report: Dictionary);
+                score_started(level: StringName);
+                /// `completed` is false if the sequence was aborted.
+                score_finished(level: StringName, completed: bool);
+                endgame_started();
+                /// Operator input was locked out for autonomous, or let back in.
+                auto_lockout_changed(locked: bool);
+                match_report_written(path: GString);
+                match_report_failed(path: GString, error: GString);
+                /// One of endgame_warning_secs was reached. `seconds_remaining` is
+                /// that threshold.
+                endgame_warning(seconds_remaining: f64);
+                /// The ping target changed, both as "host:port".
+                endpoint_changed(old: GString, new: GString);
+                inputs_neutralized(reason: StringName);
+                interface_reset();
+                started();
+                override_expired();
+                stopped();
+                service_status_changed(name: GString, up: bool);
+                verification_failed(reason: GString);
+                robot_discovered(address: GString, verified: bool);
+                discovery_finished(cancelled: bool);
+                connection_error(kind: ConnectionErrorKind, message: GString);
+                device_connection_changed(name: GString, connected: bool);
+                network_interfaces_changed(addresses: PackedStringArray);
+                diagnosis_changed(diagnosis: NetworkDiagnosis);
+                settings_loaded();
+                action_pressed(name: StringName, origin: StringName);
+                action_released(name: StringName, origin: StringName);
+                hold_progress(name: StringName, fraction: f64);
+                hold_cancelled(name: StringName);
+                action_confirmation_requested(name: StringName);
+                /// The coach didn't answer within coach_confirm_timeout_secs.
+                action_confirmation_timeout(name: StringName);
+                /// A press of `name` was turned down. `reason` is "cooldown",
+                /// "disabled", "endgame_lockout", "standby" or "autonomous",
+                /// `remaining_secs` how long until it can go through if known, else 0.
+                press_blocked(name: StringName, remaining_secs: f64, reason: StringName);
+                press_vetoed(name: StringName);
+                action_undone(original: StringName, inverse: StringName);
+                /// Emitted button_held_rate_hz times a second for each held action.
+                button_held(name: StringName, elapsed_secs: f64);
+                /// `reason` is "cancelled", "timeout", "replaced", "disabled", "rejected"
+                /// (by the coach) or the neutralize reason.
+                action_confirmation_cancelled(name: StringName, reason: StringName);
+                button_box_connection_changed(connected: bool);
+                button_acknowledged(name: GString);
+                button_ack_timeout(name: GString);
+                /// A press of `name` went unanswered for ack_timeout_ms. Its indicator,
+                /// if bound, flashes until the next good ack or clear_ack_errors().
+                button_unacknowledged(name: GString);
+                command_sent(command: Dictionary);
+                command_send_failed(command: Dictionary, reason: GString);
+                command_response(response: Dictionary);
+                controller_init_failed(reason: GString);
+                /// The thread feeding the virtual pad stopped. A restart is tried
+                /// right away; controller_state_changed follows with the outcome.
+                controller_thread_died();
+                /// "ok", "restarting" or "failed".
+                controller_state_changed(state: GString);
+            }
+            funcs {
+                start();
+                stop();
+                apply_config();
+                save_settings() -> bool;
+                load_settings() -> bool;
+                score_coral(level: GString) -> ActionResult;
+                load_sequence(path: GString) -> godot::global::Error;
+                run_sequence() -> godot::global::Error;
+                stop_sequence();
+                reset_action_counts();
+                start_match();
+                stop_match();
+                reset_match();
+                notify_robot_mode(mode: GString);
+                write_match_report(path: GString) -> GString;
+                export_scouting_csv(path: GString) -> godot::global::Error;
+                clear_last_action();
+                on_range_value_changed(value: f64, index: i64);
+                on_range_drag_ended(_value_changed: bool, index: i64);
+                bind_button(action: GString, node_path: NodePath) -> godot::global::Error;
+                unbind_button(action: GString);
+                bind_button_group(group: StringName, buttons: Dictionary) -> godot::global::Error;
+                unbind_button_group(group: StringName);
+                clear_button_group(group: StringName);
+                on_group_button_toggled(toggled_on: bool, group: StringName, action: StringName);
+                discover_robot() -> bool;
+                cancel_discovery();
+                reset_ping_stats();
+                add_robot_time_sample(robot_unix_secs: f64, sent_unix_secs: f64, received_unix_secs: f64);
+                reset_robot_clock_offset();
+                override_endgame_lockout();
+                set_action_enabled(name: GString, enabled: bool);
+                activate_action_set(name: GString) -> godot::global::Error;
+                reset_connection_stats();
+                quick_reset();
+                rearm_inputs() -> bool;
+                run_prematch_check() -> Dictionary;
+                start_status_server() -> bool;
+                stop_status_server();
+                start_remote_server() -> bool;
+                stop_remote_server();
+                request_authority() -> bool;
+                release_authority();
+                start_command_link() -> bool;
+                stop_command_link();
+                send_command(command: Dictionary) -> bool;
+                retry_controller_init() -> bool;
+                set_press_filter(filter: Callable);
+                set_axis(axis: StringName, value: f64) -> ActionResult;
+                press_button(name: StringName) -> ActionResult;
+                send_hp_signal() -> ActionResult;
+                release_button(name: StringName) -> ActionResult;
+                tap_button(name: StringName, duration_ms: i64) -> ActionResult;
+                undo_last_action() -> ActionResult;
+                acknowledge_button(name: GString);
+                acknowledge_press(name: GString, seq: i64);
+                bind_ack_indicator(action: GString, node_path: NodePath) -> godot::global::Error;
+                unbind_ack_indicator(action: GString);
+                clear_ack_errors();
+                acknowledge_topic(topic: GString);
+                acknowledge_topic_seq(topic: GString, seq: i64);
+                clear_alliance_override();
+                report_fms_alliance(is_red: bool);
+                bind_face_buttons(buttons: Dictionary) -> godot::global::Error;
+                unbind_face_buttons();
+                on_face_button_toggled(toggled_on: bool, face: GString);
+                on_button_pressed(button_name: StringName);
+                on_joy_connection_changed(device: i64, connected: bool);
+                clear_cooldowns();
+                on_confirmation_confirmed();
+                on_confirmation_canceled();
+                on_button_gui_input(event: Gd<InputEvent>, button_name: StringName, button: Gd<BaseButton>);
+                on_button_toggled(toggled_on: bool, button_name: StringName);
+                on_button_released(button_name: StringName);
+                toggle_force_connected();
+                simulate_connection_loss(duration_secs: f64);
+                simulate_connection_restore();
+            }
+            const_funcs {
+                extract_config() -> Gd<FRCInterfaceConfig>;
+                is_sequence_running() -> bool;
+                get_action_counts() -> Dictionary;
+                get_blocked_counts() -> Dictionary;
+                get_cycle_stats() -> Dictionary;
+                get_hold_duration(name: GString) -> f64;
+                get_button_group_selection(group: StringName) -> StringName;
+                get_bindings() -> Dictionary;
+                is_discovering() -> bool;
+                get_service_status() -> Dictionary;
+                get_device_status() -> Dictionary;
+                get_ping_stats() -> Dictionary;
+                get_log_path() -> GString;
+                get_local_addresses() -> PackedStringArray;
+                is_action_enabled(name: GString) -> bool;
+                get_disabled_actions() -> PackedStringArray;
+                get_active_action_set() -> GString;
+                get_connection_history() -> Array<Dictionary>;
+                get_status() -> Dictionary;
+                is_controller_ready() -> bool;
+                get_ack_errors() -> PackedStringArray;
+                get_ack_topic(name: GString) -> GString;
+            }
+        }
+    };
+}
+
+interface_class! {
+    /// The interface as a Node3D, as the existing scenes use it.
+    FRCInterfaceBase: Node3D, INode3D, Node3DNotification
+}
+
+interface_class! {
+    /// The interface on a plain Node, for 2D and Control-only scenes.
+    FRCInterface2D: Node, INode, NodeNotification
+}
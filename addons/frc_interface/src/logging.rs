@@ -0,0 +1,111 @@
+use godot::prelude::*;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How much the extension prints. Each level includes the ones above it.
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[godot(via = i64)]
+pub enum LogLevel {
+    Error = 0,
+    Warn = 1,
+    Info = 2,
+    Debug = 3,
+    /// Every report sent to the virtual controller.
+    Trace = 4,
+}
+
+// One level for the whole process: the worker threads log without access to
+// a node, so it can't be per node. Only the log_level setter changes it
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// The level in effect, whichever node set it last.
+pub fn level() -> LogLevel {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => LogLevel::Error,
+        1 => LogLevel::Warn,
+        2 => LogLevel::Info,
+        3 => LogLevel::Debug,
+        _ => LogLevel::Trace,
+    }
+}
+
+pub fn enabled(level: LogLevel) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+// Unit tests run without the engine, so they print to stderr instead
+#[cfg(not(test))]
+macro_rules! log_output {
+    ($print:ident, $($arg:tt)*) => {
+        godot::global::$print!($($arg)*)
+    };
+}
+
+#[cfg(test)]
+macro_rules! log_output {
+    ($print:ident, $($arg:tt)*) => {
+        eprintln!($($arg)*)
+    };
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::LogLevel::Error) {
+            log_output!(godot_error, $($arg)*);
+        }
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::LogLevel::Warn) {
+            log_output!(godot_warn, $($arg)*);
+        }
+    };
+}
+
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::LogLevel::Info) {
+            log_output!(godot_print, $($arg)*);
+        }
+    };
+}
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::LogLevel::Debug) {
+            log_output!(godot_print, $($arg)*);
+        }
+    };
+}
+
+macro_rules! log_trace {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::LogLevel::Trace) {
+            log_output!(godot_print, $($arg)*);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_level_set_is_the_level_read_back() {
+        let before = level();
+        for set in [LogLevel::Error, LogLevel::Warn, LogLevel::Info, LogLevel::Debug, LogLevel::Trace] {
+            set_level(set);
+            assert_eq!(level(), set);
+            assert!(enabled(set));
+            assert!(enabled(LogLevel::Error));
+        }
+        set_level(LogLevel::Warn);
+        assert!(!enabled(LogLevel::Info));
+        set_level(before);
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WriteKind {
+    MatchReport,
+    ScoutingCsv,
+}
+
+/// How one report write went.
+pub struct WriteResult {
+    pub kind: WriteKind,
+    /// The path as the caller gave it, e.g. a user:// path.
+    pub path: String,
+    pub error: Option<String>,
+}
+
+/// Writes match reports and exports from background threads, so a slow disk
+/// never stalls a frame. Outcomes are picked up with `poll()` from `process()`.
+pub struct ReportWriter {
+    sender: mpsc::Sender<WriteResult>,
+    results: mpsc::Receiver<WriteResult>,
+    // Held through each update, so two of them can't read the same old file
+    updating: Arc<Mutex<()>>,
+}
+
+impl ReportWriter {
+    pub fn new() -> Self {
+        let (sender, results) = mpsc::channel();
+        Self {
+            sender,
+            results,
+            updating: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Write `contents` to `file`, creating its directory if needed.
+    /// `path` is what the outcome is reported under.
+    pub fn write(&self, file: PathBuf, path: String, contents: String) {
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let result = match file.parent() {
+                Some(dir) => fs::create_dir_all(dir),
+                None => Ok(()),
+            }
+            .and_then(|_| fs::write(&file, contents));
+
+            let _ = sender.send(WriteResult {
+                kind: WriteKind::MatchReport,
+                path,
+                error: result.err().map(|e| e.to_string()),
+            });
+        });
+    }
+
+    /// Rewrite `file` with whatever `update` makes of its current contents
+    /// (`None` if it doesn't exist yet). An error from `update` leaves the
+    /// file as it was; the new contents replace it in one rename, so a crash
+    /// mid-write can't leave half a file.
+    pub fn update<F>(&self, kind: WriteKind, file: PathBuf, path: String, update: F)
+    where
+        F: FnOnce(Option<&str>) -> Result<String, String> + Send + 'static,
+    {
+        let sender = self.sender.clone();
+        let updating = self.updating.clone();
+        thread::spawn(move || {
+            let _guard = updating.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let existing = match fs::read_to_string(&file) {
+                Ok(contents) => Ok(Some(contents)),
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.to_string()),
+            };
+            let result = existing
+                .and_then(|existing| update(existing.as_deref()))
+                .and_then(|contents| {
+                    let staging = file.with_extension("tmp");
+                    match file.parent() {
+                        Some(dir) => fs::create_dir_all(dir),
+                        None => Ok(()),
+                    }
+                    .and_then(|_| fs::write(&staging, contents))
+                    .and_then(|_| fs::rename(&staging, &file))
+                    .map_err(|e| e.to_string())
+                });
+
+            let _ = sender.send(WriteResult {
+                kind,
+                path,
+                error: result.err(),
+            });
+        });
+    }
+
+    pub fn poll(&self) -> Vec<WriteResult> {
+        self.results.try_iter().collect()
+    }
+}
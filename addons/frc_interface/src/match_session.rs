@@ -0,0 +1,549 @@
+use crate::match_report::WriteKind;
+use crate::match_timer::{MatchDurations, MatchPhase};
+use crate::{duration_ms, InterfaceCore};
+use godot::classes::{ProjectSettings, Time};
+use godot::prelude::*;
+use serde_json::json;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Where post-match reports are written
+const MATCH_REPORT_DIR: &str = "user://match_reports";
+
+// Events kept for one match report, later ones are dropped
+const MAX_MATCH_EVENTS: usize = 5000;
+
+// Blink rate of endgame_warning_flash
+const WARNING_FLASH_PERIOD: Duration = Duration::from_millis(150);
+
+impl InterfaceCore {
+    /// Presses that reached the controller per action, since the last reset.
+    pub fn get_action_counts(&self) -> Dictionary {
+        let mut counts = Dictionary::new();
+        for (name, count) in &self.action_counts {
+            counts.set(name.as_str(), *count);
+        }
+        counts
+    }
+
+    /// Presses turned away (not connected, locked, cooling down, disabled,
+    /// locked out or vetoed) per action, since the last reset.
+    pub fn get_blocked_counts(&self) -> Dictionary {
+        let mut counts = Dictionary::new();
+        for (name, count) in &self.blocked_counts {
+            counts.set(name.as_str(), *count);
+        }
+        counts
+    }
+
+    pub fn reset_action_counts(&mut self) {
+        let names: Vec<String> = self.action_counts.drain().map(|(name, _)| name).collect();
+        self.blocked_counts.clear();
+        self.open_cycles.clear();
+        self.cycle_stats.clear();
+        for name in names {
+            let args = [StringName::from(name.as_str()).to_variant(), 0i64.to_variant()];
+            self.emit("action_count_changed", &args);
+        }
+    }
+
+    /// Start or finish the cycles `name` is the start or end of.
+    pub fn track_cycles(&mut self, name: &str) {
+        let now = Instant::now();
+        let mut completed = Vec::new();
+        for (start, end, label) in self.cycle_definitions() {
+            // An end that is also the next start closes one cycle and opens another
+            if name == end {
+                if let Some(started) = self.open_cycles.remove(&label) {
+                    let duration = now.duration_since(started);
+                    self.cycle_stats.entry(label.clone()).or_default().record(duration);
+                    completed.push((label.clone(), duration));
+                }
+            }
+            if name == start {
+                if self.open_cycles.insert(label.clone(), now).is_some() {
+                    self.cycle_stats.entry(label.clone()).or_default().aborted += 1;
+                    log_debug!("Cycle {} started again before it finished", label);
+                }
+            }
+        }
+
+        for (label, duration) in completed {
+            log_info!("Cycle {} took {:.2}s", label, duration.as_secs_f64());
+            let args = [GString::from(label.as_str()).to_variant(), duration.as_secs_f64().to_variant()];
+            self.emit("cycle_completed", &args);
+        }
+    }
+
+    /// (start, end, label) for each entry in cycle_pairs. The label
+    /// defaults to "start->end".
+    fn cycle_definitions(&self) -> Vec<(String, String, String)> {
+        self.cycle_pairs
+            .iter_shared()
+            .filter_map(|pair| pair.try_to::<Dictionary>().ok())
+            .map(|pair| {
+                let field = |key: &str| pair.get(key).map(|value| value.to_string()).unwrap_or_default();
+                let (start, end) = (field("start"), field("end"));
+                let label = match field("label") {
+                    label if label.is_empty() => format!("{}->{}", start, end),
+                    label => label,
+                };
+                (start, end, label)
+            })
+            .collect()
+    }
+
+    /// Per cycle label: `count`, `aborted`, and `min_secs`, `avg_secs`,
+    /// `max_secs` (-1 with no completed cycles). Cycles still open when
+    /// the match ends count as aborted.
+    pub fn get_cycle_stats(&self) -> Dictionary {
+        let secs = |duration: Option<Duration>| duration.map_or(-1.0, |d| d.as_secs_f64());
+        let mut stats = Dictionary::new();
+        for (label, cycle) in &self.cycle_stats {
+            let mut entry = Dictionary::new();
+            entry.set("count", cycle.count);
+            entry.set("aborted", cycle.aborted);
+            entry.set("min_secs", secs(cycle.min));
+            entry.set("avg_secs", secs(cycle.average()));
+            entry.set("max_secs", secs(cycle.max));
+            stats.set(label.as_str(), entry);
+        }
+        stats
+    }
+
+    /// Start the match clock from the beginning of auto.
+    pub fn start_match(&mut self) {
+        log_info!("Match clock started");
+        if self.reset_counts_on_match_start {
+            self.reset_action_counts();
+        }
+        self.reset_hp_signal();
+        self.match_timer.start();
+        self.endgame_lockout_overridden = false;
+        self.endgame_warnings_fired.clear();
+        self.start_match_recording();
+        self.update_match_phase();
+    }
+
+    /// Freeze the match clock, keeping the phase and time left.
+    pub fn stop_match(&mut self) {
+        self.match_timer.stop();
+        self.update_match_phase();
+        self.finish_match_recording();
+    }
+
+    /// Back to pre-match. A match still going is abandoned, and there's no
+    /// finished match to export any more.
+    pub fn reset_match(&mut self) {
+        self.match_timer.reset();
+        self.reset_hp_signal();
+        self.match_recording = false;
+        self.match_started_wall = 0.0;
+        self.finished_match = None;
+        self.endgame_lockout_overridden = false;
+        self.endgame_warnings_fired.clear();
+        self.stop_warning_flash();
+        self.update_match_phase();
+        self.update_buttons_disabled();
+    }
+
+    /// Tell the interface what mode the robot is in, e.g. from
+    /// NetworkTables. With match_sync_to_robot_mode, "autonomous" starts
+    /// the clock if it isn't already going and "disabled" after teleop
+    /// stops it.
+    pub fn notify_robot_mode(&mut self, mode: GString) {
+        let mode = match mode.to_string().to_lowercase().as_str() {
+            "auto" | "autonomous" => "autonomous",
+            "teleop" | "teleoperated" => "teleop",
+            "test" => "test",
+            "disabled" => "disabled",
+            _ => "unknown",
+        };
+        if self.robot_mode.to_string() != mode {
+            log_info!("Robot mode {} -> {}", self.robot_mode, mode);
+            self.robot_mode = GString::from(mode);
+
+            // The match clock knows about endgame, so it wins while it runs
+            if !self.match_timer.is_running() {
+                match mode {
+                    "autonomous" => self.apply_phase_action_set(MatchPhase::Auto.as_str()),
+                    "teleop" => self.apply_phase_action_set(MatchPhase::Teleop.as_str()),
+                    _ => {}
+                }
+            }
+        }
+        self.update_auto_lockout();
+
+        if !self.match_sync_to_robot_mode {
+            return;
+        }
+        match mode {
+            "autonomous" if !self.match_timer.is_running() => self.start_match(),
+            "disabled" if matches!(self.match_phase, MatchPhase::Teleop | MatchPhase::Endgame) => self.stop_match(),
+            _ => {}
+        }
+    }
+
+    /// Whether operator input is held off for autonomous right now.
+    pub fn auto_lockout_active(&self) -> bool {
+        if !self.lockout_during_auto {
+            return false;
+        }
+        match self.robot_mode.to_string().as_str() {
+            "autonomous" => true,
+            "unknown" => self.auto_lockout_when_mode_unknown,
+            _ => false,
+        }
+    }
+
+    /// Follow the lockout, letting go of everything as it starts.
+    pub fn update_auto_lockout(&mut self) {
+        let locked = self.auto_lockout_active();
+        if locked == self.auto_locked_out {
+            return;
+        }
+        self.auto_locked_out = locked;
+
+        if locked {
+            log_warn!("Operator input locked out ({})", self.robot_mode);
+            self.neutralize_inputs("autonomous");
+        } else {
+            log_info!("Operator input re-enabled ({})", self.robot_mode);
+        }
+        self.update_buttons_disabled();
+        self.emit("auto_lockout_changed", &[locked.to_variant()]);
+    }
+
+    fn start_match_recording(&mut self) {
+        self.match_recording = true;
+        self.match_started_wall = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |d| d.as_secs_f64());
+        self.match_events.clear();
+        self.match_outages.clear();
+        self.finished_match = None;
+        if !self.connected {
+            self.match_outages.push((0.0, None));
+        }
+        self.match_ping_baseline = (self.ping_stats.attempts, self.ping_stats.successes);
+    }
+
+    /// The match is over: stop collecting and write the report, once.
+    fn finish_match_recording(&mut self) {
+        if !self.match_recording {
+            return;
+        }
+        self.match_recording = false;
+        for (label, _) in self.open_cycles.drain() {
+            self.cycle_stats.entry(label).or_default().aborted += 1;
+        }
+        self.finished_match = Some(self.scouting_row());
+        if self.write_match_reports {
+            self.write_match_report(GString::new());
+        }
+    }
+
+    pub fn match_secs(&self) -> f64 {
+        self.match_timer.elapsed().map_or(0.0, |elapsed| elapsed.as_secs_f64())
+    }
+
+    /// Add an entry to the current match's report, if a match is on.
+    pub fn record_match_event(&mut self, event: &str, mut entry: serde_json::Value) {
+        if !self.match_recording || self.match_events.len() >= MAX_MATCH_EVENTS {
+            return;
+        }
+        entry["event"] = json!(event);
+        entry["match_secs"] = json!(self.match_secs());
+        entry["wall_time"] = json!(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |d| d.as_secs_f64())
+        );
+        self.match_events.push(entry);
+    }
+
+    /// Write the current (or last) match's report as JSON, in the
+    /// background. An empty `path` picks a new file in MATCH_REPORT_DIR.
+    /// Returns the path used; match_report_written or match_report_failed
+    /// follows once the file is done.
+    pub fn write_match_report(&mut self, path: GString) -> GString {
+        let path = if path.is_empty() {
+            let stamp = Time::singleton().get_datetime_string_from_system().to_string().replace(':', "-");
+            GString::from(format!("{}/match_{}.json", MATCH_REPORT_DIR, stamp).as_str())
+        } else {
+            path
+        };
+        let file = ProjectSettings::singleton().globalize_path(&path).to_string();
+
+        let contents = match serde_json::to_string_pretty(&self.match_report()) {
+            Ok(contents) => contents,
+            Err(e) => {
+                log_error!("Failed to build match report: {}", e);
+                let args = [path.to_variant(), GString::from(e.to_string().as_str()).to_variant()];
+                self.emit("match_report_failed", &args);
+                return path;
+            }
+        };
+        self.report_writer.write(file.into(), path.to_string(), contents);
+        path
+    }
+
+    /// Add the last finished match to the scouting CSV at `path`, or
+    /// replace its row if it's there already. The row is what the match
+    /// looked like as it ended, and `completed` says whether it ran to
+    /// post-match or was stopped early. Rows are keyed by event_code,
+    /// match_label and team_number, so match_label has to be set. A file
+    /// whose columns differ, or that doesn't parse, is left alone and
+    /// scouting_csv_failed says why; scouting_csv_written on success.
+    pub fn export_scouting_csv(&mut self, path: GString) -> godot::global::Error {
+        let Some((mut header, mut row)) = self.finished_match.clone() else {
+            log_warn!("No finished match to export");
+            return godot::global::Error::ERR_UNAVAILABLE;
+        };
+        if row[1].is_empty() {
+            log_warn!("Set match_label before the match ends, rows are keyed by it");
+            return godot::global::Error::ERR_UNCONFIGURED;
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64());
+        header.push("exported_at".to_string());
+        row.push(Time::singleton().get_datetime_string_from_unix_time(now as i64).to_string());
+        let file = ProjectSettings::singleton().globalize_path(&path).to_string();
+        self.report_writer
+            .update(WriteKind::ScoutingCsv, file.into(), path.to_string(), move |existing| {
+                scouting::merge_row(existing, &header, 3, &row)
+            });
+        godot::global::Error::OK
+    }
+
+    /// Column names and values for the match as it stands, less
+    /// exported_at. The first three are the key.
+    fn scouting_row(&self) -> (Vec<String>, Vec<String>) {
+        // UTC, so rows from different machines line up
+        let time = Time::singleton();
+        let timestamp = |secs: f64| time.get_datetime_string_from_unix_time(secs as i64).to_string();
+        let secs = |duration: Option<Duration>| {
+            duration.map_or(String::new(), |d| format!("{:.3}", d.as_secs_f64()))
+        };
+
+        let duration = self.match_secs();
+        let downtime: f64 = self
+            .match_outages
+            .iter()
+            .map(|(start, end)| end.unwrap_or(duration) - start)
+            .sum();
+        let mut columns = vec![
+            ("event_code", self.event_code.to_string()),
+            ("match_label", self.match_label.to_string()),
+            ("team_number", self.team_number.to_string()),
+            ("alliance", self.alliance.to_string()),
+            ("started_at", timestamp(self.match_started_wall)),
+            ("ended_at", timestamp(self.match_started_wall + duration)),
+            ("duration_secs", format!("{:.3}", duration)),
+            ("downtime_secs", format!("{:.3}", downtime)),
+            ("outages", self.match_outages.len().to_string()),
+            ("completed", (self.match_phase == MatchPhase::PostMatch).to_string()),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect::<Vec<_>>();
+
+        for action in Self::known_actions() {
+            let count = self.action_counts.get(action).copied().unwrap_or(0);
+            let blocked = self.blocked_counts.get(action).copied().unwrap_or(0);
+            columns.push((format!("{}_count", action), count.to_string()));
+            columns.push((format!("{}_blocked", action), blocked.to_string()));
+        }
+
+        for (_, _, label) in self.cycle_definitions() {
+            let cycle = self.cycle_stats.get(&label);
+            columns.push((format!("{}_cycles", label), cycle.map_or(0, |c| c.count).to_string()));
+            columns.push((format!("{}_aborted", label), cycle.map_or(0, |c| c.aborted).to_string()));
+            columns.push((format!("{}_min_secs", label), secs(cycle.and_then(|c| c.min))));
+            columns.push((format!("{}_avg_secs", label), secs(cycle.and_then(|c| c.average()))));
+            columns.push((format!("{}_max_secs", label), secs(cycle.and_then(|c| c.max))));
+        }
+
+        columns.into_iter().unzip()
+    }
+
+    pub fn match_report(&self) -> serde_json::Value {
+        let stats = &self.ping_stats;
+        let (attempts_before, successes_before) = self.match_ping_baseline;
+        let attempts = stats.attempts.saturating_sub(attempts_before);
+        let successes = stats.successes.saturating_sub(successes_before);
+
+        let now = self.match_secs();
+        let outages: Vec<serde_json::Value> = self
+            .match_outages
+            .iter()
+            .map(|(start, end)| {
+                json!({
+                    "start_secs": start,
+                    "end_secs": end,
+                    "duration_secs": end.unwrap_or(now) - start,
+                })
+            })
+            .collect();
+        let downtime: f64 = self.match_outages.iter().map(|(start, end)| end.unwrap_or(now) - start).sum();
+
+        let controller_events: Vec<&serde_json::Value> = self
+            .match_events
+            .iter()
+            .filter(|entry| matches!(entry["event"].as_str(), Some("controller" | "neutralized")))
+            .collect();
+
+        let secs = |duration: Option<Duration>| duration.map(|d| d.as_secs_f64());
+        let cycles: serde_json::Map<String, serde_json::Value> = self
+            .cycle_stats
+            .iter()
+            .map(|(label, cycle)| (label.clone(), json!({
+                "count": cycle.count,
+                "aborted": cycle.aborted,
+                "min_secs": secs(cycle.min),
+                "avg_secs": secs(cycle.average()),
+                "max_secs": secs(cycle.max),
+            })))
+            .collect();
+
+        json!({
+            "match": {
+                "started_at": self.match_started_wall,
+                "duration_secs": now,
+                "phase": self.match_phase.as_str(),
+                "team_number": self.team_number,
+                "event_code": self.event_code.to_string(),
+                "match_label": self.match_label.to_string(),
+                "alliance": self.alliance.to_string(),
+                "mode": self.mode.to_string(),
+            },
+            "generated_at": SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |d| d.as_secs_f64()),
+            "actions": {
+                "counts": self.action_counts,
+                "blocked": self.blocked_counts,
+            },
+            "cycles": cycles,
+            "events": self.match_events,
+            "connection": {
+                "pings": attempts,
+                "ping_failures": attempts - successes,
+                "latency_ms": {
+                    "min": duration_ms(stats.min),
+                    "avg": duration_ms(stats.average()),
+                    "max": duration_ms(stats.max),
+                    "p95": duration_ms(stats.percentile(95.0)),
+                },
+                "outages": outages,
+                "downtime_secs": downtime,
+            },
+            "controller": {
+                "ready": self.virtual_controller.as_ref().is_some_and(|c| c.is_running()),
+                "output_mode": format!("{:?}", self.output_mode),
+                "dry_run": self.dry_run,
+                "events": controller_events,
+            },
+        })
+    }
+
+    pub fn match_durations(&self) -> MatchDurations {
+        let secs = |secs: f64| Duration::from_secs_f64(secs.max(0.0));
+        MatchDurations {
+            auto: secs(self.match_auto_secs),
+            teleop: secs(self.match_teleop_secs),
+            endgame: secs(self.match_endgame_secs),
+        }
+    }
+
+    pub fn update_match_phase(&mut self) {
+        let durations = self.match_durations();
+        self.match_time_remaining = self.match_timer.remaining(&durations).as_secs_f64();
+        self.raise_endgame_warnings(&durations);
+
+        let phase = self.match_timer.phase(&durations);
+        if phase == self.match_phase {
+            return;
+        }
+        let previous = std::mem::replace(&mut self.match_phase, phase);
+        log_info!("Match phase {} -> {}", previous.as_str(), phase.as_str());
+        self.record_match_event("phase", json!({ "phase": phase.as_str() }));
+        self.apply_phase_action_set(phase.as_str());
+        self.update_buttons_disabled();
+        if phase == MatchPhase::PostMatch {
+            self.finish_match_recording();
+        }
+
+        self.emit("phase_changed", &[phase.to_variant()]);
+        if phase == MatchPhase::Endgame {
+            self.emit("endgame_started", &[]);
+        }
+    }
+
+    /// Raise every threshold the clock has passed since the last frame, so
+    /// a stutter across one still fires it, once.
+    fn raise_endgame_warnings(&mut self, durations: &MatchDurations) {
+        let Some(until_end) = self.match_timer.until_end(durations) else {
+            return;
+        };
+        // Thresholds longer than teleop would otherwise go off during auto
+        if self.match_timer.elapsed().is_some_and(|elapsed| elapsed < durations.auto) {
+            return;
+        }
+
+        let mut due: Vec<f64> = self
+            .endgame_warning_secs
+            .as_slice()
+            .iter()
+            .copied()
+            .filter(|secs| until_end.as_secs_f64() <= *secs && !self.endgame_warnings_fired.contains(secs))
+            .collect();
+        if due.is_empty() {
+            return;
+        }
+        due.sort_by(|a, b| b.total_cmp(a));
+        self.endgame_warnings_fired.extend(&due);
+
+        if let Some(sound) = self.endgame_warning_sound.clone() {
+            self.play_feedback_sound("endgame_warning", sound);
+        }
+        self.start_warning_flash();
+        for secs in due {
+            log_info!("Endgame warning: {} s left", secs);
+            self.emit("endgame_warning", &[secs.to_variant()]);
+        }
+    }
+
+    fn start_warning_flash(&mut self) {
+        let Some(item) = self.endgame_warning_flash.as_ref().filter(|item| item.is_instance_valid()) else {
+            return;
+        };
+        // A flash already running keeps the visibility it saved
+        let was_visible = self.warning_flash.map_or(item.is_visible(), |(_, visible)| visible);
+        self.warning_flash = Some((Instant::now(), was_visible));
+    }
+
+    pub fn update_warning_flash(&mut self) {
+        let Some((started, _)) = self.warning_flash else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        if elapsed.as_secs_f64() >= self.endgame_warning_flash_secs {
+            self.stop_warning_flash();
+            return;
+        }
+
+        let on = (elapsed.as_millis() / WARNING_FLASH_PERIOD.as_millis()) % 2 == 0;
+        if let Some(item) = self.endgame_warning_flash.as_mut().filter(|item| item.is_instance_valid()) {
+            item.set_visible(on);
+        }
+    }
+
+    fn stop_warning_flash(&mut self) {
+        let Some((_, was_visible)) = self.warning_flash.take() else {
+            return;
+        };
+        if let Some(item) = self.endgame_warning_flash.as_mut().filter(|item| item.is_instance_valid()) {
+            item.set_visible(was_visible);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use godot::prelude::*;
+use std::time::{Duration, Instant};
+
+/// Where the match is, as far as the local timer knows.
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+pub enum MatchPhase {
+    PreMatch = 0,
+    Auto = 1,
+    Teleop = 2,
+    /// The last part of teleop.
+    Endgame = 3,
+    PostMatch = 4,
+}
+
+impl MatchPhase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatchPhase::PreMatch => "pre_match",
+            MatchPhase::Auto => "auto",
+            MatchPhase::Teleop => "teleop",
+            MatchPhase::Endgame => "endgame",
+            MatchPhase::PostMatch => "post_match",
+        }
+    }
+}
+
+pub struct MatchDurations {
+    pub auto: Duration,
+    pub teleop: Duration,
+    /// Counted from the end of teleop.
+    pub endgame: Duration,
+}
+
+/// Match clock on monotonic time, so a slow frame never loses time.
+#[derive(Default)]
+pub struct MatchTimer {
+    started: Option<Instant>,
+    // Elapsed time frozen by stop()
+    stopped_at: Option<Duration>,
+}
+
+impl MatchTimer {
+    /// Start a new match from zero.
+    pub fn start(&mut self) {
+        self.started = Some(Instant::now());
+        self.stopped_at = None;
+    }
+
+    /// Freeze the clock where it is.
+    pub fn stop(&mut self) {
+        if self.stopped_at.is_none() {
+            self.stopped_at = self.elapsed();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some() && self.stopped_at.is_none()
+    }
+
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.stopped_at.or_else(|| self.started.map(|started| started.elapsed()))
+    }
+
+    pub fn phase(&self, durations: &MatchDurations) -> MatchPhase {
+        let Some(elapsed) = self.elapsed() else {
+            return MatchPhase::PreMatch;
+        };
+
+        let match_end = durations.auto + durations.teleop;
+        if elapsed < durations.auto {
+            MatchPhase::Auto
+        } else if elapsed >= match_end {
+            MatchPhase::PostMatch
+        } else if elapsed >= match_end.saturating_sub(durations.endgame) {
+            MatchPhase::Endgame
+        } else {
+            MatchPhase::Teleop
+        }
+    }
+
+    /// Time left until the end of teleop, `None` before the match starts.
+    pub fn until_end(&self, durations: &MatchDurations) -> Option<Duration> {
+        self.elapsed().map(|elapsed| (durations.auto + durations.teleop).saturating_sub(elapsed))
+    }
+
+    /// Time left in the current period, auto or teleop, like the field clock.
+    pub fn remaining(&self, durations: &MatchDurations) -> Duration {
+        let Some(elapsed) = self.elapsed() else {
+            return Duration::ZERO;
+        };
+
+        if elapsed < durations.auto {
+            durations.auto - elapsed
+        } else {
+            (durations.auto + durations.teleop).saturating_sub(elapsed)
+        }
+    }
+}
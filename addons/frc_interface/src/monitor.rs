@@ -0,0 +1,248 @@
+use crate::shutdown::{join_within, SHUTDOWN_TIMEOUT};
+use crate::status_server::DEFAULT_PORT;
+use godot::classes::{IVBoxContainer, Json, Label, VBoxContainer};
+use godot::prelude::*;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Largest status document read, anything longer is cut off
+const MAX_RESPONSE_LENGTH: u64 = 256 * 1024;
+
+/// Read-only view of a running game's interface, for an editor dock.
+///
+/// Polls the status endpoint of the game on this machine, so the interface
+/// node needs `status_server_enabled`. plugin.gd adds one to the dock.
+#[derive(GodotClass)]
+#[class(tool, base=VBoxContainer)]
+pub struct FRCInterfaceMonitor {
+    /// Must match the game's `status_server_port`.
+    #[export]
+    #[var(get, set = set_port)]
+    #[init(val = DEFAULT_PORT)]
+    port: i64,
+
+    labels: Option<MonitorLabels>,
+    poller: Option<StatusPoller>,
+
+    base: Base<VBoxContainer>,
+}
+
+struct MonitorLabels {
+    connection: Gd<Label>,
+    latency: Gd<Label>,
+    controller: Gd<Label>,
+    actions: Gd<Label>,
+}
+
+#[godot_api]
+impl IVBoxContainer for FRCInterfaceMonitor {
+    fn enter_tree(&mut self) {
+        self.start_polling();
+    }
+
+    fn ready(&mut self) {
+        self.base_mut().set_name("FRC Interface");
+
+        let mut add_label = |text: &str| {
+            let mut label = Label::new_alloc();
+            label.set_text(text);
+            self.base_mut().add_child(&label);
+            label
+        };
+        let connection = add_label("Waiting for the game...");
+        let latency = add_label("");
+        let controller = add_label("");
+        let actions = add_label("");
+
+        self.labels = Some(MonitorLabels {
+            connection,
+            latency,
+            controller,
+            actions,
+        });
+    }
+
+    fn process(&mut self, _delta: f64) {
+        let Some(latest) = self.poller.as_ref().and_then(|poller| poller.poll()) else {
+            return;
+        };
+
+        match latest {
+            Some(body) => {
+                let status = Json::parse_string(body.as_str()).try_to::<Dictionary>().unwrap_or_default();
+                self.show_status(&status);
+            }
+            None => self.show_offline(),
+        }
+    }
+
+    fn exit_tree(&mut self) {
+        self.poller = None;
+    }
+}
+
+#[godot_api]
+impl FRCInterfaceMonitor {
+    /// Point the monitor at another port, e.g. after changing it in the dock.
+    #[func]
+    fn set_port(&mut self, port: i64) {
+        self.port = port;
+        if self.poller.is_some() {
+            self.start_polling();
+        }
+    }
+
+    fn start_polling(&mut self) {
+        self.poller = None;
+        match u16::try_from(self.port) {
+            Ok(port) if port != 0 => self.poller = Some(StatusPoller::start(port)),
+            _ => log_warn!("Invalid status server port for the monitor: {}", self.port),
+        }
+    }
+
+    fn show_offline(&mut self) {
+        let Some(labels) = self.labels.as_mut() else {
+            return;
+        };
+
+        labels.connection.set_text("Game not running (or status server disabled)");
+        labels.latency.set_text("");
+        labels.controller.set_text("");
+        labels.actions.set_text("");
+    }
+
+    fn show_status(&mut self, status: &Dictionary) {
+        let Some(labels) = self.labels.as_mut() else {
+            return;
+        };
+
+        let field = |dict: &Dictionary, key: &str| dict.get(key).unwrap_or_default();
+        let section = |key: &str| field(status, key).try_to::<Dictionary>().unwrap_or_default();
+        let ping = section("ping");
+        let latency = section("latency");
+        let controller = section("controller");
+
+        let connected = field(status, "connected").try_to::<bool>().unwrap_or(false);
+        let address = field(&ping, "address").to_string();
+        let mut connection = format!("{} to {}", if connected { "Connected" } else { "Disconnected" }, address);
+        let last_error = field(&ping, "last_error").to_string();
+        if !connected && !last_error.is_empty() {
+            connection.push_str(&format!("\n{}", last_error));
+        }
+        labels.connection.set_text(connection.as_str());
+
+        let latency_text = match field(&latency, "last_ms").try_to::<f64>() {
+            Ok(last) => format!(
+                "Latency {:.1} ms (avg {:.1} ms)",
+                last,
+                field(&latency, "avg_ms").try_to::<f64>().unwrap_or(0.0)
+            ),
+            Err(_) => "Latency unknown".into(),
+        };
+        labels.latency.set_text(latency_text.as_str());
+
+        let ready = field(&controller, "ready").try_to::<bool>().unwrap_or(false);
+        // JSON numbers all come back as floats
+        let bitmask = field(&controller, "bitmask").try_to::<f64>().unwrap_or(0.0) as i64;
+        let pressed = field(&controller, "pressed").try_to::<VariantArray>().unwrap_or_default();
+        let pressed: Vec<String> = pressed.iter_shared().map(|name| name.to_string()).collect();
+        labels.controller.set_text(
+            format!(
+                "Controller {} ({})\nButtons {:#06x} {}",
+                if ready { "ready" } else { "not ready" },
+                field(&controller, "output_mode"),
+                bitmask,
+                pressed.join(", ")
+            )
+            .as_str(),
+        );
+
+        // Newest first
+        let actions = field(status, "recent_actions").try_to::<VariantArray>().unwrap_or_default();
+        let actions: Vec<Dictionary> = actions
+            .iter_shared()
+            .filter_map(|action| action.try_to::<Dictionary>().ok())
+            .collect();
+        let lines: Vec<String> = actions
+            .iter()
+            .rev()
+            .map(|action| {
+                format!(
+                    "{:>5.1}s ago  {} {} ({})",
+                    field(action, "age_secs").try_to::<f64>().unwrap_or(0.0),
+                    field(action, "action"),
+                    if field(action, "pressed").try_to::<bool>().unwrap_or(false) { "pressed" } else { "released" },
+                    field(action, "origin")
+                )
+            })
+            .collect();
+        labels.actions.set_text(format!("Recent actions:\n{}", lines.join("\n")).as_str());
+    }
+}
+
+/// Fetches the status document on a background thread once a second.
+struct StatusPoller {
+    // Dropping this wakes the thread and stops it
+    stop: Option<mpsc::Sender<()>>,
+    results: mpsc::Receiver<Option<String>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl StatusPoller {
+    fn start(port: u16) -> Self {
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let (result_tx, result_rx) = mpsc::channel();
+
+        let thread = thread::spawn(move || loop {
+            if result_tx.send(fetch_status(port)).is_err() {
+                break;
+            }
+            match stop_rx.recv_timeout(REFRESH_INTERVAL) {
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                _ => break,
+            }
+        });
+
+        Self {
+            stop: Some(stop_tx),
+            results: result_rx,
+            thread: Some(thread),
+        }
+    }
+
+    /// The newest fetch since the last call, `Some(None)` if the game didn't answer.
+    fn poll(&self) -> Option<Option<String>> {
+        self.results.try_iter().last()
+    }
+}
+
+impl Drop for StatusPoller {
+    fn drop(&mut self) {
+        self.stop = None;
+        // A fetch in progress can take REQUEST_TIMEOUT, don't hold up the editor for it
+        if let Some(handle) = self.thread.take() {
+            join_within(handle, SHUTDOWN_TIMEOUT);
+        }
+    }
+}
+
+fn fetch_status(port: u16) -> Option<String> {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let mut stream = TcpStream::connect_timeout(&addr, REQUEST_TIMEOUT).ok()?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT)).ok()?;
+
+    stream.write_all(b"GET /status HTTP/1.0\r\nHost: localhost\r\n\r\n").ok()?;
+
+    let mut response = String::new();
+    stream.take(MAX_RESPONSE_LENGTH).read_to_string(&mut response).ok()?;
+
+    let (head, body) = response.split_once("\r\n\r\n")?;
+    head.starts_with("HTTP/1.1 200").then(|| body.to_string())
+}
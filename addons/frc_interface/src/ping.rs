@@ -0,0 +1,523 @@
+use crate::clock::{sample_http_date, ClockSample};
+use crate::shutdown::{join_by, SHUTDOWN_TIMEOUT};
+use socket2::{Domain, Protocol, SockRef, Socket, TcpKeepalive, Type};
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct PingRequest {
+    pub host: String,
+    pub port: u16,
+    /// Budget for the whole probe, name resolution included.
+    pub timeout: Duration,
+    /// Keep the connection open between probes instead of reconnecting.
+    pub persistent: bool,
+    /// Local address to bind to, so the probe leaves through a specific NIC.
+    pub local_address: Option<IpAddr>,
+    /// Extra check that whatever answered is really the robot.
+    pub verification: Option<Verification>,
+    /// Forget the cached address and any open connection first, e.g. after
+    /// the local network changed.
+    pub fresh: bool,
+    /// Also sample the robot's clock from its web server.
+    pub time_sync: bool,
+}
+
+/// How to confirm the target is a roboRIO, each holding the substring to look for.
+#[derive(Clone, Debug)]
+pub enum Verification {
+    /// Read the banner the SSH server sends on the probed port.
+    SshBanner(String),
+    /// GET / from the web server on port 80 of the same host.
+    Http(String),
+}
+
+// Largest HTTP response we read while verifying
+const MAX_VERIFY_RESPONSE: u64 = 64 * 1024;
+
+pub struct PingSuccess {
+    pub addr: SocketAddr,
+    /// Local end of the connection that answered.
+    pub local_addr: Option<SocketAddr>,
+    /// Connect time, `None` when an already open connection was checked.
+    pub latency: Option<Duration>,
+    /// Outcome of the verification step, `None` if it didn't run.
+    pub verification: Option<Result<(), String>>,
+    /// Clock sample, `None` if time sync wasn't asked for.
+    pub clock_sample: Option<Result<ClockSample, String>>,
+}
+
+// How often a wait on the resolver checks whether the probe was cancelled
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// Keepalive settings for the persistent connection
+const KEEPALIVE_TIME: Duration = Duration::from_secs(2);
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(1);
+
+struct OpenConnection {
+    host: String,
+    port: u16,
+    local_address: Option<IpAddr>,
+    addr: SocketAddr,
+    stream: TcpStream,
+}
+
+impl OpenConnection {
+    fn close(self) {
+        // Let the other side know right away instead of leaving a dangling session
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+#[derive(Default)]
+struct ProbeState {
+    // Last address that answered, reused until it stops answering
+    cached: Option<(String, u16, SocketAddr)>,
+    // Long-lived connection used in persistent mode
+    connection: Option<OpenConnection>,
+}
+
+pub enum PingFailure {
+    /// The host name couldn't be turned into any address.
+    Resolve(String),
+    /// Every resolved address refused or timed out; holds the last error.
+    /// Running out of time budget is reported as `TimedOut`.
+    Connect(SocketAddr, io::Error),
+}
+
+pub struct PingResult {
+    pub request: PingRequest,
+    pub outcome: Result<PingSuccess, PingFailure>,
+}
+
+/// Runs TCP probes on a background thread so name lookups and connect
+/// timeouts never stall a frame.
+pub struct PingWorker {
+    requests: Option<mpsc::Sender<PingRequest>>,
+    cancel: Arc<AtomicBool>,
+    results: mpsc::Receiver<PingResult>,
+    thread: Option<thread::JoinHandle<()>>,
+    in_flight: bool,
+}
+
+impl PingWorker {
+    pub fn new() -> Self {
+        let (request_tx, request_rx) = mpsc::channel::<PingRequest>();
+        let (result_tx, result_rx) = mpsc::channel::<PingResult>();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let thread_cancel = cancel.clone();
+        let thread = thread::spawn(move || {
+            let cancel = thread_cancel;
+            let mut state = ProbeState::default();
+
+            while let Ok(request) = request_rx.recv() {
+                if request.fresh {
+                    state.cached = None;
+                    if let Some(connection) = state.connection.take() {
+                        connection.close();
+                    }
+                }
+
+                let outcome = if request.persistent {
+                    probe_persistent(&request, &mut state, &cancel)
+                } else {
+                    // Switching modes drops any connection we were holding
+                    if let Some(connection) = state.connection.take() {
+                        connection.close();
+                    }
+                    probe(&request, &mut state.cached, &cancel).map(|(mut success, stream)| {
+                        if !cancel.load(Ordering::SeqCst) {
+                            success.verification = verify(&request, &success, &stream);
+                        }
+                        success
+                    })
+                };
+
+                let outcome = outcome.map(|mut success| {
+                    if request.time_sync && !cancel.load(Ordering::SeqCst) {
+                        success.clock_sample = Some(sample_http_date(
+                            &request.host,
+                            success.addr.ip(),
+                            request.local_address,
+                            request.timeout,
+                        ));
+                    }
+                    success
+                });
+
+                if result_tx.send(PingResult { request, outcome }).is_err() {
+                    break;
+                }
+            }
+
+            if let Some(connection) = state.connection.take() {
+                connection.close();
+            }
+        });
+
+        Self {
+            requests: Some(request_tx),
+            cancel,
+            results: result_rx,
+            thread: Some(thread),
+            in_flight: false,
+        }
+    }
+
+    /// Queue a probe. Returns false if one is still running.
+    pub fn request(&mut self, request: PingRequest) -> bool {
+        if self.in_flight {
+            return false;
+        }
+
+        match &self.requests {
+            Some(sender) if sender.send(request).is_ok() => {
+                self.in_flight = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_in_flight(&self) -> bool {
+        self.in_flight
+    }
+
+    /// Fetch the result of the last probe if it has finished.
+    pub fn poll(&mut self) -> Option<PingResult> {
+        match self.results.try_recv() {
+            Ok(result) => {
+                self.in_flight = false;
+                Some(result)
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Stop the worker, abandoning a probe in flight. Returns within about
+    /// SHUTDOWN_TIMEOUT whatever the network is doing.
+    pub fn shutdown(&mut self) {
+        self.shutdown_by(Instant::now() + SHUTDOWN_TIMEOUT);
+    }
+
+    /// Tell the worker to stop without waiting for it.
+    pub fn begin_shutdown(&mut self) {
+        // Dropping the sender ends the worker loop
+        self.requests = None;
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    /// Stop the worker, waiting for it until `deadline` at most.
+    pub fn shutdown_by(&mut self, deadline: Instant) {
+        self.begin_shutdown();
+
+        if let Some(handle) = self.thread.take() {
+            if !join_by(handle, deadline) {
+                log_debug!("Probe still in flight, leaving it to time out in the background");
+            }
+        }
+    }
+}
+
+impl Drop for PingWorker {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn probe_persistent(
+    request: &PingRequest,
+    state: &mut ProbeState,
+    cancel: &AtomicBool,
+) -> Result<PingSuccess, PingFailure> {
+    if let Some(connection) = state.connection.take() {
+        if connection.host == request.host
+            && connection.port == request.port
+            && connection.local_address == request.local_address
+        {
+            let addr = connection.addr;
+            return match check_alive(&connection.stream) {
+                Ok(()) => {
+                    let local_addr = connection.stream.local_addr().ok();
+                    state.connection = Some(connection);
+                    Ok(PingSuccess {
+                        addr,
+                        local_addr,
+                        latency: None,
+                        verification: None,
+                        clock_sample: None,
+                    })
+                }
+                Err(e) => {
+                    connection.close();
+                    Err(PingFailure::Connect(addr, e))
+                }
+            };
+        }
+
+        // Target changed, start over
+        connection.close();
+    }
+
+    let (mut success, stream) = probe(request, &mut state.cached, cancel)?;
+
+    // Only verified once per connection, the banner is sent right after connecting
+    success.verification = verify(request, &success, &stream);
+
+    let keepalive = TcpKeepalive::new()
+        .with_time(KEEPALIVE_TIME)
+        .with_interval(KEEPALIVE_INTERVAL);
+    if let Err(e) = SockRef::from(&stream).set_tcp_keepalive(&keepalive) {
+        let _ = stream.shutdown(Shutdown::Both);
+        return Err(PingFailure::Connect(success.addr, e));
+    }
+
+    state.connection = Some(OpenConnection {
+        host: request.host.clone(),
+        port: request.port,
+        local_address: request.local_address,
+        addr: success.addr,
+        stream,
+    });
+    Ok(success)
+}
+
+/// Check an open connection without blocking: discard whatever the server
+/// sent (e.g. the SSH banner) and report whether the socket was closed or
+/// errored, which includes keepalive failures.
+fn check_alive(stream: &TcpStream) -> io::Result<()> {
+    stream.set_nonblocking(true)?;
+
+    let mut reader = stream;
+    let mut buffer = [0u8; 512];
+    let result = loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break Err(io::Error::new(io::ErrorKind::ConnectionAborted, "connection closed by remote")),
+            Ok(_) => continue,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+
+    stream.set_nonblocking(false)?;
+    result
+}
+
+fn probe(
+    request: &PingRequest,
+    cached: &mut Option<(String, u16, SocketAddr)>,
+    cancel: &AtomicBool,
+) -> Result<(PingSuccess, TcpStream), PingFailure> {
+    let deadline = Instant::now() + request.timeout;
+
+    // Try the cached address first, drop it as soon as it fails
+    if let Some((host, port, addr)) = cached.clone() {
+        if host == request.host && port == request.port {
+            return match connect(addr, request.local_address, request.timeout) {
+                Ok(connected) => Ok(connected),
+                Err(e) => {
+                    *cached = None;
+                    Err(PingFailure::Connect(addr, e))
+                }
+            };
+        }
+        *cached = None;
+    }
+
+    let addrs = resolve(request, request.timeout, cancel)?;
+
+    let mut last_failure = PingFailure::Resolve(format!("{} did not resolve to any address", request.host));
+    for addr in addrs {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(PingFailure::Connect(addr, io::Error::from(io::ErrorKind::Interrupted)));
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(PingFailure::Connect(addr, io::Error::from(io::ErrorKind::TimedOut)));
+        }
+
+        match connect(addr, request.local_address, remaining) {
+            Ok(connected) => {
+                *cached = Some((request.host.clone(), request.port, addr));
+                return Ok(connected);
+            }
+            Err(e) => last_failure = PingFailure::Connect(addr, e),
+        }
+    }
+
+    Err(last_failure)
+}
+
+fn resolve(request: &PingRequest, timeout: Duration, cancel: &AtomicBool) -> Result<Vec<SocketAddr>, PingFailure> {
+    // IP literals never need a lookup
+    if let Ok(ip) = request.host.parse::<std::net::IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, request.port)]);
+    }
+
+    // The system resolver has no timeout of its own, so run it on a helper
+    // thread and stop waiting once the budget is spent or the probe is cancelled
+    let (tx, rx) = mpsc::channel();
+    let host = request.host.clone();
+    let port = request.port;
+    thread::spawn(move || {
+        // Handles DNS and mDNS names, returning IPv4 and IPv6 results
+        let result = (host.as_str(), port).to_socket_addrs().map(|addrs| addrs.collect::<Vec<_>>());
+        let _ = tx.send(result);
+    });
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(remaining.min(CANCEL_POLL_INTERVAL)) {
+            Ok(Ok(addrs)) => return Ok(addrs),
+            Ok(Err(e)) => return Err(PingFailure::Resolve(format!("could not resolve {}: {}", request.host, e))),
+            Err(mpsc::RecvTimeoutError::Timeout) if cancel.load(Ordering::SeqCst) => {
+                return Err(PingFailure::Resolve(format!("resolving {} was cancelled", request.host)));
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) if !remaining.is_zero() => continue,
+            Err(_) => {
+                return Err(PingFailure::Resolve(format!(
+                    "resolving {} took longer than {} ms",
+                    request.host,
+                    timeout.as_millis()
+                )));
+            }
+        }
+    }
+}
+
+fn connect(addr: SocketAddr, local: Option<IpAddr>, timeout: Duration) -> io::Result<(PingSuccess, TcpStream)> {
+    let started = Instant::now();
+    let stream = connect_tcp(addr, local, timeout)?;
+    let success = PingSuccess {
+        addr,
+        local_addr: stream.local_addr().ok(),
+        latency: Some(started.elapsed()),
+        verification: None,
+        clock_sample: None,
+    };
+    Ok((success, stream))
+}
+
+fn verify(request: &PingRequest, success: &PingSuccess, stream: &TcpStream) -> Option<Result<(), String>> {
+    let result = match request.verification.as_ref()? {
+        Verification::SshBanner(expected) => read_banner(stream, request.timeout)
+            .and_then(|banner| expect_contains(&banner, expected, "SSH banner")),
+        Verification::Http(expected) => {
+            fetch_http(&request.host, success.addr, request.local_address, request.timeout)
+                .and_then(|response| expect_contains(&response, expected, "HTTP response"))
+        }
+    };
+    Some(result)
+}
+
+/// Verify an address with a fresh connection, for callers without an open stream.
+pub fn verify_address(
+    addr: SocketAddr,
+    local: Option<IpAddr>,
+    timeout: Duration,
+    verification: &Verification,
+) -> Result<(), String> {
+    match verification {
+        Verification::SshBanner(expected) => {
+            let stream = connect_tcp(addr, local, timeout).map_err(|e| format!("could not connect to {}: {}", addr, e))?;
+            let banner = read_banner(&stream, timeout);
+            let _ = stream.shutdown(Shutdown::Both);
+            expect_contains(&banner?, expected, "SSH banner")
+        }
+        Verification::Http(expected) => fetch_http(&addr.ip().to_string(), addr, local, timeout)
+            .and_then(|response| expect_contains(&response, expected, "HTTP response")),
+    }
+}
+
+fn expect_contains(text: &str, expected: &str, what: &str) -> Result<(), String> {
+    if text.contains(expected) {
+        Ok(())
+    } else {
+        Err(format!("{} does not contain \"{}\"", what, expected))
+    }
+}
+
+fn read_banner(stream: &TcpStream, timeout: Duration) -> Result<String, String> {
+    stream
+        .set_read_timeout(Some(timeout))
+        .map_err(|e| format!("could not read SSH banner: {}", e))?;
+
+    let mut reader = stream;
+    let mut banner = Vec::new();
+    let mut buffer = [0u8; 256];
+    let result = loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(n) => {
+                banner.extend_from_slice(&buffer[..n]);
+                if banner.contains(&b'\n') || banner.len() >= 1024 {
+                    break Ok(());
+                }
+            }
+            Err(e) => break Err(format!("could not read SSH banner: {}", e)),
+        }
+    };
+
+    let _ = stream.set_read_timeout(None);
+    result?;
+
+    if banner.is_empty() {
+        return Err("no SSH banner received".into());
+    }
+    Ok(String::from_utf8_lossy(&banner).into_owned())
+}
+
+fn fetch_http(host: &str, addr: SocketAddr, local: Option<IpAddr>, timeout: Duration) -> Result<String, String> {
+    let http_addr = SocketAddr::new(addr.ip(), 80);
+    let mut stream = connect_tcp(http_addr, local, timeout)
+        .map_err(|e| format!("could not reach web server at {}: {}", http_addr, e))?;
+
+    let _ = stream.set_read_timeout(Some(timeout));
+    let _ = stream.set_write_timeout(Some(timeout));
+
+    let get = format!("GET / HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n", host);
+    stream
+        .write_all(get.as_bytes())
+        .map_err(|e| format!("could not send HTTP request: {}", e))?;
+
+    let mut response = Vec::new();
+    let read = (&mut stream).take(MAX_VERIFY_RESPONSE).read_to_end(&mut response);
+
+    // A timeout after part of the page arrived still gives us something to match
+    if let Err(e) = read {
+        if response.is_empty() {
+            return Err(format!("no HTTP response: {}", e));
+        }
+    }
+
+    let _ = stream.shutdown(Shutdown::Both);
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+/// Open a TCP connection, optionally bound to a local address first so the
+/// OS can't route it out of a different interface.
+pub fn connect_tcp(addr: SocketAddr, local: Option<IpAddr>, timeout: Duration) -> io::Result<TcpStream> {
+    let Some(local) = local else {
+        return TcpStream::connect_timeout(&addr, timeout);
+    };
+
+    if local.is_ipv4() != addr.is_ipv4() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrNotAvailable,
+            format!("local address {} can't reach {}", local, addr),
+        ));
+    }
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket
+        .bind(&SocketAddr::new(local, 0).into())
+        .map_err(|e| io::Error::new(e.kind(), format!("could not bind to local address {}: {}", local, e)))?;
+    socket.connect_timeout(&addr.into(), timeout)?;
+    Ok(socket.into())
+}
@@ -0,0 +1,272 @@
+use godot::classes::web_socket_peer::State;
+use godot::classes::{TcpServer, WebSocketPeer};
+use crate::authority::Heartbeat;
+use godot::prelude::*;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+/// Something a remote client asked for, to be run through the normal press pipeline.
+pub enum RemoteEvent {
+    Press { client: u32, button: String },
+    Release { client: u32, button: String },
+    /// The other driver station instance reporting in over its authority link.
+    Heartbeat(Heartbeat),
+    /// The other instance wants authority handed over to it.
+    AuthorityRequested,
+    /// A coach's answer to a confirmation request.
+    Confirm { client: u32, id: u64, accept: bool },
+}
+
+struct RemoteClient {
+    id: u32,
+    peer: Gd<WebSocketPeer>,
+    authenticated: bool,
+    needs_state: bool,
+    held: HashSet<String>,
+}
+
+/// WebSocket server for remote button presses.
+///
+/// Everything here is polled from the main thread in `process()`, so events can
+/// be fed straight into the same press/release path as the UI buttons.
+pub struct RemoteServer {
+    server: Option<Gd<TcpServer>>,
+    clients: Vec<RemoteClient>,
+    next_client_id: u32,
+    auth_token: String,
+    last_state: String,
+}
+
+impl RemoteServer {
+    pub fn new() -> Self {
+        Self {
+            server: None,
+            clients: Vec::new(),
+            next_client_id: 1,
+            auth_token: String::new(),
+            last_state: String::new(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.server.is_some()
+    }
+
+    pub fn start(&mut self, port: u16, auth_token: String) -> Result<(), String> {
+        if self.is_running() {
+            return Err("remote server is already running".into());
+        }
+
+        let mut server = TcpServer::new_gd();
+        let result = server.listen(port);
+        if result != godot::global::Error::OK {
+            return Err(format!("failed to listen on port {}: {:?}", port, result));
+        }
+
+        self.server = Some(server);
+        self.auth_token = auth_token;
+        Ok(())
+    }
+
+    /// Stop listening and drop every client.
+    /// Returns release events for anything the clients were still holding.
+    pub fn stop(&mut self) -> Vec<RemoteEvent> {
+        let mut events = Vec::new();
+
+        for mut client in self.clients.drain(..) {
+            client.peer.close();
+            events.extend(client.held.drain().map(|button| RemoteEvent::Release { client: client.id, button }));
+        }
+
+        if let Some(mut server) = self.server.take() {
+            server.stop();
+        }
+
+        events
+    }
+
+    /// Accept new clients, read their messages and clean up closed connections.
+    pub fn poll(&mut self) -> Vec<RemoteEvent> {
+        let mut events = Vec::new();
+
+        let Some(server) = self.server.as_mut() else {
+            return events;
+        };
+
+        // Accept any pending connections
+        while server.is_connection_available() {
+            if let Some(stream) = server.take_connection() {
+                let mut peer = WebSocketPeer::new_gd();
+                if peer.accept_stream(&stream) == godot::global::Error::OK {
+                    let id = self.next_client_id;
+                    self.next_client_id += 1;
+                    log_info!("Remote client {} connected", id);
+                    self.clients.push(RemoteClient {
+                        id,
+                        peer,
+                        // No token configured means no authentication step
+                        authenticated: self.auth_token.is_empty(),
+                        needs_state: true,
+                        held: HashSet::new(),
+                    });
+                }
+            }
+        }
+
+        // Read messages from every client
+        for client in self.clients.iter_mut() {
+            client.peer.poll();
+
+            if client.peer.get_ready_state() != State::OPEN {
+                continue;
+            }
+
+            while client.peer.get_available_packet_count() > 0 {
+                let packet = client.peer.get_packet();
+                let text = String::from_utf8_lossy(packet.as_slice()).into_owned();
+                handle_message(client, &text, &self.auth_token, &mut events);
+            }
+
+            // Bring newly authenticated clients up to date
+            if client.authenticated && client.needs_state && !self.last_state.is_empty() {
+                client.peer.send_text(self.last_state.as_str());
+                client.needs_state = false;
+            }
+        }
+
+        // Drop closed clients and release whatever they were holding
+        let mut index = 0;
+        while index < self.clients.len() {
+            if self.clients[index].peer.get_ready_state() == State::CLOSED {
+                let mut client = self.clients.remove(index);
+                log_info!("Remote client {} disconnected", client.id);
+                events.extend(client.held.drain().map(|button| RemoteEvent::Release { client: client.id, button }));
+            } else {
+                index += 1;
+            }
+        }
+
+        events
+    }
+
+    pub fn send_to(&mut self, client_id: u32, text: &str) {
+        if let Some(client) = self.clients.iter_mut().find(|c| c.id == client_id) {
+            if client.peer.get_ready_state() == State::OPEN {
+                client.peer.send_text(text);
+            }
+        }
+    }
+
+    pub fn authenticated_clients(&self) -> usize {
+        self.clients
+            .iter()
+            .filter(|client| client.authenticated && client.peer.get_ready_state() == State::OPEN)
+            .count()
+    }
+
+    /// Send a message to every authenticated client.
+    pub fn broadcast(&mut self, text: &str) {
+        for client in self.clients.iter_mut() {
+            if client.authenticated && client.peer.get_ready_state() == State::OPEN {
+                client.peer.send_text(text);
+            }
+        }
+    }
+
+    /// Send a state update to every authenticated client.
+    /// The latest state is also sent to clients as soon as they authenticate.
+    pub fn broadcast_state(&mut self, text: String) {
+        for client in self.clients.iter_mut() {
+            if client.authenticated && client.peer.get_ready_state() == State::OPEN {
+                client.peer.send_text(text.as_str());
+                client.needs_state = false;
+            }
+        }
+        self.last_state = text;
+    }
+}
+
+fn handle_message(client: &mut RemoteClient, text: &str, auth_token: &str, events: &mut Vec<RemoteEvent>) {
+    let message: Value = match serde_json::from_str(text) {
+        Ok(message) => message,
+        Err(e) => {
+            send_error(client, &format!("invalid JSON: {}", e));
+            return;
+        }
+    };
+
+    let cmd = message.get("cmd").and_then(Value::as_str).unwrap_or("");
+
+    if cmd == "auth" {
+        let token = message.get("token").and_then(Value::as_str).unwrap_or("");
+        if auth_token.is_empty() || token == auth_token {
+            client.authenticated = true;
+            send_json(client, json!({ "type": "auth", "ok": true }));
+        } else {
+            log_warn!("Remote client {} failed authentication", client.id);
+            send_json(client, json!({ "type": "auth", "ok": false }));
+            client.peer.close();
+        }
+        return;
+    }
+
+    if !client.authenticated {
+        send_error(client, "not authenticated");
+        return;
+    }
+
+    match cmd {
+        "heartbeat" => {
+            let id = message.get("id").and_then(Value::as_str).and_then(|id| id.parse().ok());
+            let priority = message.get("priority").and_then(Value::as_i64);
+            let active = message.get("active").and_then(Value::as_bool);
+            match (id, priority, active) {
+                (Some(id), Some(priority), Some(active)) => events.push(RemoteEvent::Heartbeat(Heartbeat { id, priority, active })),
+                _ => send_error(client, "malformed heartbeat"),
+            }
+            return;
+        }
+        "request_authority" => {
+            events.push(RemoteEvent::AuthorityRequested);
+            return;
+        }
+        "confirm" => {
+            let accept = message.get("accept").and_then(Value::as_bool).unwrap_or(false);
+            match message.get("id").and_then(Value::as_u64) {
+                Some(id) => events.push(RemoteEvent::Confirm { client: client.id, id, accept }),
+                None => send_error(client, "missing id"),
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let Some(button) = message.get("button").and_then(Value::as_str).map(String::from) else {
+        send_error(client, "missing button");
+        return;
+    };
+
+    match cmd {
+        // A client pressing again is still one hold, like key repeat
+        "press" => {
+            if client.held.insert(button.clone()) {
+                events.push(RemoteEvent::Press { client: client.id, button });
+            }
+        }
+        "release" => {
+            if client.held.remove(&button) {
+                events.push(RemoteEvent::Release { client: client.id, button });
+            }
+        }
+        _ => send_error(client, &format!("unknown command: {}", cmd)),
+    }
+}
+
+fn send_error(client: &mut RemoteClient, message: &str) {
+    send_json(client, json!({ "type": "error", "message": message }));
+}
+
+fn send_json(client: &mut RemoteClient, value: Value) {
+    let text = value.to_string();
+    client.peer.send_text(text.as_str());
+}
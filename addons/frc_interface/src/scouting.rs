@@ -0,0 +1,93 @@
+/// Merge `row` into the CSV text `existing`, replacing the row whose first
+/// `key_len` fields match it or appending it. A missing or empty file gets
+/// `header` first. Anything that doesn't look like a file this wrote before,
+/// with the same columns, is refused rather than touched.
+pub fn merge_row(existing: Option<&str>, header: &[String], key_len: usize, row: &[String]) -> Result<String, String> {
+    let mut records = Vec::new();
+    let existing = existing.unwrap_or_default();
+    if !existing.trim().is_empty() {
+        let mut lines = existing.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+
+        let Some((_, first)) = lines.next() else {
+            return Err("File is empty".into());
+        };
+        let found = parse_line(first).map_err(|e| format!("Line 1: {}", e))?;
+        if found != header {
+            return Err(format!(
+                "Columns don't match, expected {} and found {}. Export to a new file",
+                header.join(","),
+                found.join(",")
+            ));
+        }
+
+        for (index, line) in lines {
+            let record = parse_line(line).map_err(|e| format!("Line {}: {}", index + 1, e))?;
+            if record.len() != header.len() {
+                return Err(format!(
+                    "Line {} has {} fields, expected {}",
+                    index + 1,
+                    record.len(),
+                    header.len()
+                ));
+            }
+            records.push(record);
+        }
+    }
+
+    match records.iter_mut().find(|record| record[..key_len] == row[..key_len]) {
+        Some(record) => *record = row.to_vec(),
+        None => records.push(row.to_vec()),
+    }
+
+    let mut out = format_line(header);
+    for record in &records {
+        out.push_str(&format_line(record));
+    }
+    Ok(out)
+}
+
+fn format_line(fields: &[String]) -> String {
+    let fields: Vec<String> = fields.iter().map(|field| escape(field)).collect();
+    format!("{}\n", fields.join(","))
+}
+
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Split one CSV line, with quoted fields and doubled quotes inside them.
+fn parse_line(line: &str) -> Result<Vec<String>, String> {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => {
+                quoted = false;
+                if !matches!(chars.peek(), None | Some(',')) {
+                    return Err("Text after a closing quote".into());
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            '"' => return Err("Quote in the middle of a field".into()),
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("Unterminated quote".into());
+    }
+    fields.push(field);
+    Ok(fields)
+}
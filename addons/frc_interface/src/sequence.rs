@@ -0,0 +1,188 @@
+use crate::shutdown::{join_within, SHUTDOWN_TIMEOUT};
+use serde_json::Value;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// One step of a timeline.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Step {
+    Press(String),
+    Release(String),
+    /// Press, then release after the duration.
+    Tap(String, Duration),
+    Axis(String, f64),
+    Wait(Duration),
+}
+
+impl Step {
+    pub fn describe(&self) -> String {
+        match self {
+            Step::Press(action) => format!("press {}", action),
+            Step::Release(action) => format!("release {}", action),
+            Step::Tap(action, duration) => format!("tap {} for {} ms", action, duration.as_millis()),
+            Step::Axis(axis, value) => format!("{} to {:.2}", axis, value),
+            Step::Wait(duration) => format!("wait {} ms", duration.as_millis()),
+        }
+    }
+}
+
+/// A loaded timeline. Steps are objects with one of `press`, `release`,
+/// `tap` (plus `ms`), `axis` (plus `value`) or `wait` (ms), e.g.
+///
+/// ```json
+/// { "steps": [{ "press": "intake" }, { "wait": 1500 }, { "release": "intake" },
+///             { "tap": "coral", "ms": 200 }, { "axis": "left_y", "value": -0.5 }] }
+/// ```
+///
+/// A bare array of steps works too. An optional `label` replaces the
+/// generated description in sequence_step.
+#[derive(Clone)]
+pub struct Sequence {
+    pub steps: Vec<Step>,
+    pub labels: Vec<Option<String>>,
+}
+
+impl Sequence {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let root: Value = serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))?;
+        let steps = match &root {
+            Value::Array(steps) => steps,
+            Value::Object(object) => match object.get("steps") {
+                Some(Value::Array(steps)) => steps,
+                _ => return Err("Expected a \"steps\" array".into()),
+            },
+            _ => return Err("Expected an array of steps".into()),
+        };
+        if steps.is_empty() {
+            return Err("The sequence has no steps".into());
+        }
+
+        let mut sequence = Sequence {
+            steps: Vec::new(),
+            labels: Vec::new(),
+        };
+        for (index, step) in steps.iter().enumerate() {
+            let parsed = parse_step(step).map_err(|e| format!("Step {}: {}", index, e))?;
+            sequence.steps.push(parsed);
+            sequence.labels.push(step.get("label").and_then(Value::as_str).map(str::to_string));
+        }
+        Ok(sequence)
+    }
+
+    pub fn describe(&self, index: usize) -> String {
+        match self.labels.get(index) {
+            Some(Some(label)) => label.clone(),
+            _ => self.steps.get(index).map(Step::describe).unwrap_or_default(),
+        }
+    }
+}
+
+fn parse_step(step: &Value) -> Result<Step, String> {
+    let name = |key: &str| step.get(key).and_then(Value::as_str).map(str::to_string);
+    let ms = |key: &str| -> Result<Duration, String> {
+        match step.get(key).and_then(Value::as_f64) {
+            Some(ms) if ms >= 0.0 && ms.is_finite() => Ok(Duration::from_secs_f64(ms / 1000.0)),
+            _ => Err(format!("\"{}\" must be a number of milliseconds", key)),
+        }
+    };
+
+    if let Some(action) = name("press") {
+        Ok(Step::Press(action))
+    } else if let Some(action) = name("release") {
+        Ok(Step::Release(action))
+    } else if let Some(action) = name("tap") {
+        Ok(Step::Tap(action, ms("ms")?))
+    } else if let Some(axis) = name("axis") {
+        match step.get("value").and_then(Value::as_f64) {
+            Some(value) if value.is_finite() => Ok(Step::Axis(axis, value)),
+            _ => Err("\"value\" must be a number".into()),
+        }
+    } else if step.get("wait").is_some() {
+        Ok(Step::Wait(ms("wait")?))
+    } else {
+        Err("Expected press, release, tap, axis or wait".into())
+    }
+}
+
+/// What the worker asks the main thread to do, in order.
+pub enum SequenceEvent {
+    /// Step `index` is due.
+    Step(usize),
+    /// The tap in step `index` is over.
+    TapEnd(usize),
+    Finished,
+}
+
+/// Times a sequence on a worker thread. The worker only decides when things
+/// happen; the node applies each event from `process()`, so every press goes
+/// through the same checks as the operator's.
+pub struct SequenceRunner {
+    stop: Option<mpsc::Sender<()>>,
+    events: mpsc::Receiver<SequenceEvent>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl SequenceRunner {
+    pub fn start(steps: Vec<Step>) -> Self {
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let (event_tx, event_rx) = mpsc::channel::<SequenceEvent>();
+
+        let thread = thread::spawn(move || run(steps, stop_rx, event_tx));
+
+        Self {
+            stop: Some(stop_tx),
+            events: event_rx,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn poll(&self) -> Vec<SequenceEvent> {
+        self.events.try_iter().collect()
+    }
+
+    pub fn stop(&mut self) {
+        // Dropping the sender wakes the worker out of any wait
+        self.stop = None;
+
+        if let Some(handle) = self.thread.take() {
+            join_within(handle, SHUTDOWN_TIMEOUT);
+        }
+    }
+}
+
+impl Drop for SequenceRunner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run(steps: Vec<Step>, stop: mpsc::Receiver<()>, events: mpsc::Sender<SequenceEvent>) {
+    // Deadlines run off the start, so time spent sending doesn't add up
+    let mut due = Instant::now();
+    let wait_until = |deadline: Instant| -> bool {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        matches!(stop.recv_timeout(timeout), Err(mpsc::RecvTimeoutError::Timeout))
+    };
+
+    for (index, step) in steps.iter().enumerate() {
+        if events.send(SequenceEvent::Step(index)).is_err() {
+            return;
+        }
+        match step {
+            Step::Wait(duration) => due += *duration,
+            Step::Tap(_, duration) => {
+                due += *duration;
+                if !wait_until(due) || events.send(SequenceEvent::TapEnd(index)).is_err() {
+                    return;
+                }
+                continue;
+            }
+            _ => continue,
+        }
+        if !wait_until(due) {
+            return;
+        }
+    }
+    let _ = events.send(SequenceEvent::Finished);
+}
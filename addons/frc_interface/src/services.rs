@@ -0,0 +1,137 @@
+use crate::ping::connect_tcp;
+use crate::shutdown::{join_by, SHUTDOWN_TIMEOUT};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct ServiceCheck {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub local_address: Option<IpAddr>,
+    /// A separate device (coprocessor, camera) rather than a service on the robot.
+    pub device: bool,
+}
+
+pub struct ServiceResult {
+    pub name: String,
+    pub device: bool,
+    /// Connect time if the service answered.
+    pub latency: Option<Duration>,
+}
+
+/// Checks a set of robot services and devices in parallel on a background thread.
+pub struct ServiceMonitor {
+    requests: Option<mpsc::Sender<(Vec<ServiceCheck>, Duration)>>,
+    results: mpsc::Receiver<Vec<ServiceResult>>,
+    thread: Option<thread::JoinHandle<()>>,
+    in_flight: bool,
+}
+
+impl ServiceMonitor {
+    pub fn new() -> Self {
+        let (request_tx, request_rx) = mpsc::channel::<(Vec<ServiceCheck>, Duration)>();
+        let (result_tx, result_rx) = mpsc::channel::<Vec<ServiceResult>>();
+
+        let thread = thread::spawn(move || {
+            while let Ok((checks, timeout)) = request_rx.recv() {
+                // One thread per service so the round takes as long as the slowest check
+                let results = thread::scope(|scope| {
+                    let handles: Vec<_> = checks
+                        .iter()
+                        .map(|check| scope.spawn(move || check_service(check, timeout)))
+                        .collect();
+
+                    handles
+                        .into_iter()
+                        .zip(checks.iter())
+                        .map(|(handle, check)| ServiceResult {
+                            name: check.name.clone(),
+                            device: check.device,
+                            latency: handle.join().unwrap_or(None),
+                        })
+                        .collect::<Vec<_>>()
+                });
+
+                if result_tx.send(results).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            requests: Some(request_tx),
+            results: result_rx,
+            thread: Some(thread),
+            in_flight: false,
+        }
+    }
+
+    /// Queue a round of checks. Returns false if the previous round is still running.
+    pub fn request(&mut self, checks: Vec<ServiceCheck>, timeout: Duration) -> bool {
+        if self.in_flight || checks.is_empty() {
+            return false;
+        }
+
+        match &self.requests {
+            Some(sender) if sender.send((checks, timeout)).is_ok() => {
+                self.in_flight = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn poll(&mut self) -> Option<Vec<ServiceResult>> {
+        match self.results.try_recv() {
+            Ok(results) => {
+                self.in_flight = false;
+                Some(results)
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Stop the monitor without waiting out a round in flight for more than
+    /// SHUTDOWN_TIMEOUT.
+    pub fn shutdown(&mut self) {
+        self.shutdown_by(Instant::now() + SHUTDOWN_TIMEOUT);
+    }
+
+    /// Tell the monitor to stop without waiting for it.
+    pub fn begin_shutdown(&mut self) {
+        self.requests = None;
+    }
+
+    /// Stop the monitor, waiting for it until `deadline` at most.
+    pub fn shutdown_by(&mut self, deadline: Instant) {
+        self.begin_shutdown();
+
+        if let Some(handle) = self.thread.take() {
+            if !join_by(handle, deadline) {
+                log_debug!("Service checks still in flight, leaving them to time out in the background");
+            }
+        }
+    }
+}
+
+impl Drop for ServiceMonitor {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn check_service(check: &ServiceCheck, timeout: Duration) -> Option<Duration> {
+    let addrs: Vec<SocketAddr> = (check.host.as_str(), check.port).to_socket_addrs().ok()?.collect();
+
+    for addr in addrs {
+        let started = Instant::now();
+        if connect_tcp(addr, check.local_address, timeout).is_ok() {
+            return Some(started.elapsed());
+        }
+    }
+
+    None
+}
@@ -0,0 +1,51 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest a stop or drop waits for a background thread that is still busy,
+/// e.g. in a connect or name lookup that can't be interrupted. A thread still
+/// running after this is left to finish on its own; nothing waits on it.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Join `handle` if the thread finishes within `timeout`, otherwise let it
+/// run on detached. Returns whether it finished.
+pub fn join_within<T>(handle: thread::JoinHandle<T>, timeout: Duration) -> bool {
+    join_by(handle, Instant::now() + timeout)
+}
+
+/// Join `handle` if the thread finishes by `deadline`, otherwise let it run on
+/// detached. Several threads told to stop together share one deadline, so
+/// stopping them all takes one SHUTDOWN_TIMEOUT rather than one each.
+pub fn join_by<T>(handle: thread::JoinHandle<T>, deadline: Instant) -> bool {
+    handle.thread().unpark();
+    while !handle.is_finished() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    let _ = handle.join();
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stuck_threads_share_one_deadline() {
+        let stuck: Vec<_> = (0..3).map(|_| thread::spawn(|| thread::sleep(Duration::from_secs(2)))).collect();
+
+        let started = Instant::now();
+        let deadline = started + SHUTDOWN_TIMEOUT;
+        for handle in stuck {
+            assert!(!join_by(handle, deadline));
+        }
+        assert!(started.elapsed() < SHUTDOWN_TIMEOUT * 2, "each thread waited out its own timeout");
+    }
+
+    #[test]
+    fn finished_thread_is_joined() {
+        let handle = thread::spawn(|| {});
+        assert!(join_by(handle, Instant::now() + SHUTDOWN_TIMEOUT));
+    }
+}
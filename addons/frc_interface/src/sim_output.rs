@@ -0,0 +1,183 @@
+use crate::endpoint::format_endpoint;
+use crate::virtual_controller::{AXIS_COUNT, BUTTON_MAPPING};
+use godot::classes::web_socket_peer::State;
+use godot::classes::WebSocketPeer;
+use godot::prelude::*;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+// Buttons a simulated Xbox controller reports, A through right stick
+const SIM_BUTTON_COUNT: usize = 10;
+
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Where an Xbox button shows up on a WPILib joystick.
+enum SimInput {
+    /// 1-based button number, as robot code sees it.
+    Button(usize),
+    /// POV angle in degrees.
+    Pov(i32),
+}
+
+fn sim_input(xbox_button: &str) -> Option<SimInput> {
+    match xbox_button {
+        "A" => Some(SimInput::Button(1)),
+        "B" => Some(SimInput::Button(2)),
+        "X" => Some(SimInput::Button(3)),
+        "Y" => Some(SimInput::Button(4)),
+        "LB" => Some(SimInput::Button(5)),
+        "RB" => Some(SimInput::Button(6)),
+        "BACK" => Some(SimInput::Button(7)),
+        "START" => Some(SimInput::Button(8)),
+        "LS" => Some(SimInput::Button(9)),
+        "RS" => Some(SimInput::Button(10)),
+        "DPAD_UP" => Some(SimInput::Pov(0)),
+        "DPAD_RIGHT" => Some(SimInput::Pov(90)),
+        "DPAD_DOWN" => Some(SimInput::Pov(180)),
+        "DPAD_LEFT" => Some(SimInput::Pov(270)),
+        _ => None,
+    }
+}
+
+/// Publishes the virtual controller as a joystick over the WPILib simulation
+/// WebSocket protocol (halsim_ws), so sim robot code reads it natively.
+///
+/// Polled from the main thread like the remote server.
+pub struct SimJoystickOutput {
+    url: String,
+    device: String,
+    peer: Option<Gd<WebSocketPeer>>,
+    open: bool,
+    next_attempt: Instant,
+    reconnect_delay: Duration,
+    last_sent: Option<(Vec<&'static str>, Vec<&'static str>, [f64; AXIS_COUNT])>,
+}
+
+impl SimJoystickOutput {
+    pub fn new(host: &str, port: u16, joystick: u8) -> Self {
+        Self {
+            url: format!("ws://{}/wpilibws", format_endpoint(host, port.into())),
+            device: joystick.to_string(),
+            peer: None,
+            open: false,
+            next_attempt: Instant::now(),
+            reconnect_delay: MIN_RECONNECT_DELAY,
+            last_sent: None,
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.open
+    }
+
+    /// Keep the socket alive and send the state whenever it changes.
+    /// `spare` are held SPARE_BUTTONS, by Xbox button name.
+    pub fn poll(&mut self, pressed: &[&'static str], spare: &[&'static str], axes: [f64; AXIS_COUNT]) {
+        if self.peer.is_none() && Instant::now() >= self.next_attempt {
+            self.connect();
+        }
+
+        let Some(peer) = self.peer.as_mut() else {
+            return;
+        };
+
+        peer.poll();
+        match peer.get_ready_state() {
+            State::OPEN => {
+                if !self.open {
+                    log_info!("Connected to simulation at {}", self.url);
+                    self.open = true;
+                    self.reconnect_delay = MIN_RECONNECT_DELAY;
+                    self.last_sent = None;
+                }
+
+                // Sim traffic for other devices isn't needed
+                while peer.get_available_packet_count() > 0 {
+                    peer.get_packet();
+                }
+
+                let changed = match &self.last_sent {
+                    Some((last_pressed, last_spare, last_axes)) => {
+                        last_pressed.as_slice() != pressed || last_spare.as_slice() != spare || *last_axes != axes
+                    }
+                    None => true,
+                };
+                if changed {
+                    let message = joystick_message(&self.device, pressed, spare, axes).to_string();
+                    peer.send_text(message.as_str());
+                    self.last_sent = Some((pressed.to_vec(), spare.to_vec(), axes));
+                }
+            }
+            State::CLOSED => {
+                if self.open {
+                    log_warn!("Lost connection to simulation at {}, reconnecting", self.url);
+                }
+                self.open = false;
+                self.peer = None;
+                self.schedule_reconnect();
+            }
+            _ => {}
+        }
+    }
+
+    /// Send a neutral joystick and close the socket.
+    pub fn close(&mut self) {
+        if let Some(mut peer) = self.peer.take() {
+            if self.open {
+                let message = joystick_message(&self.device, &[], &[], [0.0; AXIS_COUNT]).to_string();
+                peer.send_text(message.as_str());
+            }
+            peer.close();
+        }
+        self.open = false;
+    }
+
+    fn connect(&mut self) {
+        let mut peer = WebSocketPeer::new_gd();
+        if peer.connect_to_url(self.url.as_str()) == godot::global::Error::OK {
+            self.peer = Some(peer);
+        } else {
+            self.schedule_reconnect();
+        }
+    }
+
+    fn schedule_reconnect(&mut self) {
+        self.next_attempt = Instant::now() + self.reconnect_delay;
+        self.reconnect_delay = (self.reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+impl Drop for SimJoystickOutput {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn joystick_message(device: &str, pressed: &[&str], spare: &[&str], axes: [f64; AXIS_COUNT]) -> serde_json::Value {
+    let mut buttons = [false; SIM_BUTTON_COUNT];
+    let mut pov = -1;
+
+    let held = BUTTON_MAPPING
+        .iter()
+        .filter(|(name, _)| pressed.contains(name))
+        .map(|(_, xbox_button)| *xbox_button)
+        .chain(spare.iter().copied());
+    for xbox_button in held {
+        match sim_input(xbox_button) {
+            Some(SimInput::Button(number)) => buttons[number - 1] = true,
+            Some(SimInput::Pov(angle)) => pov = angle,
+            None => {}
+        }
+    }
+
+    json!({
+        "type": "Joystick",
+        "device": device,
+        "data": {
+            ">buttons": buttons,
+            ">axes": axes,
+            ">povs": [pov],
+        },
+    })
+}
@@ -0,0 +1,181 @@
+use crate::shutdown::{join_by, SHUTDOWN_TIMEOUT};
+use godot::prelude::*;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Port the status endpoint listens on unless status_server_port says
+/// otherwise, and the one the editor dock polls by default.
+pub const DEFAULT_PORT: i64 = 5800;
+
+// Upper bound on requests being served at the same time
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+
+// How long a client gets to send its request line before we give up on it
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Tiny HTTP server that serves a JSON snapshot of the interface state.
+///
+/// The main thread pushes a pre-rendered JSON document with `update()`; the
+/// worker threads only ever read that string, so no Godot object is touched
+/// off the main thread.
+pub struct StatusServer {
+    running: Arc<AtomicBool>,
+    snapshot: Arc<Mutex<String>>,
+    listener_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl StatusServer {
+    pub fn new() -> Self {
+        Self {
+            running: Arc::new(AtomicBool::new(false)),
+            snapshot: Arc::new(Mutex::new(String::from("{}"))),
+            listener_thread: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn start(&mut self, port: u16) -> Result<(), String> {
+        if self.is_running() || self.listener_thread.is_some() {
+            return Err("status server is already running".into());
+        }
+
+        let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port)))
+            .map_err(|e| format!("failed to bind status server to port {}: {}", port, e))?;
+
+        // Non-blocking accept so the thread can notice shutdown requests
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("failed to configure status server socket: {}", e))?;
+
+        self.running.store(true, Ordering::SeqCst);
+        let running = self.running.clone();
+        let snapshot = self.snapshot.clone();
+
+        self.listener_thread = Some(thread::spawn(move || {
+            let active = Arc::new(AtomicUsize::new(0));
+
+            while running.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if active.load(Ordering::SeqCst) >= MAX_CONCURRENT_REQUESTS {
+                            let _ = write_response(stream, "503 Service Unavailable", "{\"error\":\"busy\"}");
+                            continue;
+                        }
+
+                        active.fetch_add(1, Ordering::SeqCst);
+                        let active = active.clone();
+                        let snapshot = snapshot.clone();
+
+                        thread::spawn(move || {
+                            handle_connection(stream, &snapshot);
+                            active.fetch_sub(1, Ordering::SeqCst);
+                        });
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(50));
+                    }
+                    Err(e) => {
+                        log_warn!("Status server failed to accept connection: {}", e);
+                        thread::sleep(Duration::from_millis(50));
+                    }
+                }
+            }
+        }));
+
+        Ok(())
+    }
+
+    /// Replace the document served to clients.
+    pub fn update(&self, json: String) {
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            *snapshot = json;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_by(Instant::now() + SHUTDOWN_TIMEOUT);
+    }
+
+    /// Tell the listener to stop without waiting for it.
+    pub fn begin_stop(&self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    /// Stop the listener, waiting for it until `deadline` at most.
+    pub fn stop_by(&mut self, deadline: Instant) {
+        self.begin_stop();
+
+        if let Some(handle) = self.listener_thread.take() {
+            join_by(handle, deadline);
+        }
+    }
+}
+
+impl Drop for StatusServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn handle_connection(stream: TcpStream, snapshot: &Mutex<String>) {
+    // Accepted sockets may inherit the listener's non-blocking mode
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(REQUEST_TIMEOUT));
+
+    let mut reader = match stream.try_clone() {
+        Ok(s) => BufReader::new(s),
+        Err(_) => return,
+    };
+
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+
+    // Drain the headers, we don't care about any of them
+    let mut header = String::new();
+    loop {
+        header.clear();
+        match reader.read_line(&mut header) {
+            Ok(0) => break,
+            Ok(_) if header.trim().is_empty() => break,
+            Ok(_) => continue,
+            Err(_) => return,
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("");
+
+    let result = if method != "GET" {
+        write_response(stream, "405 Method Not Allowed", "{\"error\":\"method not allowed\"}")
+    } else if path == "/" || path == "/status" {
+        let body = snapshot.lock().map(|s| s.clone()).unwrap_or_else(|_| String::from("{}"));
+        write_response(stream, "200 OK", &body)
+    } else {
+        write_response(stream, "404 Not Found", "{\"error\":\"not found\"}")
+    };
+
+    if let Err(e) = result {
+        log_warn!("Status server failed to write response: {}", e);
+    }
+}
+
+fn write_response(mut stream: TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
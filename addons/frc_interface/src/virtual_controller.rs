@@ -1,29 +1,280 @@
+use crate::shutdown::join_within;
 use godot::prelude::*;
 use vigem_client::XButtons;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
 use std::thread;
 use std::time::Duration;
-use std::sync::atomic::Ordering; // Import Ordering directly
+use std::sync::atomic::{AtomicU16, AtomicU32, AtomicU64, AtomicU8, Ordering};
+
+/// Which Xbox button each named action is sent as.
+pub const BUTTON_MAPPING: [(&str, &str); 9] = [
+    ("climb", "START"),
+    ("zero", "BACK"),
+    ("intake", "DPAD_RIGHT"),
+    ("high", "DPAD_UP"),
+    ("mid", "DPAD_LEFT"),
+    ("low", "DPAD_DOWN"),
+    ("coral", "B"),
+    ("intake_alga", "LB"),
+    ("drop_alga", "RB"),
+];
+
+/// Xbox buttons no action is sent as, free for other signals to the robot.
+pub const SPARE_BUTTONS: [&str; 5] = ["A", "X", "Y", "LS", "RS"];
+
+/// Analog axes in the order WPILib reads an Xbox controller. Sticks run
+/// -1 to 1 with up negative, as robot code sees them; triggers run 0 to 1.
+pub const AXIS_NAMES: [&str; AXIS_COUNT] = ["left_x", "left_y", "left_trigger", "right_trigger", "right_x", "right_y"];
+pub const AXIS_COUNT: usize = 6;
+
+/// Longest shutdown() waits for the control thread to finish. A thread still
+/// stuck after this (e.g. inside a ViGEm call) is left behind so closing the
+/// scene or the editor can't hang on it.
+pub const SHUTDOWN_JOIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Why the pad didn't take an input.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputError {
+    UnknownAxis,
+    /// shutdown() has started, nothing more goes out.
+    ShuttingDown,
+}
+
+/// Where a controller is in its life, shared with the control thread.
+/// Inputs are kept while Idle (e.g. between restart attempts) so they go
+/// out once the pad is back. From the moment shutdown() starts they are
+/// refused; the thread finishes its last report and exits, and only then
+/// is the target dropped.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+enum Lifecycle {
+    Idle,
+    Running,
+    ShuttingDown,
+    ShutDown,
+}
+
+impl Lifecycle {
+    fn load(state: &AtomicU8) -> Self {
+        match state.load(Ordering::SeqCst) {
+            0 => Lifecycle::Idle,
+            1 => Lifecycle::Running,
+            2 => Lifecycle::ShuttingDown,
+            _ => Lifecycle::ShutDown,
+        }
+    }
+
+    fn takes_input(self) -> bool {
+        matches!(self, Lifecycle::Idle | Lifecycle::Running)
+    }
+}
+
+// The ViGEm pad every node in the process uses, alive while any handle is
+static SHARED_VIGEM: Mutex<Weak<Mutex<VirtualController>>> = Mutex::new(Weak::new());
+
+/// Handle to a virtual controller. ViGEm handles all point at one pad so
+/// the Driver Station sees a single joystick no matter how many scenes use
+/// it; the pad is unplugged when the last handle is dropped. Clones share
+/// one node's part of the pad: a button stays down while any node holds it,
+/// and a node's neutralize, or dropping its last clone, only lets go of
+/// what that node holds.
+#[derive(Clone)]
+pub struct SharedController(Arc<Handle>);
+
+// One node's hold on a controller, let go of when its last clone drops
+struct Handle {
+    controller: Arc<Mutex<VirtualController>>,
+    owned: Arc<Owned>,
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        lock_recovering(&*self.controller, "virtual controller").release_owned(&self.owned);
+    }
+}
+
+/// The XInput bits of the buttons one node holds, and a bit per axis it has
+/// moved off zero.
+#[derive(Default)]
+struct Owned {
+    buttons: AtomicU16,
+    axes: AtomicU8,
+}
+
+impl SharedController {
+    /// Join the process-wide ViGEm pad, plugging it in if nobody has yet.
+    /// While other nodes still hold the pad it's the same one, plugged in
+    /// again if it stopped, so the Driver Station never sees a second joystick.
+    pub fn vigem() -> Result<Self, String> {
+        let mut shared = lock_recovering(&SHARED_VIGEM, "shared pad handle");
+        if let Some(controller) = shared.upgrade() {
+            let handle = Self::join(controller);
+            if !handle.is_running() {
+                handle.restart()?;
+            }
+            return Ok(handle);
+        }
+
+        let mut controller = VirtualController::new();
+        controller.initialize()?;
+
+        let controller = Arc::new(Mutex::new(controller));
+        *shared = Arc::downgrade(&controller);
+        Ok(Self::join(controller))
+    }
+
+    /// A controller of this node's own that only tracks button state, for
+    /// outputs that read the state themselves (e.g. the simulation backend).
+    pub fn state_only() -> Self {
+        let mut controller = VirtualController::new();
+        controller.initialize_state_only();
+        Self::join(Arc::new(Mutex::new(controller)))
+    }
+
+    /// A new node's handle on `controller`, holding nothing yet.
+    fn join(controller: Arc<Mutex<VirtualController>>) -> Self {
+        let owned = Arc::new(Owned::default());
+        {
+            let mut controller = lock_recovering(&*controller, "virtual controller");
+            controller.owners.retain(|owner| owner.strong_count() > 0);
+            controller.owners.push(Arc::downgrade(&owned));
+        }
+        Self(Arc::new(Handle { controller, owned }))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VirtualController> {
+        lock_recovering(&*self.0.controller, "virtual controller")
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.lock().is_running()
+    }
+
+    pub fn thread_alive(&self) -> bool {
+        self.lock().thread_alive()
+    }
+
+    pub fn restart(&self) -> Result<(), String> {
+        self.lock().restart()
+    }
+
+    pub fn pressed_buttons(&self) -> Vec<&'static str> {
+        self.lock().pressed_buttons()
+    }
+
+    /// Let go of every button and axis this node holds, leaving what other
+    /// nodes hold down, and send a report even if nothing changed.
+    pub fn neutralize(&self) {
+        self.lock().release_owned(&self.0.owned);
+    }
+
+    pub fn set_button(&self, button: &str, pressed: bool) -> Result<(), InputError> {
+        self.set_buttons(&[(button, pressed)])
+    }
+
+    /// Change several buttons in one atomic update, so they go out in the
+    /// same report.
+    pub fn set_buttons(&self, changes: &[(&str, bool)]) -> Result<(), InputError> {
+        let controller = self.lock();
+        controller.check_input()?;
+        let (clear, set) = button_changes(changes);
+        controller.update_owned_buttons(&self.0.owned, clear, set);
+        Ok(())
+    }
+
+    pub fn spare_buttons(&self) -> Vec<&'static str> {
+        self.lock().spare_buttons()
+    }
+
+    /// Hold exactly `buttons` of the SPARE_BUTTONS, replacing the previous set
+    /// in one report. Others are ignored.
+    pub fn set_spare_buttons(&self, buttons: &[&str]) -> Result<(), InputError> {
+        let controller = self.lock();
+        controller.check_input()?;
+        controller.update_owned_buttons(&self.0.owned, spare_bits(&SPARE_BUTTONS), spare_bits(buttons));
+        Ok(())
+    }
+
+    pub fn batch(&self) -> Batch {
+        self.lock().batch()
+    }
+
+    pub fn axes(&self) -> [f64; AXIS_COUNT] {
+        self.lock().axes()
+    }
+
+    pub fn set_axis(&self, axis: &str, value: f64) -> Result<(), InputError> {
+        let controller = self.lock();
+        controller.set_axis(axis, value)?;
+        if let Some(index) = AXIS_NAMES.iter().position(|name| *name == axis) {
+            let bit = 1 << index;
+            let _ = self.0.owned.axes.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |axes| {
+                Some(if value == 0.0 { axes & !bit } else { axes | bit })
+            });
+        }
+        Ok(())
+    }
+
+    pub fn user_index(&self) -> Option<u32> {
+        self.lock().user_index
+    }
+}
 
 pub struct VirtualController {
     client: Option<vigem_client::Client>,
     target: Option<Arc<Mutex<vigem_client::XTarget>>>,
     control_thread: Option<thread::JoinHandle<()>>,
-    running: Arc<std::sync::atomic::AtomicBool>,
-    button_state: Arc<Mutex<ButtonState>>,
+    lifecycle: Arc<AtomicU8>,
+    pad: Arc<PadState>,
+    force_update: Arc<std::sync::atomic::AtomicBool>,
+    // XInput slot Windows gave the pad, which the Driver Station lists it under
+    user_index: Option<u32>,
+    // What each node's handle holds, see SharedController
+    owners: Vec<Weak<Owned>>,
 }
 
+/// What the pad reports, shared with the control thread without a lock:
+/// the XInput bits of every button held (actions and spares alike) and each
+/// axis as f64 bits. Every change is a single atomic write.
 #[derive(Default)]
-struct ButtonState {
-    climb: bool,
-    zero: bool,
-    intake: bool,
-    high: bool,
-    mid: bool,
-    low: bool,
-    coral: bool,
-    intake_alga: bool,
-    drop_alga: bool,
+struct PadState {
+    buttons: AtomicU16,
+    axes: [AtomicU64; AXIS_COUNT],
+    // Live Batch guards, nothing is sent while there are any
+    batches: AtomicU32,
+    // Bumped whenever a batch ends, so a read that a batch overlapped is retried
+    commits: AtomicU32,
+}
+
+impl PadState {
+    fn buttons(&self) -> u16 {
+        self.buttons.load(Ordering::SeqCst)
+    }
+
+    /// Clear the `clear` bits and set the `set` bits in one step.
+    fn update_buttons(&self, clear: u16, set: u16) {
+        let _ = self
+            .buttons
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |bits| Some((bits & !clear) | set));
+    }
+
+    fn axes(&self) -> [f64; AXIS_COUNT] {
+        std::array::from_fn(|index| f64::from_bits(self.axes[index].load(Ordering::SeqCst)))
+    }
+
+    /// Buttons and axes as one state that a caller meant to send, or `None`
+    /// while a batch is open or if one was committed during the read.
+    fn snapshot(&self) -> Option<(u16, [f64; AXIS_COUNT])> {
+        let commits = self.commits.load(Ordering::SeqCst);
+        if self.batches.load(Ordering::SeqCst) != 0 {
+            return None;
+        }
+        let state = (self.buttons(), self.axes());
+        if self.batches.load(Ordering::SeqCst) != 0 || self.commits.load(Ordering::SeqCst) != commits {
+            return None;
+        }
+        Some(state)
+    }
 }
 
 impl VirtualController {
@@ -32,12 +283,19 @@ impl VirtualController {
             client: None,
             target: None,
             control_thread: None,
-            running: Arc::new(std::sync::atomic::AtomicBool::new(false)),
-            button_state: Arc::new(Mutex::new(ButtonState::default())),
+            lifecycle: Arc::new(AtomicU8::new(Lifecycle::Idle as u8)),
+            pad: Arc::new(PadState::default()),
+            force_update: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            user_index: None,
+            owners: Vec::new(),
         }
     }
 
-    pub fn initialize(&mut self) -> bool {
+    /// Connect to ViGEm and plug in the controller. Safe to call again after
+    /// a failure, anything left from an earlier attempt is torn down first.
+    pub fn initialize(&mut self) -> Result<(), String> {
+        self.stop_thread(Lifecycle::Idle);
+
         // Try to connect to the ViGEm client
         match vigem_client::Client::connect() {
             Ok(client) => {
@@ -46,152 +304,622 @@ impl VirtualController {
                 
                 // Plugin the virtual controller
                 if let Err(e) = target.plugin() {
-                    godot_error!("Failed to plugin virtual controller: {}", e);
-                    return false;
+                    return Err(format!("Failed to plugin virtual controller: {}", e));
                 }
                 
                 // Wait for the controller to be ready
+                // Dropping the target unplugs it again
                 if let Err(e) = target.wait_ready() {
-                    godot_error!("Failed to wait for virtual controller ready: {}", e);
-                    return false;
+                    return Err(format!("Failed to wait for virtual controller ready: {}", e));
                 }
                 
+                self.user_index = target.get_user_index().ok();
+
                 // Store the client and target
                 let target = Arc::new(Mutex::new(target));
                 self.target = Some(target.clone());
                 
                 // Start the control thread
-                self.running.store(true, Ordering::SeqCst); // Fixed ordering
-                let running = self.running.clone();
-                let button_state = self.button_state.clone();
+                self.set_lifecycle(Lifecycle::Running);
+                let lifecycle = self.lifecycle.clone();
+                let pad = self.pad.clone();
+                let force_update = self.force_update.clone();
                 
                 self.control_thread = Some(thread::spawn(move || {
-                    let mut last_state = ButtonState::default();
+                    let mut last_buttons = 0u16;
+                    let mut last_axes = [0.0; AXIS_COUNT];
                     
-                    while running.load(Ordering::SeqCst) { // Fixed ordering
-                        // Lock the button state
-                        let current_state = {
-                            let guard = button_state.lock().unwrap();
-                            ButtonState {
-                                climb: guard.climb,
-                                zero: guard.zero,
-                                intake: guard.intake,
-                                high: guard.high,
-                                mid: guard.mid,
-                                low: guard.low,
-                                coral: guard.coral,
-                                intake_alga: guard.intake_alga,
-                                drop_alga: guard.drop_alga,
-                            }
+                    while Lifecycle::load(&lifecycle) == Lifecycle::Running {
+                        // Whatever changed since the last wakeup goes out in one
+                        // report, and never halfway through a batch
+                        let Some((buttons, axes)) = pad.snapshot() else {
+                            thread::park_timeout(Duration::from_millis(10));
+                            continue;
                         };
-                        
-                        // Check if the state changed
-                        if current_state.climb != last_state.climb
-                           || current_state.zero != last_state.zero
-                           || current_state.intake != last_state.intake
-                           || current_state.high != last_state.high
-                           || current_state.mid != last_state.mid
-                           || current_state.low != last_state.low
-                           || current_state.coral != last_state.coral
-                           || current_state.intake_alga != last_state.intake_alga
-                           || current_state.drop_alga != last_state.drop_alga {
-                            
-                            // Update the controller state
-                            // Create a new buttons object for each button press
-                            let mut button_value = 0u16;
-                            
-                            // Use the constants correctly
-                            if current_state.climb {
-                                button_value |= XButtons::START;
-                            }
-                            if current_state.zero {
-                                button_value |= XButtons::BACK;
-                            }
-                            if current_state.intake {
-                                button_value |= XButtons::RIGHT;
-                            }
-                            if current_state.high {
-                                button_value |= XButtons::UP;
-                            }
-                            if current_state.mid {
-                                button_value |= XButtons::LEFT;
-                            }
-                            if current_state.low {
-                                button_value |= XButtons::DOWN;
-                            }
-                            if current_state.coral {
-                                button_value |= XButtons::B;
-                            }
-                            if current_state.intake_alga {
-                                button_value |= XButtons::LB;
-                            }
-                            if current_state.drop_alga {
-                                button_value |= XButtons::RB;
-                            }
-                            
-                            let buttons = vigem_client::XButtons(button_value);
-                            
+
+                        // Check if the state changed (or a resend was requested)
+                        if force_update.swap(false, Ordering::SeqCst) || buttons != last_buttons || axes != last_axes {
+                            // XInput sticks are up positive, the Driver Station flips them back
+                            let [left_x, left_y, left_trigger, right_trigger, right_x, right_y] = axes;
                             let gamepad = vigem_client::XGamepad {
-                                buttons,
-                                ..Default::default()
+                                buttons: XButtons(buttons),
+                                left_trigger: trigger_value(left_trigger),
+                                right_trigger: trigger_value(right_trigger),
+                                thumb_lx: stick_value(left_x),
+                                thumb_ly: stick_value(-left_y),
+                                thumb_rx: stick_value(right_x),
+                                thumb_ry: stick_value(-right_y),
                             };
                             
-                            if let Ok(mut t) = target.lock() {
-                                if let Err(e) = t.update(&gamepad) {
-                                    godot_error!("Failed to update virtual controller: {}", e);
-                                }
+                            if buttons != last_buttons {
+                                log_debug!("Virtual controller buttons: {:?}", pressed_actions(buttons));
+                            }
+                            log_trace!("Virtual controller update: buttons {:#06x}, axes {:?}", buttons, axes);
+
+                            if let Err(e) = lock_recovering(&*target, "ViGEm target").update(&gamepad) {
+                                log_error!("Failed to update virtual controller: {}", e);
                             }
                             
-                            last_state = current_state;
+                            last_buttons = buttons;
+                            last_axes = axes;
                         }
                         
-                        // Sleep for a short time
-                        thread::sleep(Duration::from_millis(10));
+                        // Sleep for a short time, shutdown() unparks it early
+                        thread::park_timeout(Duration::from_millis(10));
                     }
                 }));
                 
-                return true;
-            }
-            Err(e) => {
-                godot_error!("Failed to connect to ViGEm client: {}", e);
-                return false;
+                Ok(())
             }
+            Err(e) => Err(format!("Failed to connect to ViGEm client: {}", e)),
         }
     }
     
+    /// Track button state without a ViGEm device.
+    pub fn initialize_state_only(&mut self) {
+        self.stop_thread(Lifecycle::Idle);
+        self.set_lifecycle(Lifecycle::Running);
+    }
+
+    /// Stop the control thread and unplug the pad, waiting at most
+    /// SHUTDOWN_JOIN_TIMEOUT for the thread. Inputs are refused from here
+    /// on, until the next initialize. Drop goes through here too.
     pub fn shutdown(&mut self) {
-        if self.running.load(Ordering::SeqCst) {
-            self.running.store(false, Ordering::SeqCst);
-            
-            if let Some(handle) = self.control_thread.take() {
-                let _ = handle.join();
+        self.stop_thread(Lifecycle::ShuttingDown);
+        self.set_lifecycle(Lifecycle::ShutDown);
+    }
+
+    fn set_lifecycle(&self, lifecycle: Lifecycle) {
+        self.lifecycle.store(lifecycle as u8, Ordering::SeqCst);
+    }
+
+    /// Move to `lifecycle`, which stops the control thread, then drop the
+    /// target once the thread is gone.
+    fn stop_thread(&mut self, lifecycle: Lifecycle) {
+        self.set_lifecycle(lifecycle);
+
+        if let Some(handle) = self.control_thread.take() {
+            if !join_within(handle, SHUTDOWN_JOIN_TIMEOUT) {
+                log_error!(
+                    "Virtual controller thread didn't stop within {} ms, leaving it behind",
+                    SHUTDOWN_JOIN_TIMEOUT.as_millis()
+                );
+                // The thread keeps its own handle to the target, so unplug it
+                // here if it isn't in the middle of using it
+                if let Some(target) = &self.target {
+                    match target.try_lock() {
+                        Ok(mut target) => {
+                            if let Err(e) = target.unplug() {
+                                log_error!("Failed to unplug virtual controller: {}", e);
+                            }
+                        }
+                        Err(_) => log_error!("Virtual controller target is busy, it stays plugged in until exit"),
+                    }
+                }
             }
-            
-            self.target = None;
-            self.client = None;
         }
+
+        self.target = None;
+        self.client = None;
+        self.user_index = None;
     }
     
-    pub fn set_button(&self, button: &str, pressed: bool) {
-        if let Ok(mut state) = self.button_state.lock() {
-            match button {
-                "climb" => state.climb = pressed,
-                "zero" => state.zero = pressed,
-                "intake" => state.intake = pressed,
-                "high" => state.high = pressed,
-                "mid" => state.mid = pressed,
-                "low" => state.low = pressed,
-                "coral" => state.coral = pressed,
-                "intake_alga" => state.intake_alga = pressed,
-                "drop_alga" => state.drop_alga = pressed,
-                _ => godot_warn!("Unknown button: {}", button),
+    pub fn is_running(&self) -> bool {
+        Lifecycle::load(&self.lifecycle) == Lifecycle::Running
+    }
+
+    fn check_input(&self) -> Result<(), InputError> {
+        if Lifecycle::load(&self.lifecycle).takes_input() {
+            Ok(())
+        } else {
+            Err(InputError::ShuttingDown)
+        }
+    }
+
+    /// Whether the control thread is still sending updates. A panic in it
+    /// leaves the controller Running, so this looks at the thread itself. State-only
+    /// controllers have no thread and are alive while running.
+    pub fn thread_alive(&self) -> bool {
+        match &self.control_thread {
+            Some(handle) => !handle.is_finished(),
+            None => self.target.is_none() && self.is_running(),
+        }
+    }
+
+    /// Plug the pad in again with a fresh control thread. The button state is
+    /// kept, and sent in full as soon as the thread is up.
+    pub fn restart(&mut self) -> Result<(), String> {
+        self.initialize()?;
+        self.force_update.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn pressed_buttons(&self) -> Vec<&'static str> {
+        pressed_actions(self.pad.buttons())
+    }
+    
+    pub fn axes(&self) -> [f64; AXIS_COUNT] {
+        self.pad.axes()
+    }
+
+    /// Set an axis by name, clamped to its range.
+    pub fn set_axis(&self, axis: &str, value: f64) -> Result<(), InputError> {
+        self.check_input()?;
+        let Some(index) = AXIS_NAMES.iter().position(|name| *name == axis) else {
+            return Err(InputError::UnknownAxis);
+        };
+        let value = if axis.ends_with("_trigger") {
+            value.clamp(0.0, 1.0)
+        } else {
+            value.clamp(-1.0, 1.0)
+        };
+
+        self.pad.axes[index].store(value.to_bits(), Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Buttons and axes held through every handle but `owned`'s.
+    fn held_by_others(&self, owned: &Arc<Owned>) -> (u16, u8) {
+        self.owners
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|other| !Arc::ptr_eq(other, owned))
+            .fold((0, 0), |(buttons, axes), other| {
+                (buttons | other.buttons.load(Ordering::SeqCst), axes | other.axes.load(Ordering::SeqCst))
+            })
+    }
+
+    /// Change `owned`'s buttons, leaving down any bit another handle holds.
+    fn update_owned_buttons(&self, owned: &Arc<Owned>, clear: u16, set: u16) {
+        let _ = owned
+            .buttons
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |bits| Some((bits & !clear) | set));
+        let (others, _) = self.held_by_others(owned);
+        self.pad.update_buttons(clear & !others, set);
+    }
+
+    /// Let go of whatever `owned` holds that no other handle does, and make
+    /// sure a report goes out even if the pad was already at rest.
+    fn release_owned(&self, owned: &Arc<Owned>) {
+        let (buttons, axes) = self.held_by_others(owned);
+        self.pad.update_buttons(owned.buttons.swap(0, Ordering::SeqCst) & !buttons, 0);
+        let zeroed = owned.axes.swap(0, Ordering::SeqCst) & !axes;
+        for (index, axis) in self.pad.axes.iter().enumerate() {
+            if zeroed & (1 << index) != 0 {
+                axis.store(0.0f64.to_bits(), Ordering::SeqCst);
             }
         }
+        self.force_update.store(true, Ordering::SeqCst);
+    }
+
+    /// Hold back reports until the returned guard drops, so every change made
+    /// in between goes out together in one, e.g. releasing several buttons on
+    /// a mapping switch. Batches nest.
+    pub fn batch(&self) -> Batch {
+        self.pad.batches.fetch_add(1, Ordering::SeqCst);
+        Batch { pad: self.pad.clone() }
+    }
+
+    pub fn spare_buttons(&self) -> Vec<&'static str> {
+        let bits = self.pad.buttons();
+        SPARE_BUTTONS
+            .into_iter()
+            .filter(|button| bits & xinput_bit(button) != 0)
+            .collect()
+    }
+
+}
+
+/// An open batch of pad changes, see `VirtualController::batch()`. Ends when
+/// dropped, however the scope that holds it is left.
+#[must_use = "the batch ends as soon as the guard is dropped"]
+pub struct Batch {
+    pad: Arc<PadState>,
+}
+
+impl Drop for Batch {
+    fn drop(&mut self) {
+        self.pad.commits.fetch_add(1, Ordering::SeqCst);
+        self.pad.batches.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The bits to clear and to set for `changes` of actions, skipping unknown ones.
+fn button_changes(changes: &[(&str, bool)]) -> (u16, u16) {
+    let (mut clear, mut set) = (0, 0);
+    for &(button, pressed) in changes {
+        let bit = action_bit(button);
+        if bit == 0 {
+            log_warn!("Unknown button: {}", button);
+        } else if pressed {
+            set |= bit;
+        } else {
+            clear |= bit;
+        }
+    }
+    (clear, set)
+}
+
+/// XInput bits of the SPARE_BUTTONS among `buttons`, others are ignored.
+fn spare_bits(buttons: &[&str]) -> u16 {
+    buttons
+        .iter()
+        .filter(|button| SPARE_BUTTONS.contains(button))
+        .map(|button| xinput_bit(button))
+        .fold(0, |bits, bit| bits | bit)
+}
+
+/// XInput bit an action is sent as, per BUTTON_MAPPING, 0 if unknown.
+fn action_bit(action: &str) -> u16 {
+    BUTTON_MAPPING
+        .iter()
+        .find(|(name, _)| *name == action)
+        .map_or(0, |(_, xbox_button)| xinput_bit(xbox_button))
+}
+
+/// BUTTON_MAPPING actions whose bits are set in `bits`.
+fn pressed_actions(bits: u16) -> Vec<&'static str> {
+    BUTTON_MAPPING
+        .iter()
+        .filter(|(_, xbox_button)| bits & xinput_bit(xbox_button) != 0)
+        .map(|(action, _)| *action)
+        .collect()
+}
+
+/// XInput button bits for a set of pressed actions, as the pad reports them.
+pub fn button_bitmask(pressed: &[&str]) -> u16 {
+    pressed.iter().map(|action| action_bit(action)).fold(0, |bits, bit| bits | bit)
+}
+
+/// XInput bit of an Xbox button by name, 0 if unknown.
+pub fn xinput_bit(xbox_button: &str) -> u16 {
+    match xbox_button {
+        "START" => XButtons::START,
+        "BACK" => XButtons::BACK,
+        "DPAD_RIGHT" => XButtons::RIGHT,
+        "DPAD_UP" => XButtons::UP,
+        "DPAD_LEFT" => XButtons::LEFT,
+        "DPAD_DOWN" => XButtons::DOWN,
+        "A" => XButtons::A,
+        "B" => XButtons::B,
+        "X" => XButtons::X,
+        "Y" => XButtons::Y,
+        "LB" => XButtons::LB,
+        "RB" => XButtons::RB,
+        "LS" => XButtons::LTHUMB,
+        "RS" => XButtons::RTHUMB,
+        _ => 0,
     }
 }
 
+/// Lock `mutex` even if a thread panicked while holding it. Everything kept
+/// behind these locks is plain state that is valid after any single write,
+/// so carrying on beats dropping every input for the rest of the session.
+/// The poison is cleared, so this logs once per panic.
+fn lock_recovering<'a, T>(mutex: &'a Mutex<T>, what: &str) -> MutexGuard<'a, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        log_error!("Recovered the {} lock after a thread panicked while holding it", what);
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+fn stick_value(value: f64) -> i16 {
+    (value.clamp(-1.0, 1.0) * i16::MAX as f64).round() as i16
+}
+
+fn trigger_value(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * u8::MAX as f64).round() as u8
+}
+
 impl Drop for VirtualController {
     fn drop(&mut self) {
         self.shutdown();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn concurrent_set_and_clear_keep_other_bits() {
+        let pad = Arc::new(PadState::default());
+        let bits = [XButtons::A, XButtons::B, XButtons::X, XButtons::Y, XButtons::START];
+
+        let workers: Vec<_> = bits
+            .into_iter()
+            .map(|bit| {
+                let pad = pad.clone();
+                thread::spawn(move || {
+                    for _ in 0..10_000 {
+                        pad.update_buttons(0, bit);
+                        pad.update_buttons(bit, 0);
+                    }
+                    // Every other bit ends up held
+                    if bit.trailing_zeros() % 2 == 1 {
+                        pad.update_buttons(0, bit);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let expected = bits
+            .into_iter()
+            .filter(|bit| bit.trailing_zeros() % 2 == 1)
+            .fold(0, |bits, bit| bits | bit);
+        assert_eq!(pad.buttons(), expected);
+    }
+
+    #[test]
+    fn concurrent_actions_from_several_threads() {
+        let controller = SharedController::state_only();
+
+        let workers: Vec<_> = BUTTON_MAPPING
+            .iter()
+            .enumerate()
+            .map(|(index, (action, _))| {
+                let controller = controller.clone();
+                thread::spawn(move || {
+                    for _ in 0..1_000 {
+                        controller.set_button(action, true).unwrap();
+                        controller.set_button(action, false).unwrap();
+                    }
+                    controller.set_button(action, index % 2 == 0).unwrap();
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let expected: Vec<&str> = BUTTON_MAPPING
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| index % 2 == 0)
+            .map(|(_, (action, _))| *action)
+            .collect();
+        assert_eq!(controller.pressed_buttons(), expected);
+        assert!(controller.spare_buttons().is_empty());
+    }
+
+    #[test]
+    fn nothing_written_after_shutdown_starts() {
+        let controller = SharedController::state_only();
+        let done = Arc::new(AtomicBool::new(false));
+
+        let writer = {
+            let controller = controller.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                let mut accepted = 0;
+                while !done.load(Ordering::SeqCst) {
+                    if controller.set_button("high", true).is_ok() {
+                        accepted += 1;
+                    }
+                    let _ = controller.set_axis("left_x", 1.0);
+                    let _ = controller.set_spare_buttons(&["A"]);
+                }
+                accepted
+            })
+        };
+
+        for _ in 0..200 {
+            controller.lock().shutdown();
+            controller.neutralize();
+            // Give the writer plenty of chances to sneak something in
+            thread::sleep(Duration::from_millis(1));
+            assert!(controller.pressed_buttons().is_empty());
+            assert!(controller.spare_buttons().is_empty());
+            assert_eq!(controller.axes(), [0.0; AXIS_COUNT]);
+            assert_eq!(controller.set_button("high", true), Err(InputError::ShuttingDown));
+            assert_eq!(controller.set_axis("left_x", 1.0), Err(InputError::ShuttingDown));
+
+            controller.lock().initialize_state_only();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        done.store(true, Ordering::SeqCst);
+        assert!(writer.join().unwrap() > 0, "the writer never got a press in while running");
+    }
+
+    #[test]
+    fn a_node_only_lets_go_of_its_own_buttons() {
+        let first = SharedController::state_only();
+        let second = SharedController::join(first.0.controller.clone());
+        first.set_button("intake", true).unwrap();
+        first.set_axis("left_x", 0.5).unwrap();
+        second.set_buttons(&[("intake", true), ("climb", true)]).unwrap();
+        second.set_axis("right_y", -1.0).unwrap();
+
+        first.neutralize();
+        assert_eq!(first.pressed_buttons(), vec!["climb", "intake"], "neutralize let go of another node's buttons");
+        assert_eq!(first.axes(), [0.0, 0.0, 0.0, 0.0, 0.0, -1.0]);
+
+        // Held by both, released by one: still down
+        first.set_button("climb", true).unwrap();
+        first.set_button("climb", false).unwrap();
+        assert_eq!(second.pressed_buttons(), vec!["climb", "intake"]);
+
+        second.set_button("intake", false).unwrap();
+        assert_eq!(second.pressed_buttons(), vec!["climb"]);
+    }
+
+    #[test]
+    fn dropping_the_last_clone_lets_go() {
+        let first = SharedController::state_only();
+        let second = SharedController::join(first.0.controller.clone());
+        first.set_button("coral", true).unwrap();
+        second.set_spare_buttons(&["A"]).unwrap();
+        second.set_button("zero", true).unwrap();
+
+        let clone = second.clone();
+        drop(second);
+        assert_eq!(first.pressed_buttons(), vec!["zero", "coral"], "a clone dropping let go for the node");
+
+        drop(clone);
+        assert_eq!(first.pressed_buttons(), vec!["coral"]);
+        assert!(first.spare_buttons().is_empty());
+        assert_eq!(first.lock().owners.iter().filter(|owner| owner.strong_count() > 0).count(), 1);
+    }
+
+    #[test]
+    fn release_after_neutralize_reaches_the_pad() {
+        let controller = SharedController::state_only();
+        controller.set_button("intake", true).unwrap();
+
+        // Neutralizing clears the pad, a late release still goes through
+        controller.neutralize();
+        assert_eq!(controller.set_button("intake", false), Ok(()));
+        assert!(controller.pressed_buttons().is_empty());
+
+        controller.set_button("intake", true).unwrap();
+        controller.set_button("intake", false).unwrap();
+        assert!(controller.pressed_buttons().is_empty(), "intake stuck after the neutralize");
+    }
+
+    #[test]
+    fn nothing_is_sent_while_a_batch_is_open() {
+        let controller = SharedController::state_only();
+        let pad = controller.lock().pad.clone();
+        let high = action_bit("high");
+        let low = action_bit("low");
+
+        let batch = controller.batch();
+        controller.set_button("high", true).unwrap();
+        assert_eq!(pad.snapshot(), None);
+        {
+            let _inner = controller.batch();
+            controller.set_button("low", true).unwrap();
+        }
+        assert_eq!(pad.snapshot(), None, "an inner batch ended the outer one");
+        drop(batch);
+
+        assert_eq!(pad.snapshot(), Some((high | low, [0.0; AXIS_COUNT])));
+    }
+
+    #[test]
+    fn a_batch_left_early_still_ends() {
+        let controller = SharedController::state_only();
+        let pad = controller.lock().pad.clone();
+        let press = |fail: bool| -> Result<(), InputError> {
+            let _batch = controller.batch();
+            controller.set_button("mid", true)?;
+            if fail {
+                return Err(InputError::UnknownAxis);
+            }
+            controller.set_axis("left_y", 0.5)
+        };
+        assert!(press(true).is_err());
+        assert!(pad.snapshot().is_some());
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _batch = controller.batch();
+            panic!("caller failed halfway through");
+        }));
+        assert!(panicked.is_err());
+        assert!(pad.snapshot().is_some(), "reports frozen after a panic in a batch");
+    }
+
+    #[test]
+    fn no_intermediate_state_is_ever_read() {
+        let controller = SharedController::state_only();
+        let high = action_bit("high");
+        let low = action_bit("low");
+        controller.set_button("high", true).unwrap();
+        let done = Arc::new(AtomicBool::new(false));
+
+        // Reads the way the control thread does
+        let reader = {
+            let pad = controller.lock().pad.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                // Always reads at least once, even if the writes finish first
+                let mut seen = Vec::new();
+                loop {
+                    let finished = done.load(Ordering::SeqCst);
+                    if let Some((buttons, axes)) = pad.snapshot() {
+                        seen.push((buttons, axes[0]));
+                    }
+                    if finished {
+                        break;
+                    }
+                }
+                seen
+            })
+        };
+
+        // Swap high and low in two separate writes, plus an axis, each time
+        for round in 0..20_000 {
+            let (from, to, x) = if round % 2 == 0 { ("high", "low", 1.0) } else { ("low", "high", 0.0) };
+            let _batch = controller.batch();
+            controller.set_button(from, false).unwrap();
+            controller.set_button(to, true).unwrap();
+            controller.set_axis("left_x", x).unwrap();
+        }
+
+        done.store(true, Ordering::SeqCst);
+        let seen = reader.join().unwrap();
+        assert!(!seen.is_empty());
+        for (buttons, x) in seen {
+            assert!(
+                (buttons, x) == (high, 0.0) || (buttons, x) == (low, 1.0),
+                "sent an intermediate state: buttons {:#06x}, left_x {}",
+                buttons,
+                x
+            );
+        }
+    }
+
+    #[test]
+    fn controller_takes_input_after_a_panic_while_locked() {
+        let controller = SharedController::state_only();
+        let holder = controller.clone();
+        let result = thread::spawn(move || {
+            let _guard = holder.lock();
+            panic!("panicked while holding the controller lock");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(controller.0.controller.is_poisoned());
+
+        assert_eq!(controller.set_button("high", true), Ok(()));
+        assert_eq!(controller.pressed_buttons(), vec!["high"]);
+        assert!(!controller.0.controller.is_poisoned(), "the lock stays poisoned after recovering");
+    }
+
+    #[test]
+    fn spare_buttons_leave_actions_alone() {
+        let controller = SharedController::state_only();
+        controller.set_button("coral", true).unwrap();
+        controller.set_spare_buttons(&["A", "Y"]).unwrap();
+        controller.set_spare_buttons(&["X"]).unwrap();
+
+        assert_eq!(controller.pressed_buttons(), vec!["coral"]);
+        assert_eq!(controller.spare_buttons(), vec!["X"]);
+    }
+}